@@ -0,0 +1,110 @@
+use crate::market_data_service::get_candles::{CandleInterval, GetCandlesResponse, HistoricCandle};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candle {
+    pub time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: i64,
+}
+
+impl Candle {
+    /// Converts an API candle, returns None if the candle time can't be parsed
+    pub fn from_historic(candle: &HistoricCandle) -> Option<Self> {
+        Some(Self {
+            time: DateTime::parse_from_rfc3339(&candle.time).ok()?.with_timezone(&Utc),
+            open: candle.open.to_f64(),
+            high: candle.high.to_f64(),
+            low: candle.low.to_f64(),
+            close: candle.close.to_f64(),
+            volume: candle.volume.parse().unwrap_or(0),
+        })
+    }
+}
+
+/// Candles stored on disk together with the period they cover
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredCandles {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    candles: Vec<Candle>,
+}
+
+/// Local storage of downloaded candles, one JSON file per instrument and interval
+pub struct CandleStore {
+    dir: PathBuf,
+}
+
+impl CandleStore {
+    pub fn new(dir: &str) -> Self {
+        Self { dir: PathBuf::from(dir) }
+    }
+
+    fn path(&self, instrument_uid: &str, interval: CandleInterval) -> PathBuf {
+        self.dir.join(format!("{}_{}.json", instrument_uid, interval.as_str()))
+    }
+
+    fn load(&self, instrument_uid: &str, interval: CandleInterval) -> Result<Option<StoredCandles>, Box<dyn Error>> {
+        let path = self.path(instrument_uid, interval);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&data)?))
+    }
+
+    fn save(&self, instrument_uid: &str, interval: CandleInterval, stored: &StoredCandles) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(instrument_uid, interval), serde_json::to_string(stored)?)?;
+        Ok(())
+    }
+
+    /// Returns completed candles for the period, downloading only what is missing locally
+    pub async fn load_or_download(
+        &self,
+        client: &reqwest::Client,
+        token: &str,
+        instrument_uid: &str,
+        interval: CandleInterval,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Candle>, Box<dyn Error>> {
+        let (covered_from, download_from, mut candles) = match self.load(instrument_uid, interval)? {
+            Some(stored) if stored.from <= from => (stored.from, stored.to, stored.candles),
+            _ => (from, from, Vec::new()),
+        };
+
+        if download_from < to {
+            info!("Downloading {} candles for {} from {} to {}", interval.as_str(), instrument_uid, download_from, to);
+            let downloaded = GetCandlesResponse::get_candles_range(client, token, instrument_uid, download_from, to, interval).await?;
+            candles.extend(
+                downloaded
+                    .iter()
+                    .filter(|candle| candle.is_complete)
+                    .filter_map(Candle::from_historic),
+            );
+            candles.sort_by_key(|candle| candle.time);
+            candles.dedup_by_key(|candle| candle.time);
+
+            // Resume the next download from the last completed candle
+            self.save(instrument_uid, interval, &StoredCandles {
+                from: covered_from,
+                to: candles.last().map_or(covered_from, |candle| candle.time),
+                candles: candles.clone(),
+            })?;
+        }
+
+        Ok(candles
+            .into_iter()
+            .filter(|candle| candle.time >= from && candle.time < to)
+            .collect())
+    }
+}
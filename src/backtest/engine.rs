@@ -0,0 +1,276 @@
+use crate::backtest::candles::Candle;
+use crate::backtest::metrics::Metrics;
+use crate::backtest::strategy::BacktestStrategy;
+use crate::bot::signal::Signal;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trade {
+    pub entry_time: DateTime<Utc>,
+    pub entry_price: f64,
+    pub exit_time: DateTime<Utc>,
+    pub exit_price: f64,
+    pub quantity: i64,
+    pub commission: f64,
+    pub pnl: f64,
+    pub return_percentage: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityPoint {
+    pub time: DateTime<Utc>,
+    pub equity: f64,
+    pub in_market: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BacktestResult {
+    pub instrument: String,
+    pub strategy: String,
+    pub trades: Vec<Trade>,
+    pub equity_curve: Vec<EquityPoint>,
+    pub metrics: Metrics,
+}
+
+/// Open long position
+struct Position {
+    entry_time: DateTime<Utc>,
+    entry_price: f64,
+    quantity: i64,
+    entry_commission: f64,
+}
+
+/// Simulates a long-only strategy: Buy opens a position with all available cash,
+/// Sell closes it. Orders are filled at the open of the candle following the signal.
+pub struct Backtester {
+    initial_capital: f64,
+    commission_percentage: f64,
+    slippage_percentage: f64,
+}
+
+impl Backtester {
+    pub fn new(initial_capital: f64, commission_percentage: f64, slippage_percentage: f64) -> Self {
+        Self {
+            initial_capital,
+            commission_percentage,
+            slippage_percentage,
+        }
+    }
+
+    pub fn run(&self, instrument: &str, strategy: &mut dyn BacktestStrategy, candles: &[Candle]) -> BacktestResult {
+        self.run_with_warm_up(instrument, strategy, &[], candles)
+    }
+
+    /// Feeds `warm_up` candles to the strategy without trading, then runs the backtest on `candles`
+    pub fn run_with_warm_up(
+        &self,
+        instrument: &str,
+        strategy: &mut dyn BacktestStrategy,
+        warm_up: &[Candle],
+        candles: &[Candle],
+    ) -> BacktestResult {
+        for candle in warm_up {
+            strategy.on_candle(candle);
+        }
+
+        let commission_rate = self.commission_percentage / 100.0;
+        let slippage_rate = self.slippage_percentage / 100.0;
+
+        let mut cash = self.initial_capital;
+        let mut position: Option<Position> = None;
+        let mut pending = Signal::Hold;
+        let mut trades = Vec::new();
+        let mut equity_curve = Vec::with_capacity(candles.len());
+
+        for candle in candles {
+            // Execute the signal received at the previous close
+            match pending {
+                Signal::Buy if position.is_none() => {
+                    let price = candle.open * (1.0 + slippage_rate);
+                    let quantity = (cash / (price * (1.0 + commission_rate))).floor() as i64;
+                    if quantity > 0 {
+                        let commission = price * quantity as f64 * commission_rate;
+                        cash -= price * quantity as f64 + commission;
+                        position = Some(Position {
+                            entry_time: candle.time,
+                            entry_price: price,
+                            quantity,
+                            entry_commission: commission,
+                        });
+                    }
+                }
+                Signal::Sell => {
+                    if let Some(open) = position.take() {
+                        let price = candle.open * (1.0 - slippage_rate);
+                        cash += self.close_position(open, candle.time, price, &mut trades);
+                    }
+                }
+                _ => {}
+            }
+
+            pending = strategy.on_candle(candle);
+
+            let market_value = position.as_ref().map_or(0.0, |p| p.quantity as f64 * candle.close);
+            equity_curve.push(EquityPoint {
+                time: candle.time,
+                equity: cash + market_value,
+                in_market: position.is_some(),
+            });
+        }
+
+        // Close the remaining position at the last close
+        if let (Some(open), Some(last)) = (position.take(), candles.last()) {
+            let price = last.close * (1.0 - slippage_rate);
+            cash += self.close_position(open, last.time, price, &mut trades);
+            if let Some(point) = equity_curve.last_mut() {
+                point.equity = cash;
+            }
+        }
+
+        let metrics = Metrics::calculate(self.initial_capital, &equity_curve, &trades);
+
+        BacktestResult {
+            instrument: instrument.to_string(),
+            strategy: strategy.name(),
+            trades,
+            equity_curve,
+            metrics,
+        }
+    }
+
+    /// Records the trade and returns the cash received for the position
+    fn close_position(&self, position: Position, time: DateTime<Utc>, price: f64, trades: &mut Vec<Trade>) -> f64 {
+        let proceeds = price * position.quantity as f64;
+        let commission = proceeds * self.commission_percentage / 100.0;
+        let cost = position.entry_price * position.quantity as f64;
+        let pnl = proceeds - commission - cost - position.entry_commission;
+
+        trades.push(Trade {
+            entry_time: position.entry_time,
+            entry_price: position.entry_price,
+            exit_time: time,
+            exit_price: price,
+            quantity: position.quantity,
+            commission: position.entry_commission + commission,
+            pnl,
+            return_percentage: pnl / (cost + position.entry_commission) * 100.0,
+        });
+
+        proceeds - commission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Returns the given signal at each candle close
+    struct Scripted {
+        signals: Vec<Signal>,
+        index: usize,
+    }
+
+    impl BacktestStrategy for Scripted {
+        fn name(&self) -> String {
+            "scripted".to_string()
+        }
+
+        fn on_candle(&mut self, _candle: &Candle) -> Signal {
+            let signal = self.signals.get(self.index).copied().unwrap_or(Signal::Hold);
+            self.index += 1;
+            signal
+        }
+    }
+
+    fn candles(prices: &[(f64, f64)]) -> Vec<Candle> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, &(open, close))| Candle {
+                time: Utc.with_ymd_and_hms(2024, 1, 1 + i as u32, 0, 0, 0).unwrap(),
+                open,
+                high: open.max(close),
+                low: open.min(close),
+                close,
+                volume: 100,
+            })
+            .collect()
+    }
+
+    fn run(backtester: &Backtester, signals: Vec<Signal>, prices: &[(f64, f64)]) -> BacktestResult {
+        backtester.run("TEST", &mut Scripted { signals, index: 0 }, &candles(prices))
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn fills_at_next_open_without_costs() {
+        let backtester = Backtester::new(1000.0, 0.0, 0.0);
+        let signals = vec![Signal::Buy, Signal::Hold, Signal::Sell, Signal::Hold];
+        let result = run(&backtester, signals, &[(100.0, 100.0), (100.0, 110.0), (120.0, 120.0), (125.0, 130.0)]);
+
+        assert_eq!(result.trades.len(), 1);
+        let trade = &result.trades[0];
+        assert_close(trade.entry_price, 100.0);
+        assert_close(trade.exit_price, 125.0);
+        assert_eq!(trade.quantity, 10);
+        assert_close(trade.pnl, 250.0);
+        assert_close(trade.return_percentage, 25.0);
+
+        let equity: Vec<f64> = result.equity_curve.iter().map(|p| p.equity).collect();
+        assert_eq!(equity, vec![1000.0, 1100.0, 1200.0, 1250.0]);
+    }
+
+    #[test]
+    fn applies_commission_and_slippage() {
+        let backtester = Backtester::new(1000.0, 1.0, 1.0);
+        let signals = vec![Signal::Buy, Signal::Hold, Signal::Sell, Signal::Hold];
+        let result = run(&backtester, signals, &[(100.0, 100.0), (100.0, 110.0), (120.0, 120.0), (130.0, 130.0)]);
+
+        // Buy at 100 + 1% = 101, floor(1000 / (101 * 1.01)) = 9 shares, commission 9.09
+        // Sell at 130 - 1% = 128.7, proceeds 1158.3, commission 11.583
+        let trade = &result.trades[0];
+        assert_close(trade.entry_price, 101.0);
+        assert_eq!(trade.quantity, 9);
+        assert_close(trade.exit_price, 128.7);
+        assert_close(trade.commission, 9.09 + 11.583);
+        assert_close(trade.pnl, 1158.3 - 11.583 - 909.0 - 9.09);
+        assert_close(trade.return_percentage, trade.pnl / 918.09 * 100.0);
+
+        let cash = 1000.0 - 909.0 - 9.09;
+        assert_close(result.equity_curve[1].equity, cash + 9.0 * 110.0);
+        assert_close(result.equity_curve[3].equity, cash + 1158.3 - 11.583);
+    }
+
+    #[test]
+    fn closes_open_position_at_last_close() {
+        let backtester = Backtester::new(1000.0, 0.0, 1.0);
+        let result = run(&backtester, vec![Signal::Buy], &[(100.0, 100.0), (100.0, 105.0), (110.0, 120.0)]);
+
+        let trade = &result.trades[0];
+        assert_close(trade.entry_price, 101.0);
+        assert_eq!(trade.quantity, 9);
+        assert_close(trade.exit_price, 120.0 * 0.99);
+        assert_close(result.equity_curve.last().unwrap().equity, 1000.0 - 909.0 + 9.0 * 118.8);
+    }
+
+    #[test]
+    fn ignores_sell_without_position_and_repeated_buy() {
+        let backtester = Backtester::new(1000.0, 0.0, 0.0);
+        let signals = vec![Signal::Sell, Signal::Buy, Signal::Buy, Signal::Sell, Signal::Hold];
+        let result = run(
+            &backtester,
+            signals,
+            &[(100.0, 100.0), (100.0, 100.0), (100.0, 100.0), (200.0, 200.0), (50.0, 50.0)],
+        );
+
+        assert_eq!(result.trades.len(), 1);
+        assert_close(result.trades[0].entry_price, 100.0);
+        assert_close(result.trades[0].exit_price, 50.0);
+        assert_eq!(result.trades[0].quantity, 10);
+    }
+}
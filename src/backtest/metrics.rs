@@ -0,0 +1,213 @@
+use crate::backtest::engine::{EquityPoint, Trade};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Metrics {
+    pub total_return_percentage: f64,
+    pub cagr_percentage: f64,
+    pub sharpe: f64,
+    pub sortino: f64,
+    pub max_drawdown_percentage: f64,
+    pub win_rate_percentage: f64,
+    /// Infinite when there are no losing trades, serialized to JSON as `null`
+    pub profit_factor: f64,
+    pub exposure_percentage: f64,
+    pub trades: usize,
+}
+
+impl Metrics {
+    pub fn calculate(initial_capital: f64, equity_curve: &[EquityPoint], trades: &[Trade]) -> Self {
+        let (Some(first), Some(last)) = (equity_curve.first(), equity_curve.last()) else {
+            return Self::default();
+        };
+
+        let final_equity = last.equity;
+        let years = (last.time - first.time).num_seconds() as f64 / (365.25 * 24.0 * 3600.0);
+
+        let returns: Vec<f64> = equity_curve
+            .windows(2)
+            .map(|w| if w[0].equity > 0.0 { w[1].equity / w[0].equity - 1.0 } else { 0.0 })
+            .collect();
+
+        // Annualize with the number of candles actually traded per year
+        let periods_per_year = if years > 0.0 { returns.len() as f64 / years } else { 0.0 };
+
+        let cagr = if years > 0.0 && initial_capital > 0.0 && final_equity > 0.0 {
+            ((final_equity / initial_capital).powf(1.0 / years) - 1.0) * 100.0
+        } else {
+            0.0
+        };
+
+        let gross_profit: f64 = trades.iter().filter(|t| t.pnl > 0.0).map(|t| t.pnl).sum();
+        let gross_loss: f64 = trades.iter().filter(|t| t.pnl < 0.0).map(|t| -t.pnl).sum();
+        let profit_factor = if gross_loss > 0.0 {
+            gross_profit / gross_loss
+        } else if gross_profit > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        };
+
+        let win_rate = if trades.is_empty() {
+            0.0
+        } else {
+            trades.iter().filter(|t| t.pnl > 0.0).count() as f64 / trades.len() as f64 * 100.0
+        };
+
+        Self {
+            total_return_percentage: (final_equity / initial_capital - 1.0) * 100.0,
+            cagr_percentage: cagr,
+            sharpe: sharpe_ratio(&returns, periods_per_year),
+            sortino: sortino_ratio(&returns, periods_per_year),
+            max_drawdown_percentage: max_drawdown(equity_curve),
+            win_rate_percentage: win_rate,
+            profit_factor,
+            exposure_percentage: equity_curve.iter().filter(|p| p.in_market).count() as f64
+                / equity_curve.len() as f64
+                * 100.0,
+            trades: trades.len(),
+        }
+    }
+
+    /// Prints metrics in a readable format
+    pub fn print(&self) {
+        println!("  Total return:   {:.2}%", self.total_return_percentage);
+        println!("  CAGR:           {:.2}%", self.cagr_percentage);
+        println!("  Sharpe:         {:.2}", self.sharpe);
+        println!("  Sortino:        {:.2}", self.sortino);
+        println!("  Max drawdown:   {:.2}%", self.max_drawdown_percentage);
+        println!("  Win rate:       {:.2}%", self.win_rate_percentage);
+        println!("  Profit factor:  {:.2}", self.profit_factor);
+        println!("  Exposure:       {:.2}%", self.exposure_percentage);
+        println!("  Trades:         {}", self.trades);
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+pub fn sharpe_ratio(returns: &[f64], periods_per_year: f64) -> f64 {
+    let avg = mean(returns);
+    let variance = mean(&returns.iter().map(|r| (r - avg).powi(2)).collect::<Vec<_>>());
+    let std_dev = variance.sqrt();
+    if std_dev == 0.0 {
+        return 0.0;
+    }
+    avg / std_dev * periods_per_year.sqrt()
+}
+
+pub fn sortino_ratio(returns: &[f64], periods_per_year: f64) -> f64 {
+    let avg = mean(returns);
+    let downside = mean(&returns.iter().map(|r| r.min(0.0).powi(2)).collect::<Vec<_>>()).sqrt();
+    if downside == 0.0 {
+        return 0.0;
+    }
+    avg / downside * periods_per_year.sqrt()
+}
+
+/// Maximum peak-to-trough decline of the equity curve in percent
+pub fn max_drawdown(equity_curve: &[EquityPoint]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut max_drawdown: f64 = 0.0;
+    for point in equity_curve {
+        peak = peak.max(point.equity);
+        if peak > 0.0 {
+            max_drawdown = max_drawdown.max((peak - point.equity) / peak * 100.0);
+        }
+    }
+    max_drawdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn curve(values: &[f64]) -> Vec<EquityPoint> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &equity)| EquityPoint {
+                time: Utc.with_ymd_and_hms(2024, 1, 1 + i as u32, 0, 0, 0).unwrap(),
+                equity,
+                in_market: i % 2 == 1,
+            })
+            .collect()
+    }
+
+    fn trades(pnls: &[f64]) -> Vec<Trade> {
+        let time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        pnls.iter()
+            .map(|&pnl| Trade {
+                entry_time: time,
+                entry_price: 100.0,
+                exit_time: time,
+                exit_price: 100.0 + pnl,
+                quantity: 1,
+                commission: 0.0,
+                pnl,
+                return_percentage: pnl,
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn max_drawdown_is_largest_peak_to_trough_decline() {
+        // 120 -> 90 is 25%, 130 -> 104 is 20%
+        assert_close(max_drawdown(&curve(&[100.0, 120.0, 90.0, 130.0, 104.0])), 25.0);
+        assert_close(max_drawdown(&curve(&[100.0, 110.0, 120.0])), 0.0);
+    }
+
+    #[test]
+    fn sharpe_uses_mean_over_standard_deviation() {
+        // Mean 0.01, standard deviation 0.01
+        assert_close(sharpe_ratio(&[0.02, 0.0], 252.0), 252f64.sqrt());
+        assert_close(sharpe_ratio(&[0.01, -0.01, 0.01, -0.01], 252.0), 0.0);
+        assert_close(sharpe_ratio(&[0.01, 0.01], 252.0), 0.0);
+    }
+
+    #[test]
+    fn sortino_uses_downside_deviation() {
+        // Mean 0.005, downside deviation sqrt((0 + 0.0001) / 2)
+        assert_close(sortino_ratio(&[0.02, -0.01], 4.0), 0.005 / 0.00005f64.sqrt() * 2.0);
+        assert_close(sortino_ratio(&[0.02, 0.01], 4.0), 0.0);
+    }
+
+    #[test]
+    fn profit_factor_and_win_rate_from_trades() {
+        let metrics = Metrics::calculate(100.0, &curve(&[100.0, 110.0]), &trades(&[30.0, -10.0, 20.0, -5.0]));
+        assert_close(metrics.profit_factor, 50.0 / 15.0);
+        assert_close(metrics.win_rate_percentage, 50.0);
+        assert_eq!(metrics.trades, 4);
+    }
+
+    #[test]
+    fn profit_factor_without_losses_or_trades() {
+        let equity = curve(&[100.0, 110.0]);
+        assert_eq!(Metrics::calculate(100.0, &equity, &trades(&[10.0])).profit_factor, f64::INFINITY);
+        assert_close(Metrics::calculate(100.0, &equity, &[]).profit_factor, 0.0);
+    }
+
+    #[test]
+    fn total_return_and_exposure() {
+        let metrics = Metrics::calculate(100.0, &curve(&[100.0, 120.0, 90.0, 150.0]), &[]);
+        assert_close(metrics.total_return_percentage, 50.0);
+        assert_close(metrics.exposure_percentage, 50.0);
+        assert_close(metrics.max_drawdown_percentage, 25.0);
+    }
+
+    #[test]
+    fn empty_equity_curve_has_default_metrics() {
+        let metrics = Metrics::calculate(100.0, &[], &[]);
+        assert_eq!(metrics.trades, 0);
+        assert_close(metrics.total_return_percentage, 0.0);
+    }
+}
@@ -0,0 +1,9 @@
+pub mod candles;
+pub mod engine;
+pub mod metrics;
+pub mod optimizer;
+pub mod portfolio;
+pub mod report;
+pub mod runner;
+pub mod strategy;
+pub mod walk_forward;
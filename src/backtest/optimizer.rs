@@ -0,0 +1,365 @@
+use crate::backtest::candles::Candle;
+use crate::backtest::engine::Backtester;
+use crate::backtest::metrics::Metrics;
+use crate::backtest::strategy::EmaCrossBacktest;
+use crate::config::{Objective, OptimizationConfig, SearchMethod, StrategyConfig};
+use crate::market_data_service::get_tech_analysis::IndicatorInterval;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashSet;
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParameterSet {
+    pub short_ema_length: i32,
+    pub long_ema_length: i32,
+    pub hysteresis_percentage: f64,
+    pub hysteresis_periods: u32,
+    pub interval: IndicatorInterval,
+}
+
+impl ParameterSet {
+    pub fn to_strategy_config(self) -> StrategyConfig {
+        StrategyConfig {
+            short_ema_length: self.short_ema_length,
+            long_ema_length: self.long_ema_length,
+            interval: self.interval,
+            hysteresis_percentage: self.hysteresis_percentage,
+            hysteresis_periods: self.hysteresis_periods,
+        }
+    }
+
+    fn key(&self) -> (i32, i32, u64, u32, IndicatorInterval) {
+        (
+            self.short_ema_length,
+            self.long_ema_length,
+            self.hysteresis_percentage.to_bits(),
+            self.hysteresis_periods,
+            self.interval,
+        )
+    }
+}
+
+impl std::fmt::Display for ParameterSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "EMA {}/{}, hysteresis {}% x{}, {:?}",
+            self.short_ema_length,
+            self.long_ema_length,
+            self.hysteresis_percentage,
+            self.hysteresis_periods,
+            self.interval
+        )
+    }
+}
+
+/// Candles of one instrument for one interval
+pub struct InstrumentCandles {
+    pub ticker: String,
+    pub interval: IndicatorInterval,
+    pub candles: Vec<Candle>,
+}
+
+/// Metrics of a parameter set averaged over all instruments
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub parameters: ParameterSet,
+    pub score: f64,
+    pub metrics: Metrics,
+    pub instruments: usize,
+}
+
+/// Builds the parameter sets to evaluate, skipping those where the short EMA is not shorter than the long one
+pub fn parameter_sets(config: &OptimizationConfig) -> Vec<ParameterSet> {
+    let mut grid = Vec::new();
+    for &interval in &config.intervals {
+        for &short_ema_length in &config.short_ema_lengths {
+            for &long_ema_length in &config.long_ema_lengths {
+                if short_ema_length >= long_ema_length {
+                    continue;
+                }
+                for &hysteresis_percentage in &config.hysteresis_percentages {
+                    for &hysteresis_periods in &config.hysteresis_periods {
+                        grid.push(ParameterSet {
+                            short_ema_length,
+                            long_ema_length,
+                            hysteresis_percentage,
+                            hysteresis_periods,
+                            interval,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    match config.method {
+        SearchMethod::Grid => grid,
+        SearchMethod::Random => {
+            let mut rng = match config.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            grid.shuffle(&mut rng);
+            grid.truncate(config.random_samples);
+            grid
+        }
+    }
+}
+
+fn objective_value(metrics: &Metrics, objective: Objective) -> f64 {
+    match objective {
+        Objective::Sharpe => metrics.sharpe,
+        Objective::Sortino => metrics.sortino,
+        Objective::Cagr => metrics.cagr_percentage,
+        Objective::TotalReturn => metrics.total_return_percentage,
+        // Infinite profit factor of strategies without losses would dominate the ranking
+        Objective::ProfitFactor => metrics.profit_factor.min(100.0),
+    }
+}
+
+/// Averages metrics over instruments
+fn average(metrics: &[Metrics]) -> Metrics {
+    let n = metrics.len().max(1) as f64;
+    let avg = |f: fn(&Metrics) -> f64| metrics.iter().map(f).sum::<f64>() / n;
+    Metrics {
+        total_return_percentage: avg(|m| m.total_return_percentage),
+        cagr_percentage: avg(|m| m.cagr_percentage),
+        sharpe: avg(|m| m.sharpe),
+        sortino: avg(|m| m.sortino),
+        max_drawdown_percentage: avg(|m| m.max_drawdown_percentage),
+        win_rate_percentage: avg(|m| m.win_rate_percentage),
+        profit_factor: avg(|m| m.profit_factor.min(100.0)),
+        exposure_percentage: avg(|m| m.exposure_percentage),
+        trades: metrics.iter().map(|m| m.trades).sum(),
+    }
+}
+
+/// Backtests a parameter set on every instrument with candles of its interval in the period.
+/// `split` selects the candles used for warm-up and for trading.
+pub fn evaluate<F>(
+    parameters: ParameterSet,
+    universe: &[InstrumentCandles],
+    backtester: &Backtester,
+    objective: Objective,
+    split: F,
+) -> Evaluation
+where
+    F: Fn(&[Candle]) -> (&[Candle], &[Candle]),
+{
+    let config = parameters.to_strategy_config();
+    let metrics: Vec<Metrics> = universe
+        .iter()
+        .filter(|instrument| instrument.interval == parameters.interval)
+        .filter_map(|instrument| {
+            let (warm_up, candles) = split(&instrument.candles);
+            // Instruments without candles in the period would pull the averages towards zero
+            if candles.is_empty() {
+                return None;
+            }
+            let mut strategy = EmaCrossBacktest::from_config(&config);
+            Some(
+                backtester
+                    .run_with_warm_up(&instrument.ticker, &mut strategy, warm_up, candles)
+                    .metrics,
+            )
+        })
+        .collect();
+
+    let metrics_avg = average(&metrics);
+    Evaluation {
+        parameters,
+        score: objective_value(&metrics_avg, objective),
+        metrics: metrics_avg,
+        instruments: metrics.len(),
+    }
+}
+
+/// Evaluates all parameter sets in parallel and returns them ranked by score
+pub fn optimize<F>(
+    parameter_sets: &[ParameterSet],
+    universe: &[InstrumentCandles],
+    backtester: &Backtester,
+    objective: Objective,
+    split: F,
+) -> Vec<Evaluation>
+where
+    F: Fn(&[Candle]) -> (&[Candle], &[Candle]) + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = parameter_sets.len().div_ceil(threads).max(1);
+
+    let evaluations: Vec<Evaluation> = thread::scope(|scope| {
+        let handles: Vec<_> = parameter_sets
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let split = &split;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|&parameters| evaluate(parameters, universe, backtester, objective, split))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("optimization thread panicked"))
+            .collect()
+    });
+
+    rank(evaluations)
+}
+
+/// Sorts evaluations by score, dropping those without instruments and repeated parameter sets
+fn rank(mut evaluations: Vec<Evaluation>) -> Vec<Evaluation> {
+    evaluations.retain(|evaluation| evaluation.instruments > 0);
+    evaluations.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    // Parameter lists in config may contain duplicate values
+    let mut seen = HashSet::new();
+    evaluations.retain(|evaluation| seen.insert(evaluation.parameters.key()));
+    evaluations
+}
+
+/// Prints ranked evaluations
+pub fn print_ranking(evaluations: &[Evaluation], top: usize) {
+    println!(
+        "{:>4} | {:<60} | {:>8} | {:>8} | {:>8} | {:>8} | {:>8} | {:>6}",
+        "#", "PARAMETERS", "SCORE", "SHARPE", "CAGR%", "MAX DD%", "WIN%", "TRADES"
+    );
+    for (rank, evaluation) in evaluations.iter().take(top).enumerate() {
+        println!(
+            "{:>4} | {:<60} | {:>8.3} | {:>8.3} | {:>8.2} | {:>8.2} | {:>8.2} | {:>6}",
+            rank + 1,
+            evaluation.parameters.to_string(),
+            evaluation.score,
+            evaluation.metrics.sharpe,
+            evaluation.metrics.cagr_percentage,
+            evaluation.metrics.max_drawdown_percentage,
+            evaluation.metrics.win_rate_percentage,
+            evaluation.metrics.trades
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn config(method: SearchMethod, seed: Option<u64>, random_samples: usize) -> OptimizationConfig {
+        OptimizationConfig {
+            method,
+            random_samples,
+            seed,
+            objective: Objective::Sharpe,
+            short_ema_lengths: vec![5, 10, 20],
+            long_ema_lengths: vec![10, 20, 50],
+            hysteresis_percentages: vec![0.0, 0.1],
+            hysteresis_periods: vec![1],
+            intervals: vec![IndicatorInterval::Day],
+            top: 10,
+            walk_forward: None,
+            output_strategy_file: None,
+        }
+    }
+
+    fn evaluation(short_ema_length: i32, score: f64, instruments: usize) -> Evaluation {
+        Evaluation {
+            parameters: ParameterSet {
+                short_ema_length,
+                long_ema_length: 50,
+                hysteresis_percentage: 0.1,
+                hysteresis_periods: 1,
+                interval: IndicatorInterval::Day,
+            },
+            score,
+            metrics: Metrics::default(),
+            instruments,
+        }
+    }
+
+    fn candles(first_day: u32, count: u32) -> Vec<Candle> {
+        (0..count)
+            .map(|i| {
+                let price = 100.0 + (i as f64 / 3.0).sin() * 10.0;
+                Candle {
+                    time: Utc.with_ymd_and_hms(2024, 1, first_day + i, 0, 0, 0).unwrap(),
+                    open: price,
+                    high: price + 1.0,
+                    low: price - 1.0,
+                    close: price,
+                    volume: 100,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grid_skips_short_not_below_long() {
+        let sets = parameter_sets(&config(SearchMethod::Grid, None, 0));
+        // (5,10) (5,20) (5,50) (10,20) (10,50) (20,50), two hysteresis values each
+        assert_eq!(sets.len(), 12);
+        assert!(sets.iter().all(|set| set.short_ema_length < set.long_ema_length));
+    }
+
+    #[test]
+    fn seeded_random_search_is_deterministic_and_truncated() {
+        let first = parameter_sets(&config(SearchMethod::Random, Some(7), 5));
+        let second = parameter_sets(&config(SearchMethod::Random, Some(7), 5));
+        assert_eq!(first.len(), 5);
+        assert_eq!(first, second);
+
+        let grid = parameter_sets(&config(SearchMethod::Grid, None, 0));
+        assert!(first.iter().all(|set| grid.contains(set)));
+    }
+
+    #[test]
+    fn random_search_never_exceeds_the_grid() {
+        assert_eq!(parameter_sets(&config(SearchMethod::Random, Some(7), 100)).len(), 12);
+    }
+
+    #[test]
+    fn ranking_is_by_score_without_duplicates_or_empty_evaluations() {
+        let ranked = rank(vec![
+            evaluation(5, 0.5, 2),
+            evaluation(10, 1.5, 2),
+            evaluation(5, 0.5, 2),
+            evaluation(20, 9.0, 0),
+            evaluation(15, -1.0, 1),
+        ]);
+
+        let order: Vec<(i32, f64)> = ranked.iter().map(|e| (e.parameters.short_ema_length, e.score)).collect();
+        assert_eq!(order, vec![(10, 1.5), (5, 0.5), (15, -1.0)]);
+    }
+
+    #[test]
+    fn instruments_without_candles_in_the_period_are_not_averaged() {
+        let universe = vec![
+            InstrumentCandles {
+                ticker: "SBER".to_string(),
+                interval: IndicatorInterval::Day,
+                candles: candles(1, 30),
+            },
+            InstrumentCandles {
+                ticker: "GAZP".to_string(),
+                interval: IndicatorInterval::Day,
+                candles: Vec::new(),
+            },
+            InstrumentCandles {
+                ticker: "LKOH".to_string(),
+                interval: IndicatorInterval::Hour,
+                candles: candles(1, 30),
+            },
+        ];
+        let backtester = Backtester::new(1000.0, 0.0, 0.0);
+        let parameters = parameter_sets(&config(SearchMethod::Grid, None, 0))[0];
+
+        let evaluation = evaluate(parameters, &universe, &backtester, Objective::Sharpe, |candles| (&[], candles));
+        assert_eq!(evaluation.instruments, 1);
+    }
+}
@@ -0,0 +1,606 @@
+use crate::backtest::candles::Candle;
+use crate::backtest::engine::{EquityPoint, Trade};
+use crate::backtest::metrics::Metrics;
+use crate::backtest::optimizer::InstrumentCandles;
+use crate::backtest::strategy::{BacktestStrategy, EmaCrossBacktest};
+use crate::bot::signal::Signal;
+use crate::config::{PortfolioConfig, PositionSizing, Rebalance, StrategyConfig};
+use chrono::{DateTime, Datelike, Utc};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone)]
+pub struct Contribution {
+    pub ticker: String,
+    pub pnl: f64,
+    /// P&L relative to the initial capital
+    pub contribution_percentage: f64,
+    pub trades: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct PortfolioResult {
+    pub strategy: String,
+    pub equity_curve: Vec<EquityPoint>,
+    pub trades: Vec<(String, Trade)>,
+    pub metrics: Metrics,
+    pub contributions: Vec<Contribution>,
+    /// Tickers of instruments that traded, in the order of `correlation` rows
+    pub correlation_tickers: Vec<String>,
+    /// Correlation matrix of per-candle strategy returns
+    pub correlation: Vec<Vec<f64>>,
+}
+
+/// Open position in one instrument
+struct Holding {
+    entry_time: DateTime<Utc>,
+    quantity: i64,
+    /// Remaining cost including commissions
+    cost: f64,
+    bought_quantity: i64,
+    bought_value: f64,
+    /// P&L realized by partial sells during rebalancing
+    realized: f64,
+}
+
+/// Simulation state of one instrument
+struct Slot<'a> {
+    ticker: &'a str,
+    candles: &'a [Candle],
+    next: usize,
+    strategy: Box<dyn BacktestStrategy>,
+    pending: Signal,
+    last_close: Option<f64>,
+    holding: Option<Holding>,
+    realized: f64,
+    trades: Vec<Trade>,
+    previous_pnl: f64,
+    returns: Vec<f64>,
+}
+
+impl Slot<'_> {
+    fn market_value(&self) -> f64 {
+        match (&self.holding, self.last_close) {
+            (Some(holding), Some(close)) => holding.quantity as f64 * close,
+            _ => 0.0,
+        }
+    }
+
+    /// Realized plus unrealized P&L since the start
+    fn total_pnl(&self) -> f64 {
+        let unrealized = self.holding.as_ref().map_or(0.0, |h| self.market_value() - h.cost + h.realized);
+        self.realized + unrealized
+    }
+}
+
+/// Runs one strategy over many instruments with shared capital
+pub struct PortfolioBacktester {
+    initial_capital: f64,
+    commission_rate: f64,
+    slippage_rate: f64,
+    config: PortfolioConfig,
+}
+
+impl PortfolioBacktester {
+    pub fn new(
+        initial_capital: f64,
+        commission_percentage: f64,
+        slippage_percentage: f64,
+        config: PortfolioConfig,
+    ) -> Self {
+        Self {
+            initial_capital,
+            commission_rate: commission_percentage / 100.0,
+            slippage_rate: slippage_percentage / 100.0,
+            config,
+        }
+    }
+
+    /// Target value of one position for the current equity
+    fn target_value(&self, equity: f64) -> f64 {
+        match self.config.sizing {
+            PositionSizing::EqualWeight => equity / self.config.max_positions.max(1) as f64,
+            PositionSizing::FixedFraction => equity * self.config.position_fraction,
+            PositionSizing::FixedAmount => self.config.position_amount,
+        }
+    }
+
+    fn is_rebalance_time(&self, previous: Option<DateTime<Utc>>, time: DateTime<Utc>) -> bool {
+        let Some(previous) = previous else {
+            return false;
+        };
+        match self.config.rebalance {
+            Rebalance::None => false,
+            Rebalance::Daily => previous.date_naive() != time.date_naive(),
+            Rebalance::Weekly => previous.iso_week() != time.iso_week(),
+            Rebalance::Monthly => (previous.year(), previous.month()) != (time.year(), time.month()),
+        }
+    }
+
+    fn buy(&self, slot: &mut Slot, cash: &mut f64, time: DateTime<Utc>, open: f64, value: f64) {
+        let price = open * (1.0 + self.slippage_rate);
+        let quantity = (value.min(*cash) / (price * (1.0 + self.commission_rate))).floor() as i64;
+        if quantity <= 0 {
+            return;
+        }
+        let notional = price * quantity as f64;
+        let commission = notional * self.commission_rate;
+        *cash -= notional + commission;
+
+        let holding = slot.holding.get_or_insert(Holding {
+            entry_time: time,
+            quantity: 0,
+            cost: 0.0,
+            bought_quantity: 0,
+            bought_value: 0.0,
+            realized: 0.0,
+        });
+        holding.quantity += quantity;
+        holding.cost += notional + commission;
+        holding.bought_quantity += quantity;
+        holding.bought_value += notional;
+    }
+
+    /// Sells part or all of the holding, records a trade when the position is closed
+    fn sell(&self, slot: &mut Slot, cash: &mut f64, time: DateTime<Utc>, price: f64, quantity: i64) {
+        let Some(holding) = slot.holding.as_mut() else {
+            return;
+        };
+        let quantity = quantity.min(holding.quantity);
+        if quantity <= 0 {
+            return;
+        }
+        let price = price * (1.0 - self.slippage_rate);
+        let proceeds = price * quantity as f64;
+        let commission = proceeds * self.commission_rate;
+        let cost = holding.cost * quantity as f64 / holding.quantity as f64;
+        *cash += proceeds - commission;
+
+        holding.realized += proceeds - commission - cost;
+        holding.cost -= cost;
+        holding.quantity -= quantity;
+
+        if holding.quantity == 0 {
+            let holding = slot.holding.take().unwrap();
+            let invested = holding.bought_value * (1.0 + self.commission_rate);
+            slot.realized += holding.realized;
+            slot.trades.push(Trade {
+                entry_time: holding.entry_time,
+                entry_price: holding.bought_value / holding.bought_quantity as f64,
+                exit_time: time,
+                exit_price: price,
+                quantity: holding.bought_quantity,
+                commission: (holding.bought_value + proceeds) * self.commission_rate,
+                pnl: holding.realized,
+                return_percentage: holding.realized / invested * 100.0,
+            });
+        }
+    }
+
+    /// Runs each instrument with its strategy parameters, e.g. with overrides applied
+    pub fn run(
+        &self,
+        universe: &[InstrumentCandles],
+        strategy_for: impl Fn(&InstrumentCandles) -> StrategyConfig,
+    ) -> PortfolioResult {
+        self.simulate(universe, |instrument| {
+            Box::new(EmaCrossBacktest::from_config(&strategy_for(instrument))) as Box<dyn BacktestStrategy>
+        })
+    }
+
+    fn simulate(
+        &self,
+        universe: &[InstrumentCandles],
+        strategy_for: impl Fn(&InstrumentCandles) -> Box<dyn BacktestStrategy>,
+    ) -> PortfolioResult {
+        let mut slots: Vec<Slot> = universe
+            .iter()
+            .map(|instrument| Slot {
+                ticker: &instrument.ticker,
+                candles: &instrument.candles,
+                next: 0,
+                strategy: strategy_for(instrument),
+                pending: Signal::Hold,
+                last_close: None,
+                holding: None,
+                realized: 0.0,
+                trades: Vec::new(),
+                previous_pnl: 0.0,
+                returns: Vec::new(),
+            })
+            .collect();
+
+        let timeline: BTreeSet<DateTime<Utc>> = universe
+            .iter()
+            .flat_map(|instrument| instrument.candles.iter().map(|candle| candle.time))
+            .collect();
+
+        let mut cash = self.initial_capital;
+        let mut equity = self.initial_capital;
+        let mut previous_time = None;
+        let mut equity_curve = Vec::with_capacity(timeline.len());
+
+        for &time in &timeline {
+            let present: Vec<usize> = (0..slots.len())
+                .filter(|&i| slots[i].candles.get(slots[i].next).is_some_and(|c| c.time == time))
+                .collect();
+
+            // Sells first to free capital for buys at the same open
+            for &i in &present {
+                let open = slots[i].candles[slots[i].next].open;
+                if slots[i].pending == Signal::Sell {
+                    let quantity = slots[i].holding.as_ref().map_or(0, |h| h.quantity);
+                    self.sell(&mut slots[i], &mut cash, time, open, quantity);
+                }
+            }
+
+            if self.is_rebalance_time(previous_time, time) {
+                let target = self.target_value(equity);
+                for &i in &present {
+                    let open = slots[i].candles[slots[i].next].open;
+                    let Some(quantity) = slots[i].holding.as_ref().map(|h| h.quantity) else {
+                        continue;
+                    };
+                    let difference = ((target - quantity as f64 * open) / open).trunc() as i64;
+                    if difference < 0 {
+                        self.sell(&mut slots[i], &mut cash, time, open, -difference);
+                    } else if difference > 0 {
+                        self.buy(&mut slots[i], &mut cash, time, open, difference as f64 * open);
+                    }
+                }
+            }
+
+            let mut open_positions = slots.iter().filter(|slot| slot.holding.is_some()).count();
+            for &i in &present {
+                if slots[i].pending == Signal::Buy
+                    && slots[i].holding.is_none()
+                    && open_positions < self.config.max_positions
+                {
+                    let open = slots[i].candles[slots[i].next].open;
+                    self.buy(&mut slots[i], &mut cash, time, open, self.target_value(equity));
+                    if slots[i].holding.is_some() {
+                        open_positions += 1;
+                    }
+                }
+            }
+
+            for &i in &present {
+                let slot = &mut slots[i];
+                let candle = &slot.candles[slot.next];
+                slot.pending = slot.strategy.on_candle(candle);
+                slot.last_close = Some(candle.close);
+                slot.next += 1;
+            }
+
+            let previous_equity = equity;
+            equity = cash + slots.iter().map(Slot::market_value).sum::<f64>();
+            for slot in &mut slots {
+                let pnl = slot.total_pnl();
+                let step_return = if previous_equity > 0.0 { (pnl - slot.previous_pnl) / previous_equity } else { 0.0 };
+                slot.returns.push(step_return);
+                slot.previous_pnl = pnl;
+            }
+
+            equity_curve.push(EquityPoint {
+                time,
+                equity,
+                in_market: open_positions > 0,
+            });
+            previous_time = Some(time);
+        }
+
+        // Close remaining positions at the last known close
+        if let Some(&last_time) = timeline.last() {
+            for slot in &mut slots {
+                if let (Some(holding), Some(close)) = (slot.holding.as_ref(), slot.last_close) {
+                    let quantity = holding.quantity;
+                    self.sell(slot, &mut cash, last_time, close, quantity);
+                }
+            }
+            if let Some(point) = equity_curve.last_mut() {
+                point.equity = cash;
+            }
+        }
+
+        let trades: Vec<(String, Trade)> = slots
+            .iter()
+            .flat_map(|slot| slot.trades.iter().map(|trade| (slot.ticker.to_string(), trade.clone())))
+            .collect();
+        let all_trades: Vec<Trade> = trades.iter().map(|(_, trade)| trade.clone()).collect();
+
+        let mut contributions: Vec<Contribution> = slots
+            .iter()
+            .filter(|slot| !slot.trades.is_empty())
+            .map(|slot| Contribution {
+                ticker: slot.ticker.to_string(),
+                pnl: slot.realized,
+                contribution_percentage: slot.realized / self.initial_capital * 100.0,
+                trades: slot.trades.len(),
+            })
+            .collect();
+        contributions.sort_by(|a, b| b.pnl.total_cmp(&a.pnl));
+
+        let traded: Vec<&Slot> = slots.iter().filter(|slot| !slot.trades.is_empty()).collect();
+        let correlation = traded
+            .iter()
+            .map(|a| traded.iter().map(|b| correlation(&a.returns, &b.returns)).collect())
+            .collect();
+
+        PortfolioResult {
+            strategy: strategy_names(&slots),
+            metrics: Metrics::calculate(self.initial_capital, &equity_curve, &all_trades),
+            equity_curve,
+            trades,
+            contributions,
+            correlation_tickers: traded.iter().map(|slot| slot.ticker.to_string()).collect(),
+            correlation,
+        }
+    }
+}
+
+/// Strategy of the instruments, distinct ones are listed when overrides differ
+fn strategy_names(slots: &[Slot]) -> String {
+    let mut names: Vec<String> = Vec::new();
+    for name in slots.iter().map(|slot| slot.strategy.name()) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names.join(", ")
+}
+
+/// Pearson correlation coefficient
+pub fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    if n == 0 {
+        return 0.0;
+    }
+    let mean_a = a[..n].iter().sum::<f64>() / n as f64;
+    let mean_b = b[..n].iter().sum::<f64>() / n as f64;
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+    for i in 0..n {
+        covariance += (a[i] - mean_a) * (b[i] - mean_b);
+        variance_a += (a[i] - mean_a).powi(2);
+        variance_b += (b[i] - mean_b).powi(2);
+    }
+    if variance_a == 0.0 || variance_b == 0.0 {
+        return 0.0;
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+impl PortfolioResult {
+    /// Prints metrics, per-instrument contribution and the most correlated pairs
+    pub fn print(&self) {
+        println!("Portfolio backtest: {}", self.strategy);
+        self.metrics.print();
+
+        if let (Some(first), Some(last)) = (self.equity_curve.first(), self.equity_curve.last()) {
+            println!("  Equity: {:.2} ({}) -> {:.2} ({})", first.equity, first.time, last.equity, last.time);
+        }
+
+        println!("  Contribution by instrument:");
+        for contribution in &self.contributions {
+            println!(
+                "  {:<12} P&L {:>14.2} ({:+.2}%), trades {}",
+                contribution.ticker, contribution.pnl, contribution.contribution_percentage, contribution.trades
+            );
+        }
+
+        println!("  Trades:");
+        for (ticker, trade) in &self.trades {
+            println!(
+                "  {:<12} {} {:>12.4} -> {} {:>12.4} x{:<8} P&L {:>12.2} ({:+.2}%)",
+                ticker,
+                trade.entry_time.format("%Y-%m-%d %H:%M"),
+                trade.entry_price,
+                trade.exit_time.format("%Y-%m-%d %H:%M"),
+                trade.exit_price,
+                trade.quantity,
+                trade.pnl,
+                trade.return_percentage
+            );
+        }
+
+        let mut pairs = Vec::new();
+        for i in 0..self.correlation_tickers.len() {
+            for j in (i + 1)..self.correlation_tickers.len() {
+                pairs.push((i, j, self.correlation[i][j]));
+            }
+        }
+        if !pairs.is_empty() {
+            let average = pairs.iter().map(|&(_, _, c)| c).sum::<f64>() / pairs.len() as f64;
+            println!("  Average pairwise correlation of returns: {:.3}", average);
+            pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+            println!("  Most correlated pairs:");
+            for &(i, j, c) in pairs.iter().take(10) {
+                println!("  {:<12} {:<12} {:.3}", self.correlation_tickers[i], self.correlation_tickers[j], c);
+            }
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data_service::get_tech_analysis::IndicatorInterval;
+    use chrono::{Duration, TimeZone};
+
+    /// Returns the given signal at each candle close
+    struct Scripted {
+        signals: Vec<Signal>,
+        index: usize,
+    }
+
+    impl BacktestStrategy for Scripted {
+        fn name(&self) -> String {
+            "scripted".to_string()
+        }
+
+        fn on_candle(&mut self, _candle: &Candle) -> Signal {
+            let signal = self.signals.get(self.index).copied().unwrap_or(Signal::Hold);
+            self.index += 1;
+            signal
+        }
+    }
+
+    const B: Signal = Signal::Buy;
+    const S: Signal = Signal::Sell;
+    const H: Signal = Signal::Hold;
+
+    /// Daily candles opening and closing at the given prices
+    fn instrument(ticker: &str, prices: &[f64]) -> InstrumentCandles {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        InstrumentCandles {
+            ticker: ticker.to_string(),
+            interval: IndicatorInterval::Day,
+            candles: prices
+                .iter()
+                .enumerate()
+                .map(|(i, &price)| Candle {
+                    time: start + Duration::days(i as i64),
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: 100,
+                })
+                .collect(),
+        }
+    }
+
+    fn backtester(max_positions: usize, sizing: PositionSizing, rebalance: Rebalance, costs: f64) -> PortfolioBacktester {
+        let config = PortfolioConfig {
+            max_positions,
+            sizing,
+            position_fraction: 0.5,
+            position_amount: 800.0,
+            rebalance,
+        };
+        PortfolioBacktester::new(1000.0, costs, costs, config)
+    }
+
+    fn run(backtester: &PortfolioBacktester, universe: &[InstrumentCandles], signals: &[(&str, Vec<Signal>)]) -> PortfolioResult {
+        backtester.simulate(universe, |instrument| {
+            let signals = signals.iter().find(|(ticker, _)| *ticker == instrument.ticker).unwrap().1.clone();
+            Box::new(Scripted { signals, index: 0 })
+        })
+    }
+
+    fn final_equity(result: &PortfolioResult) -> f64 {
+        result.equity_curve.last().unwrap().equity
+    }
+
+    fn tickers(result: &PortfolioResult) -> Vec<&str> {
+        result.trades.iter().map(|(ticker, _)| ticker.as_str()).collect()
+    }
+
+    #[test]
+    fn buys_at_next_open_and_sells_on_signal() {
+        let universe = [instrument("SBER", &[10.0, 10.0, 11.0, 12.0, 12.0])];
+        let result = run(&backtester(1, PositionSizing::EqualWeight, Rebalance::None, 0.0), &universe, &[("SBER", vec![B, H, S])]);
+
+        let (_, trade) = &result.trades[0];
+        assert_eq!(trade.quantity, 100);
+        assert_eq!(trade.entry_price, 10.0);
+        assert_eq!(trade.exit_price, 12.0);
+        assert!((trade.pnl - 200.0).abs() < 1e-9);
+        assert!((final_equity(&result) - 1200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn max_positions_blocks_buys_until_a_position_is_sold() {
+        let universe = [instrument("SBER", &[10.0; 6]), instrument("GAZP", &[10.0; 6])];
+        let signals = [("SBER", vec![B, H, S]), ("GAZP", vec![B, H, B])];
+        let result = run(&backtester(1, PositionSizing::FixedFraction, Rebalance::None, 0.0), &universe, &signals);
+
+        // GAZP is blocked on day 1 and bought on day 3 with the cash SBER freed at the same open
+        assert_eq!(tickers(&result), vec!["SBER", "GAZP"]);
+        let (_, gazp) = &result.trades[1];
+        assert_eq!(gazp.entry_time, universe[1].candles[3].time);
+    }
+
+    #[test]
+    fn buys_never_spend_more_than_the_cash() {
+        let universe = [instrument("SBER", &[10.0; 4]), instrument("GAZP", &[10.0; 4])];
+        let signals = [("SBER", vec![B]), ("GAZP", vec![B])];
+        let result = run(&backtester(2, PositionSizing::FixedAmount, Rebalance::None, 1.0), &universe, &signals);
+
+        assert_eq!(tickers(&result), vec!["SBER", "GAZP"]);
+        let spent: f64 = result.trades.iter().map(|(_, t)| t.entry_price * t.quantity as f64 * 1.01).sum();
+        assert!(spent <= 1000.0, "spent {}", spent);
+        // The second position only gets what is left after the first
+        assert!(result.trades[1].1.quantity < result.trades[0].1.quantity);
+        assert!(final_equity(&result) >= 0.0);
+    }
+
+    #[test]
+    fn final_equity_is_capital_plus_trade_pnl() {
+        let sber: Vec<f64> = (0..70).map(|i| 100.0 + i as f64).collect();
+        let gazp: Vec<f64> = (0..70).map(|i| 200.0 - i as f64).collect();
+        let universe = [instrument("SBER", &sber), instrument("GAZP", &gazp)];
+        let signals = [("SBER", vec![B, H, H, H, H, S, H, B]), ("GAZP", vec![H, B])];
+
+        for rebalance in [Rebalance::None, Rebalance::Daily, Rebalance::Weekly, Rebalance::Monthly] {
+            let result = run(&backtester(2, PositionSizing::EqualWeight, rebalance, 0.5), &universe, &signals);
+            let pnl: f64 = result.trades.iter().map(|(_, trade)| trade.pnl).sum();
+            assert!((final_equity(&result) - 1000.0 - pnl).abs() < 1e-6, "{:?}", rebalance);
+            let contributions: f64 = result.contributions.iter().map(|c| c.pnl).sum();
+            assert!((contributions - pnl).abs() < 1e-6, "{:?}", rebalance);
+        }
+    }
+
+    #[test]
+    fn rebalancing_trims_a_rising_position() {
+        // Price doubles in January and keeps rising in February
+        let prices: Vec<f64> = (0..60).map(|i| 1.0 + i as f64 / 30.0).collect();
+        let universe = [instrument("SBER", &prices)];
+        let final_with = |rebalance| {
+            let result = run(&backtester(2, PositionSizing::EqualWeight, rebalance, 0.0), &universe, &[("SBER", vec![B])]);
+            // Partial sells don't close the position
+            assert_eq!(result.trades.len(), 1, "{:?}", rebalance);
+            final_equity(&result)
+        };
+
+        let none = final_with(Rebalance::None);
+        let monthly = final_with(Rebalance::Monthly);
+        let weekly = final_with(Rebalance::Weekly);
+        let daily = final_with(Rebalance::Daily);
+        assert!(none > monthly, "{} {}", none, monthly);
+        assert!(monthly > weekly, "{} {}", monthly, weekly);
+        assert!(weekly > daily, "{} {}", weekly, daily);
+    }
+
+    #[test]
+    fn rebalance_times() {
+        let at = |month, day, hour| Utc.with_ymd_and_hms(2024, month, day, hour, 0, 0).unwrap();
+        let cases = [
+            // Monday 1st to Monday 1st 10:00, Sunday 7th to Monday 8th, January to February
+            (at(1, 1, 0), at(1, 1, 10), [false, false, false, false]),
+            (at(1, 1, 0), at(1, 2, 0), [false, true, false, false]),
+            (at(1, 7, 0), at(1, 8, 0), [false, true, true, false]),
+            (at(1, 31, 0), at(2, 1, 0), [false, true, false, true]),
+        ];
+        let modes = [Rebalance::None, Rebalance::Daily, Rebalance::Weekly, Rebalance::Monthly];
+
+        for (previous, time, expected) in cases {
+            for (rebalance, expected) in modes.into_iter().zip(expected) {
+                let backtester = backtester(1, PositionSizing::EqualWeight, rebalance, 0.0);
+                assert_eq!(backtester.is_rebalance_time(Some(previous), time), expected, "{:?} {} {}", rebalance, previous, time);
+                assert!(!backtester.is_rebalance_time(None, time));
+            }
+        }
+    }
+
+    #[test]
+    fn correlation_of_known_vectors() {
+        let a = [1.0, 2.0, 3.0, 4.0];
+        assert!((correlation(&a, &a) - 1.0).abs() < 1e-12);
+        assert!((correlation(&a, &[8.0, 6.0, 4.0, 2.0]) + 1.0).abs() < 1e-12);
+        assert!((correlation(&a, &[1.0, 3.0, 2.0, 4.0]) - 0.8).abs() < 1e-12);
+        assert_eq!(correlation(&a, &[5.0; 4]), 0.0);
+        assert_eq!(correlation(&[], &[]), 0.0);
+    }
+}
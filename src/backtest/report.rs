@@ -0,0 +1,392 @@
+use crate::backtest::engine::{BacktestResult, EquityPoint, Trade};
+use crate::backtest::metrics::Metrics;
+use crate::backtest::portfolio::PortfolioResult;
+use chrono::Datelike;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use teloxide::utils::html;
+use tracing::info;
+
+const CHART_WIDTH: f64 = 960.0;
+const CHART_HEIGHT: f64 = 260.0;
+
+/// Backtest data needed to build reports
+pub struct Report<'a> {
+    pub title: String,
+    pub equity_curve: &'a [EquityPoint],
+    /// Trades with the ticker of the instrument
+    pub trades: Vec<(&'a str, &'a Trade)>,
+    pub metrics: &'a Metrics,
+}
+
+impl<'a> Report<'a> {
+    pub fn from_backtest(result: &'a BacktestResult) -> Self {
+        Self {
+            title: format!("{} / {}", result.instrument, result.strategy),
+            equity_curve: &result.equity_curve,
+            trades: result.trades.iter().map(|trade| (result.instrument.as_str(), trade)).collect(),
+            metrics: &result.metrics,
+        }
+    }
+
+    pub fn from_portfolio(result: &'a PortfolioResult) -> Self {
+        Self {
+            title: format!("Portfolio / {}", result.strategy),
+            equity_curve: &result.equity_curve,
+            trades: result.trades.iter().map(|(ticker, trade)| (ticker.as_str(), trade)).collect(),
+            metrics: &result.metrics,
+        }
+    }
+
+    /// Writes `<name>.html`, `<name>_trades.csv` and `<name>_metrics.json` into `dir`.
+    /// An infinite profit factor is written to the JSON as `null`
+    pub fn write(&self, dir: &str, name: &str) -> Result<(), Box<dyn Error>> {
+        let dir = Path::new(dir);
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(format!("{}.html", name)), self.to_html())?;
+        fs::write(dir.join(format!("{}_trades.csv", name)), self.trades_csv())?;
+        fs::write(dir.join(format!("{}_metrics.json", name)), serde_json::to_string_pretty(self.metrics)?)?;
+        info!("Backtest report {} written to {}", name, dir.display());
+        Ok(())
+    }
+
+    pub fn trades_csv(&self) -> String {
+        let mut csv = String::from("ticker,entry_time,entry_price,exit_time,exit_price,quantity,commission,pnl,return_percentage\n");
+        for (ticker, trade) in &self.trades {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{:.2},{:.2},{:.4}",
+                csv_field(ticker),
+                trade.entry_time.to_rfc3339(),
+                trade.entry_price,
+                trade.exit_time.to_rfc3339(),
+                trade.exit_price,
+                trade.quantity,
+                trade.commission,
+                trade.pnl,
+                trade.return_percentage
+            );
+        }
+        csv
+    }
+
+    /// Self-contained HTML page with inline SVG charts and no external resources
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+             body {{ font-family: sans-serif; margin: 24px; color: #222; }}\n\
+             table {{ border-collapse: collapse; margin-bottom: 24px; }}\n\
+             td, th {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }}\n\
+             th {{ background: #f0f0f0; }}\n\
+             svg {{ background: #fafafa; border: 1px solid #ddd; margin-bottom: 24px; }}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+            title = html::escape(&self.title)
+        );
+
+        html.push_str("<h2>Metrics</h2>\n");
+        html.push_str(&self.metrics_table());
+        html.push_str("<h2>Equity curve</h2>\n");
+        html.push_str(&line_chart(
+            &self.equity_curve.iter().map(|p| p.equity).collect::<Vec<_>>(),
+            "#1f77b4",
+            false,
+        ));
+        html.push_str("<h2>Drawdown</h2>\n");
+        html.push_str(&line_chart(&drawdowns(self.equity_curve), "#d62728", true));
+        html.push_str("<h2>Monthly returns, %</h2>\n");
+        html.push_str(&self.monthly_returns_table());
+        html.push_str("<h2>Trades</h2>\n");
+        html.push_str(&self.trades_table());
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn metrics_table(&self) -> String {
+        let m = self.metrics;
+        let rows = [
+            ("Total return", format!("{:.2}%", m.total_return_percentage)),
+            ("CAGR", format!("{:.2}%", m.cagr_percentage)),
+            ("Sharpe", format!("{:.2}", m.sharpe)),
+            ("Sortino", format!("{:.2}", m.sortino)),
+            ("Max drawdown", format!("{:.2}%", m.max_drawdown_percentage)),
+            ("Win rate", format!("{:.2}%", m.win_rate_percentage)),
+            ("Profit factor", format!("{:.2}", m.profit_factor)),
+            ("Exposure", format!("{:.2}%", m.exposure_percentage)),
+            ("Trades", m.trades.to_string()),
+        ];
+        let mut table = String::from("<table>\n");
+        for (name, value) in rows {
+            let _ = writeln!(table, "<tr><th>{}</th><td>{}</td></tr>", name, value);
+        }
+        table.push_str("</table>\n");
+        table
+    }
+
+    fn monthly_returns_table(&self) -> String {
+        let returns = monthly_returns(self.equity_curve);
+        let mut years: BTreeMap<i32, [Option<f64>; 12]> = BTreeMap::new();
+        for ((year, month), value) in returns {
+            years.entry(year).or_insert([None; 12])[month as usize - 1] = Some(value);
+        }
+
+        let mut table = String::from("<table>\n<tr><th>Year</th>");
+        for month in ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"] {
+            let _ = write!(table, "<th>{}</th>", month);
+        }
+        table.push_str("</tr>\n");
+        for (year, months) in years {
+            let _ = write!(table, "<tr><th>{}</th>", year);
+            for value in months {
+                match value {
+                    Some(value) => {
+                        let _ = write!(table, "<td style=\"background:{}\">{:.2}</td>", heat_color(value), value);
+                    }
+                    None => table.push_str("<td></td>"),
+                }
+            }
+            table.push_str("</tr>\n");
+        }
+        table.push_str("</table>\n");
+        table
+    }
+
+    fn trades_table(&self) -> String {
+        let mut table = String::from(
+            "<table>\n<tr><th>Ticker</th><th>Entry</th><th>Entry price</th><th>Exit</th>\
+             <th>Exit price</th><th>Quantity</th><th>Commission</th><th>P&amp;L</th><th>Return, %</th></tr>\n",
+        );
+        for (ticker, trade) in &self.trades {
+            let _ = writeln!(
+                table,
+                "<tr><td>{}</td><td>{}</td><td>{:.4}</td><td>{}</td><td>{:.4}</td><td>{}</td><td>{:.2}</td>\
+                 <td style=\"color:{}\">{:.2}</td><td>{:+.2}</td></tr>",
+                html::escape(ticker),
+                trade.entry_time.format("%Y-%m-%d %H:%M"),
+                trade.entry_price,
+                trade.exit_time.format("%Y-%m-%d %H:%M"),
+                trade.exit_price,
+                trade.quantity,
+                trade.commission,
+                if trade.pnl >= 0.0 { "#2ca02c" } else { "#d62728" },
+                trade.pnl,
+                trade.return_percentage
+            );
+        }
+        table.push_str("</table>\n");
+        table
+    }
+}
+
+/// Quotes a CSV field containing separators, quotes or line breaks
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Drawdown from the running peak in percent (non-positive values)
+fn drawdowns(equity_curve: &[EquityPoint]) -> Vec<f64> {
+    let mut peak = f64::MIN;
+    equity_curve
+        .iter()
+        .map(|point| {
+            peak = peak.max(point.equity);
+            if peak > 0.0 { (point.equity - peak) / peak * 100.0 } else { 0.0 }
+        })
+        .collect()
+}
+
+/// Returns of each calendar month relative to the equity at the end of the previous month
+fn monthly_returns(equity_curve: &[EquityPoint]) -> Vec<((i32, u32), f64)> {
+    let mut month_end: BTreeMap<(i32, u32), f64> = BTreeMap::new();
+    for point in equity_curve {
+        month_end.insert((point.time.year(), point.time.month()), point.equity);
+    }
+
+    let mut previous = equity_curve.first().map(|point| point.equity);
+    month_end
+        .into_iter()
+        .map(|(month, equity)| {
+            let base = previous.unwrap_or(equity);
+            previous = Some(equity);
+            (month, if base > 0.0 { (equity / base - 1.0) * 100.0 } else { 0.0 })
+        })
+        .collect()
+}
+
+/// Green for gains, red for losses, saturated at ±10%
+fn heat_color(value: f64) -> String {
+    let intensity = (value.abs() / 10.0).min(1.0);
+    let fade = (255.0 - intensity * 155.0) as u8;
+    if value >= 0.0 {
+        format!("rgb({},255,{})", fade, fade)
+    } else {
+        format!("rgb(255,{},{})", fade, fade)
+    }
+}
+
+/// Renders values as an SVG polyline, optionally filled down to zero
+fn line_chart(values: &[f64], color: &str, fill: bool) -> String {
+    if values.is_empty() {
+        return "<p>No data</p>\n".to_string();
+    }
+
+    let mut min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let mut max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    // Filled charts keep the zero line visible
+    if fill {
+        min = min.min(0.0);
+        max = max.max(0.0);
+    }
+    let range = if max > min { max - min } else { 1.0 };
+    let step = if values.len() > 1 { CHART_WIDTH / (values.len() - 1) as f64 } else { 0.0 };
+    let y = |value: f64| CHART_HEIGHT - (value - min) / range * CHART_HEIGHT;
+
+    let mut points = String::new();
+    for (i, value) in values.iter().enumerate() {
+        let _ = write!(points, "{:.1},{:.1} ", i as f64 * step, y(*value));
+    }
+
+    let mut svg = format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"-60 -10 {vw} {vh}\">\n",
+        w = CHART_WIDTH + 80.0,
+        h = CHART_HEIGHT + 30.0,
+        vw = CHART_WIDTH + 80.0,
+        vh = CHART_HEIGHT + 30.0
+    );
+    if fill {
+        let _ = writeln!(
+            svg,
+            "<polygon points=\"0,{zero:.1} {points}{end:.1},{zero:.1}\" fill=\"{color}\" fill-opacity=\"0.3\"/>",
+            zero = y(0.0),
+            points = points,
+            end = (values.len() - 1) as f64 * step,
+            color = color
+        );
+    }
+    let _ = writeln!(svg, "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\"/>", points.trim_end(), color);
+    let _ = writeln!(svg, "<text x=\"-55\" y=\"10\" font-size=\"11\">{:.2}</text>", max);
+    let _ = writeln!(svg, "<text x=\"-55\" y=\"{:.1}\" font-size=\"11\">{:.2}</text>", CHART_HEIGHT, min);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn at(month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn curve(points: &[(DateTime<Utc>, f64)]) -> Vec<EquityPoint> {
+        points
+            .iter()
+            .map(|&(time, equity)| EquityPoint { time, equity, in_market: true })
+            .collect()
+    }
+
+    fn trade(pnl: f64) -> Trade {
+        Trade {
+            entry_time: at(1, 2),
+            entry_price: 10.5,
+            exit_time: at(1, 5),
+            exit_price: 11.0,
+            quantity: 10,
+            commission: 0.25,
+            pnl,
+            return_percentage: 4.7619,
+        }
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn drawdowns_from_running_peak() {
+        let equity = curve(&[(at(1, 1), 100.0), (at(1, 2), 120.0), (at(1, 3), 90.0), (at(1, 4), 130.0), (at(1, 5), 117.0)]);
+        assert_close(&drawdowns(&equity), &[0.0, 0.0, -25.0, 0.0, -10.0]);
+        assert!(drawdowns(&[]).is_empty());
+    }
+
+    #[test]
+    fn monthly_returns_against_previous_month_end() {
+        let equity = curve(&[
+            (at(1, 1), 100.0),
+            (at(1, 31), 110.0),
+            (at(2, 15), 99.0),
+            (at(2, 29), 121.0),
+            // March is skipped entirely
+            (at(4, 30), 96.8),
+        ]);
+        let returns = monthly_returns(&equity);
+
+        let months: Vec<(i32, u32)> = returns.iter().map(|(month, _)| *month).collect();
+        assert_eq!(months, vec![(2024, 1), (2024, 2), (2024, 4)]);
+        let values: Vec<f64> = returns.iter().map(|(_, value)| *value).collect();
+        assert_close(&values, &[10.0, 10.0, -20.0]);
+    }
+
+    #[test]
+    fn csv_quotes_fields_with_separators() {
+        let trade = trade(5.0);
+        let metrics = Metrics::default();
+        let report = Report {
+            title: "test".to_string(),
+            equity_curve: &[],
+            trades: vec![("SBER", &trade), ("A,\"B\"", &trade)],
+            metrics: &metrics,
+        };
+
+        let csv = report.trades_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "SBER,2024-01-02T00:00:00+00:00,10.5,2024-01-05T00:00:00+00:00,11,10,0.25,5.00,4.7619"
+        );
+        assert!(lines[2].starts_with("\"A,\"\"B\"\"\",2024-01-02"));
+    }
+
+    #[test]
+    fn html_escapes_title_and_tickers() {
+        let trade = trade(-1.0);
+        let metrics = Metrics::default();
+        let equity = curve(&[(at(1, 1), 100.0), (at(2, 1), 99.0)]);
+        let report = Report {
+            title: "<script> & co".to_string(),
+            equity_curve: &equity,
+            trades: vec![("<b>", &trade)],
+            metrics: &metrics,
+        };
+
+        let html = report.to_html();
+        assert!(html.contains("<title>&lt;script&gt; &amp; co</title>"));
+        assert!(html.contains("<td>&lt;b&gt;</td>"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<polyline"));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn infinite_profit_factor_is_null_in_json() {
+        let metrics = Metrics {
+            profit_factor: f64::INFINITY,
+            ..Metrics::default()
+        };
+        let json: serde_json::Value = serde_json::to_value(&metrics).unwrap();
+        assert!(json["profit_factor"].is_null());
+    }
+}
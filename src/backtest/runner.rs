@@ -0,0 +1,275 @@
+use crate::backtest::candles::CandleStore;
+use crate::backtest::engine::{BacktestResult, Backtester};
+use crate::backtest::optimizer::{self, InstrumentCandles};
+use crate::backtest::portfolio::PortfolioBacktester;
+use crate::backtest::report::Report;
+use crate::backtest::strategy::EmaCrossBacktest;
+use crate::backtest::walk_forward;
+use crate::config::{BacktestConfig, Config, StrategyConfig};
+use crate::instruments::cache::InstrumentCache;
+use crate::instruments::universe::Universe;
+use crate::market_data_service::get_candles::CandleInterval;
+use crate::market_data_service::get_tech_analysis::IndicatorInterval;
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use tracing::{error, info};
+
+/// Runs the configured strategy over historical candles of every instrument
+/// listed in the `backtest` section and prints the results
+pub async fn run(config: &Config) -> Result<(), Box<dyn Error>> {
+    let backtest = config.backtest.as_ref().ok_or("`backtest` section is missing in config")?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+
+    let results = run_backtests(&client, config, backtest).await?;
+    for result in &results {
+        print_result(result);
+        if let Some(dir) = &backtest.report_dir {
+            Report::from_backtest(result).write(dir, &format!("backtest_{}", result.instrument))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn run_backtests(
+    client: &reqwest::Client,
+    config: &Config,
+    backtest: &BacktestConfig,
+) -> Result<Vec<BacktestResult>, Box<dyn Error>> {
+    let instruments = InstrumentCache::open(client, config).await?;
+
+    let store = CandleStore::new(&backtest.candles_dir);
+    let backtester = Backtester::new(
+        backtest.initial_capital,
+        backtest.commission_percentage,
+        backtest.slippage_percentage,
+    );
+    let from = start_of_day(backtest.from);
+    let to = backtest.to.map_or_else(Utc::now, start_of_day);
+
+    let mut results = Vec::new();
+    for ticker in &backtest.instruments {
+        let class_codes = config.filter.class_codes();
+        let Some(instrument) = instruments.by_ticker(ticker, &class_codes) else {
+            error!("Instrument {} not found in {}", ticker, class_codes.join(", "));
+            continue;
+        };
+
+        let strategy_config = config.strategy_for(ticker, &instrument.class_code, instrument.sector());
+        let interval = CandleInterval::from(strategy_config.interval);
+        let candles = store
+            .load_or_download(client, &config.t_token, &instrument.uid, interval, from, to)
+            .await?;
+        info!("Running backtest for {} on {} candles", ticker, candles.len());
+
+        let mut strategy = EmaCrossBacktest::from_config(&strategy_config);
+        results.push(backtester.run(ticker, &mut strategy, &candles));
+    }
+
+    Ok(results)
+}
+
+/// Optimizes strategy parameters over the whole filtered universe and
+/// prints the ranking, walk-forward report and the winning `strategy:` block
+pub async fn optimize(config: &Config) -> Result<(), Box<dyn Error>> {
+    let backtest = config.backtest.as_ref().ok_or("`backtest` section is missing in config")?;
+    let optimization = config.optimization.as_ref().ok_or("`optimization` section is missing in config")?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+
+    let universe = load_universe(&client, config, backtest, &optimization.intervals).await?;
+
+    let parameter_sets = optimizer::parameter_sets(optimization);
+    info!(
+        "Optimizing {} parameter sets over {} instrument series",
+        parameter_sets.len(),
+        universe.len()
+    );
+
+    let backtester = Backtester::new(
+        backtest.initial_capital,
+        backtest.commission_percentage,
+        backtest.slippage_percentage,
+    );
+
+    let ranking = optimizer::optimize(&parameter_sets, &universe, &backtester, optimization.objective, |candles| {
+        (&candles[..0], candles)
+    });
+    optimizer::print_ranking(&ranking, optimization.top);
+
+    if let Some(walk_forward) = &optimization.walk_forward {
+        let windows = walk_forward::run(
+            &parameter_sets,
+            &universe,
+            &backtester,
+            optimization.objective,
+            walk_forward.windows,
+            walk_forward.train_ratio,
+        );
+        walk_forward::print_report(&windows);
+    }
+
+    let Some(best) = ranking.first() else {
+        println!("No parameter set produced results");
+        return Ok(());
+    };
+
+    #[derive(Serialize)]
+    struct StrategyBlock {
+        strategy: StrategyConfig,
+    }
+
+    let block = serde_yaml::to_string(&StrategyBlock {
+        strategy: best.parameters.to_strategy_config(),
+    })?;
+    println!("Best parameters ({}):\n{}", best.parameters, block);
+
+    if let Some(path) = &optimization.output_strategy_file {
+        fs::write(path, &block)?;
+        info!("Strategy config written to {}", path);
+    }
+
+    Ok(())
+}
+
+/// Runs the configured strategy over the whole filtered universe with shared capital
+pub async fn portfolio(config: &Config) -> Result<(), Box<dyn Error>> {
+    let backtest = config.backtest.as_ref().ok_or("`backtest` section is missing in config")?;
+    let portfolio = config.portfolio.as_ref().ok_or("`portfolio` section is missing in config")?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+
+    let (universe, strategies) = load_portfolio(&client, config, backtest).await?;
+    info!("Running portfolio backtest over {} instruments", universe.len());
+
+    let backtester = PortfolioBacktester::new(
+        backtest.initial_capital,
+        backtest.commission_percentage,
+        backtest.slippage_percentage,
+        portfolio.clone(),
+    );
+    let result = backtester.run(&universe, |instrument| {
+        strategies.get(&instrument.ticker).cloned().unwrap_or_else(|| config.strategy.clone())
+    });
+    result.print();
+    if let Some(dir) = &backtest.report_dir {
+        Report::from_portfolio(&result).write(dir, "portfolio")?;
+    }
+
+    Ok(())
+}
+
+/// Loads candles of every instrument selected by the scanner filter for each interval
+pub async fn load_universe(
+    client: &reqwest::Client,
+    config: &Config,
+    backtest: &BacktestConfig,
+    intervals: &[IndicatorInterval],
+) -> Result<Vec<InstrumentCandles>, Box<dyn Error>> {
+    let instruments = InstrumentCache::open(client, config).await?;
+    let selected = Universe::new()
+        .select(client, &config.t_token, &config.filter, &instruments)
+        .await;
+
+    let store = CandleStore::new(&backtest.candles_dir);
+    let from = start_of_day(backtest.from);
+    let to = backtest.to.map_or_else(Utc::now, start_of_day);
+
+    let mut universe = Vec::new();
+    for instrument in &selected {
+        for &interval in intervals {
+            match store
+                .load_or_download(client, &config.t_token, &instrument.uid, CandleInterval::from(interval), from, to)
+                .await
+            {
+                Ok(candles) => universe.push(InstrumentCandles {
+                    ticker: instrument.ticker.clone(),
+                    interval,
+                    candles,
+                }),
+                Err(e) => error!("Error loading candles for {} ({}): {}", instrument.ticker, instrument.uid, e),
+            }
+        }
+    }
+
+    Ok(universe)
+}
+
+/// Loads candles of every instrument selected by the scanner filter in the interval of its strategy,
+/// returns the strategy of each ticker with `strategy_overrides` applied
+async fn load_portfolio(
+    client: &reqwest::Client,
+    config: &Config,
+    backtest: &BacktestConfig,
+) -> Result<(Vec<InstrumentCandles>, HashMap<String, StrategyConfig>), Box<dyn Error>> {
+    let instruments = InstrumentCache::open(client, config).await?;
+    let selected = Universe::new()
+        .select(client, &config.t_token, &config.filter, &instruments)
+        .await;
+
+    let store = CandleStore::new(&backtest.candles_dir);
+    let from = start_of_day(backtest.from);
+    let to = backtest.to.map_or_else(Utc::now, start_of_day);
+
+    let mut universe = Vec::new();
+    let mut strategies = HashMap::new();
+    for instrument in &selected {
+        let strategy = config.strategy_for(&instrument.ticker, &instrument.class_code, instrument.sector());
+        match store
+            .load_or_download(client, &config.t_token, &instrument.uid, CandleInterval::from(strategy.interval), from, to)
+            .await
+        {
+            Ok(candles) => {
+                universe.push(InstrumentCandles {
+                    ticker: instrument.ticker.clone(),
+                    interval: strategy.interval,
+                    candles,
+                });
+                strategies.insert(instrument.ticker.clone(), strategy);
+            }
+            Err(e) => error!("Error loading candles for {} ({}): {}", instrument.ticker, instrument.uid, e),
+        }
+    }
+
+    Ok((universe, strategies))
+}
+
+pub fn start_of_day(date: NaiveDate) -> chrono::DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+/// Prints metrics, trades and equity curve summary
+pub fn print_result(result: &BacktestResult) {
+    println!("Backtest: {} / {}", result.instrument, result.strategy);
+    result.metrics.print();
+
+    if let (Some(first), Some(last)) = (result.equity_curve.first(), result.equity_curve.last()) {
+        let peak = result.equity_curve.iter().map(|p| p.equity).fold(f64::MIN, f64::max);
+        println!(
+            "  Equity: {:.2} ({}) -> {:.2} ({}), peak {:.2}",
+            first.equity, first.time, last.equity, last.time, peak
+        );
+    }
+
+    println!("  Trades:");
+    for trade in &result.trades {
+        println!(
+            "  {} {:>12.4} -> {} {:>12.4} x{:<8} P&L {:>12.2} ({:+.2}%)",
+            trade.entry_time.format("%Y-%m-%d %H:%M"),
+            trade.entry_price,
+            trade.exit_time.format("%Y-%m-%d %H:%M"),
+            trade.exit_price,
+            trade.quantity,
+            trade.pnl,
+            trade.return_percentage
+        );
+    }
+    println!();
+}
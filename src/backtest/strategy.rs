@@ -0,0 +1,97 @@
+use crate::backtest::candles::Candle;
+use crate::bot::signal::{CrossoverSignal, Signal};
+use crate::config::StrategyConfig;
+
+/// Strategy that can be driven candle by candle over historical data
+pub trait BacktestStrategy {
+    fn name(&self) -> String;
+
+    /// Called on every closed candle, returns the signal at its close
+    fn on_candle(&mut self, candle: &Candle) -> Signal;
+}
+
+/// Exponential moving average seeded with the simple average of the first `length` values
+#[derive(Debug, Clone)]
+pub struct Ema {
+    length: usize,
+    alpha: f64,
+    seed: Vec<f64>,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(length: usize) -> Self {
+        Self {
+            length: length.max(1),
+            alpha: 2.0 / (length.max(1) as f64 + 1.0),
+            seed: Vec::new(),
+            value: None,
+        }
+    }
+
+    /// Adds a value and returns the EMA once enough values have been seen
+    pub fn next(&mut self, price: f64) -> Option<f64> {
+        match self.value {
+            Some(value) => self.value = Some(value + self.alpha * (price - value)),
+            None => {
+                self.seed.push(price);
+                if self.seed.len() == self.length {
+                    self.value = Some(self.seed.iter().sum::<f64>() / self.length as f64);
+                }
+            }
+        }
+        self.value
+    }
+}
+
+/// EMA crossover strategy with the same signal logic as `EmaCrossStrategy`,
+/// but with EMAs calculated locally from candle closes
+pub struct EmaCrossBacktest {
+    short_ema_length: i32,
+    long_ema_length: i32,
+    short_ema: Ema,
+    long_ema: Ema,
+    signal_generator: CrossoverSignal,
+}
+
+impl EmaCrossBacktest {
+    pub fn new(
+        short_ema_length: i32,
+        long_ema_length: i32,
+        hysteresis_percentage: f64,
+        hysteresis_periods: u32,
+    ) -> Self {
+        Self {
+            short_ema_length,
+            long_ema_length,
+            short_ema: Ema::new(short_ema_length.max(1) as usize),
+            long_ema: Ema::new(long_ema_length.max(1) as usize),
+            signal_generator: CrossoverSignal::new(hysteresis_percentage, hysteresis_periods),
+        }
+    }
+
+    pub fn from_config(config: &StrategyConfig) -> Self {
+        Self::new(
+            config.short_ema_length,
+            config.long_ema_length,
+            config.hysteresis_percentage,
+            config.hysteresis_periods,
+        )
+    }
+}
+
+impl BacktestStrategy for EmaCrossBacktest {
+    fn name(&self) -> String {
+        format!("EMA cross {}/{}", self.short_ema_length, self.long_ema_length)
+    }
+
+    fn on_candle(&mut self, candle: &Candle) -> Signal {
+        let short = self.short_ema.next(candle.close);
+        let long = self.long_ema.next(candle.close);
+
+        match (short, long) {
+            (Some(short), Some(long)) => self.signal_generator.update(short, long),
+            _ => Signal::Hold,
+        }
+    }
+}
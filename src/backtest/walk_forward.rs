@@ -0,0 +1,147 @@
+use crate::backtest::candles::Candle;
+use crate::backtest::engine::Backtester;
+use crate::backtest::optimizer::{self, Evaluation, InstrumentCandles, ParameterSet};
+use crate::config::Objective;
+use chrono::{DateTime, Utc};
+
+/// Result of optimizing on the training part of a window and
+/// evaluating the winner on the following out-of-sample part
+pub struct WalkForwardWindow {
+    pub train_from: DateTime<Utc>,
+    pub test_from: DateTime<Utc>,
+    pub test_to: DateTime<Utc>,
+    pub in_sample: Evaluation,
+    pub out_of_sample: Evaluation,
+}
+
+/// Splits candles into those before `from` (warm-up) and those within `[from, to)`
+fn split_period(candles: &[Candle], from: DateTime<Utc>, to: DateTime<Utc>) -> (&[Candle], &[Candle]) {
+    let start = candles.partition_point(|candle| candle.time < from);
+    let end = candles.partition_point(|candle| candle.time < to);
+    (&candles[..start], &candles[start..end])
+}
+
+/// Runs walk-forward analysis over consecutive non-overlapping windows
+pub fn run(
+    parameter_sets: &[ParameterSet],
+    universe: &[InstrumentCandles],
+    backtester: &Backtester,
+    objective: Objective,
+    windows: usize,
+    train_ratio: f64,
+) -> Vec<WalkForwardWindow> {
+    let start = universe.iter().filter_map(|i| i.candles.first()).map(|c| c.time).min();
+    let end = universe.iter().filter_map(|i| i.candles.last()).map(|c| c.time).max();
+    let (Some(start), Some(end)) = (start, end) else {
+        return Vec::new();
+    };
+
+    let windows = windows.max(1);
+    let window_length = (end - start) / windows as i32;
+    let train_length = chrono::Duration::seconds((window_length.num_seconds() as f64 * train_ratio.clamp(0.0, 1.0)) as i64);
+
+    let mut results = Vec::new();
+    for index in 0..windows {
+        let train_from = start + window_length * index as i32;
+        let test_from = train_from + train_length;
+        // The last window includes the final candle
+        let test_to = if index + 1 == windows { end + chrono::Duration::seconds(1) } else { train_from + window_length };
+
+        let ranking = optimizer::optimize(parameter_sets, universe, backtester, objective, |candles| {
+            split_period(candles, train_from, test_from)
+        });
+        let Some(in_sample) = ranking.into_iter().next() else {
+            continue;
+        };
+
+        let out_of_sample = optimizer::evaluate(in_sample.parameters, universe, backtester, objective, |candles| {
+            split_period(candles, test_from, test_to)
+        });
+
+        results.push(WalkForwardWindow {
+            train_from,
+            test_from,
+            test_to,
+            in_sample,
+            out_of_sample,
+        });
+    }
+
+    results
+}
+
+/// Prints per-window winners with in-sample and out-of-sample scores
+pub fn print_report(windows: &[WalkForwardWindow]) {
+    println!("Walk-forward analysis:");
+    for (index, window) in windows.iter().enumerate() {
+        println!(
+            "  Window {}: train {} .. {}, test .. {}",
+            index + 1,
+            window.train_from.format("%Y-%m-%d"),
+            window.test_from.format("%Y-%m-%d"),
+            window.test_to.format("%Y-%m-%d")
+        );
+        println!("    Best: {}", window.in_sample.parameters);
+        println!(
+            "    In-sample score {:.3} (CAGR {:.2}%), out-of-sample score {:.3} (CAGR {:.2}%, max DD {:.2}%, trades {})",
+            window.in_sample.score,
+            window.in_sample.metrics.cagr_percentage,
+            window.out_of_sample.score,
+            window.out_of_sample.metrics.cagr_percentage,
+            window.out_of_sample.metrics.max_drawdown_percentage,
+            window.out_of_sample.metrics.trades
+        );
+    }
+
+    if !windows.is_empty() {
+        let avg_score = windows.iter().map(|w| w.out_of_sample.score).sum::<f64>() / windows.len() as f64;
+        println!("  Average out-of-sample score: {:.3}", avg_score);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap()
+    }
+
+    fn candles(days: std::ops::RangeInclusive<u32>) -> Vec<Candle> {
+        days.map(|d| Candle {
+            time: day(d),
+            open: 100.0,
+            high: 101.0,
+            low: 99.0,
+            close: 100.0,
+            volume: 10,
+        })
+        .collect()
+    }
+
+    fn days(candles: &[Candle]) -> Vec<u32> {
+        candles.iter().map(|candle| candle.time.format("%d").to_string().parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn split_period_warms_up_before_from_and_trades_until_to() {
+        let candles = candles(1..=10);
+        let (warm_up, period) = split_period(&candles, day(4), day(8));
+        assert_eq!(days(warm_up), vec![1, 2, 3]);
+        assert_eq!(days(period), vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn split_period_outside_the_candles() {
+        let candles = candles(1..=10);
+
+        let (warm_up, period) = split_period(&candles, day(20), day(25));
+        assert_eq!(warm_up.len(), 10);
+        assert!(period.is_empty());
+
+        let (warm_up, period) = split_period(&candles, day(1), day(1));
+        assert!(warm_up.is_empty());
+        assert!(period.is_empty());
+    }
+}
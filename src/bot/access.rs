@@ -0,0 +1,126 @@
+use crate::bot::commands::CommandContext;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+/// Subscription request waiting for an admin decision
+#[derive(Debug, Clone)]
+pub struct PendingRequest {
+    pub chat_id: ChatId,
+    pub name: String,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// Pending subscription requests by chat ID
+pub type PendingRequests = Arc<Mutex<HashMap<i64, PendingRequest>>>;
+
+/// User who sent the message, the chat itself for channel posts
+pub fn sender_id(message: &Message) -> i64 {
+    message.from.as_ref().map(|user| user.id.0 as i64).unwrap_or(message.chat.id.0)
+}
+
+/// Returns true if the chat or its sender is allowed by config, or the chat was approved by an admin
+pub async fn is_allowed(context: &CommandContext, message: &Message) -> bool {
+    let approved = context.subscribers.lock().await.is_approved(message.chat.id);
+    let config = context.config.read().await;
+    approved || config.telegram.is_allowed(message.chat.id.0) || config.telegram.is_allowed(sender_id(message))
+}
+
+pub async fn is_admin(context: &CommandContext, message: &Message) -> bool {
+    context.config.read().await.telegram.is_admin(sender_id(message))
+}
+
+/// Forwards a subscription request to every admin with approve and reject buttons
+pub async fn request_approval(bot: &Bot, context: &CommandContext, message: &Message) -> String {
+    let admins = context.config.read().await.telegram.admin_ids.clone();
+    if admins.is_empty() {
+        info!("Rejected subscription from chat {}: not in the allowlist", message.chat.id);
+        return "⛔ Access denied".to_string();
+    }
+
+    let chat_id = message.chat.id;
+    let name = match (&message.from, message.chat.username()) {
+        (Some(user), _) => match &user.username {
+            Some(username) => format!("{} (@{})", user.full_name(), username),
+            None => user.full_name(),
+        },
+        (None, Some(username)) => format!("@{}", username),
+        (None, None) => chat_id.to_string(),
+    };
+
+    {
+        let mut pending = context.pending.lock().await;
+        if pending.contains_key(&chat_id.0) {
+            return "Your subscription request is waiting for approval".to_string();
+        }
+        pending.insert(chat_id.0, PendingRequest {
+            chat_id,
+            name: name.clone(),
+            requested_at: Utc::now(),
+        });
+    }
+
+    let keyboard = InlineKeyboardMarkup::new([[
+        InlineKeyboardButton::callback("✅ Approve", format!("approve:{}", chat_id.0)),
+        InlineKeyboardButton::callback("❌ Reject", format!("reject:{}", chat_id.0)),
+    ]]);
+    let text = format!("Subscription request from {}\nChat ID: {}", name, chat_id);
+
+    for admin in admins {
+        if let Err(err) = bot.send_message(ChatId(admin), text.as_str()).reply_markup(keyboard.clone()).await {
+            error!("Error sending subscription request to admin {}: {}", admin, err);
+        }
+    }
+
+    info!("Subscription request from {} ({}) sent to admins", name, chat_id);
+    "Your subscription request has been sent to the administrators".to_string()
+}
+
+/// Handles approve and reject buttons of subscription requests
+pub async fn handle_approval(bot: Bot, query: CallbackQuery, context: CommandContext) -> ResponseResult<()> {
+    let Some((action, chat_id)) = query
+        .data
+        .as_deref()
+        .and_then(|data| data.split_once(':'))
+        .and_then(|(action, id)| Some((action, ChatId(id.parse().ok()?))))
+    else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+
+    if !context.config.read().await.telegram.is_admin(query.from.id.0 as i64) {
+        bot.answer_callback_query(query.id).text("Only admins can approve requests").await?;
+        return Ok(());
+    }
+
+    let Some(request) = context.pending.lock().await.remove(&chat_id.0) else {
+        bot.answer_callback_query(query.id).text("The request was already handled").await?;
+        return Ok(());
+    };
+
+    let admin = query.from.full_name();
+    let result = if action == "approve" {
+        context.subscribers.lock().await.insert(chat_id, true);
+        info!("Subscription of {} ({}) approved by {}", request.name, chat_id, admin);
+        if let Err(err) = bot.send_message(chat_id, "✅ Your subscription to trading signals has been approved").await {
+            error!("Error notifying chat {} about approval: {}", chat_id, err);
+        }
+        format!("✅ {} approved by {}", request.name, admin)
+    } else {
+        info!("Subscription of {} ({}) rejected by {}", request.name, chat_id, admin);
+        if let Err(err) = bot.send_message(chat_id, "❌ Your subscription request has been rejected").await {
+            error!("Error notifying chat {} about rejection: {}", chat_id, err);
+        }
+        format!("❌ {} rejected by {}", request.name, admin)
+    };
+
+    bot.answer_callback_query(query.id).await?;
+    if let Some(message) = &query.message {
+        bot.edit_message_text(message.chat().id, message.id(), result).await?;
+    }
+    Ok(())
+}
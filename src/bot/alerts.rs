@@ -0,0 +1,573 @@
+use crate::market_data_service::get_candles::{CandleInterval, GetCandlesResponse};
+use crate::market_data_service::get_last_prices::{
+    GetLastPricesRequest, GetLastPricesResponse, InstrumentStatus, LastPriceType,
+};
+use crate::market_data_service::get_tech_analysis::{
+    GetTechAnalysisRequest, GetTechAnalysisResponse, IndicatorInterval, IndicatorType, TypeOfPrice,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use teloxide::types::ChatId;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+pub type Alerts = Arc<Mutex<AlertStore>>;
+
+/// RSI length used when the alert doesn't specify one
+const DEFAULT_RSI_LENGTH: i32 = 14;
+
+/// What an alert waits for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    Above { price: f64 },
+    Below { price: f64 },
+    /// Price crosses the level in either direction
+    Cross { price: f64 },
+    /// Absolute change from the day open in percent
+    Move { percentage: f64 },
+    RsiAbove { length: i32, value: f64 },
+    RsiBelow { length: i32, value: f64 },
+}
+
+impl AlertCondition {
+    /// Parses conditions like "> 300", "cross 300", "move 3%" or "rsi14 < 30"
+    pub fn parse(tokens: &[&str]) -> Option<Self> {
+        let number = |text: &str| text.trim_end_matches('%').replace(',', ".").parse::<f64>().ok().filter(|v| *v > 0.0);
+
+        match tokens {
+            [">" | "above", price] => Some(Self::Above { price: number(price)? }),
+            ["<" | "below", price] => Some(Self::Below { price: number(price)? }),
+            ["cross" | "crosses", price] => Some(Self::Cross { price: number(price)? }),
+            ["move" | "moves", percentage] => Some(Self::Move { percentage: number(percentage)? }),
+            [indicator, operator, value] => {
+                let length = match indicator.strip_prefix("rsi")? {
+                    "" => DEFAULT_RSI_LENGTH,
+                    length => length.parse().ok().filter(|l| *l > 1)?,
+                };
+                let value = number(value).filter(|v| *v < 100.0)?;
+                match *operator {
+                    ">" | "above" => Some(Self::RsiAbove { length, value }),
+                    "<" | "below" => Some(Self::RsiBelow { length, value }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// True if the condition holds for the observed value, crossings are checked separately
+    fn holds(&self, value: f64) -> bool {
+        match *self {
+            Self::Above { price } => value >= price,
+            Self::Below { price } => value <= price,
+            Self::Cross { .. } => false,
+            Self::Move { percentage } => value.abs() >= percentage,
+            Self::RsiAbove { value: level, .. } => value >= level,
+            Self::RsiBelow { value: level, .. } => value <= level,
+        }
+    }
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Above { price } => write!(f, "price > {}", price),
+            Self::Below { price } => write!(f, "price < {}", price),
+            Self::Cross { price } => write!(f, "price crosses {}", price),
+            Self::Move { percentage } => write!(f, "move ±{}% from day open", percentage),
+            Self::RsiAbove { length, value } => write!(f, "RSI({}) > {}", length, value),
+            Self::RsiBelow { length, value } => write!(f, "RSI({}) < {}", length, value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: u64,
+    pub chat_id: i64,
+    pub ticker: String,
+    pub instrument_uid: String,
+    pub condition: AlertCondition,
+    /// Repeating alerts stay after triggering and fire again once the condition was false
+    pub repeat: bool,
+    pub created_at: DateTime<Utc>,
+    /// Value observed on the previous check
+    #[serde(default)]
+    pub last_value: Option<f64>,
+    /// The condition held on the previous check
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub triggered_at: Option<DateTime<Utc>>,
+}
+
+/// Alert that fired on this check
+#[derive(Debug, Clone)]
+pub struct TriggeredAlert {
+    pub alert: Alert,
+    pub last_price: f64,
+    /// Observed value: price, change in percent or RSI
+    pub value: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredAlerts {
+    next_id: u64,
+    alerts: Vec<Alert>,
+}
+
+/// Alerts of all chats persisted to a JSON file on every change
+pub struct AlertStore {
+    path: PathBuf,
+    next_id: u64,
+    alerts: BTreeMap<u64, Alert>,
+}
+
+impl AlertStore {
+    /// Loads alerts from the file, starts empty if it doesn't exist yet
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let path = PathBuf::from(path);
+        let stored: StoredAlerts = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            StoredAlerts::default()
+        };
+        info!("Loaded {} alerts from {}", stored.alerts.len(), path.display());
+
+        Ok(Self {
+            path,
+            next_id: stored.next_id.max(1),
+            alerts: stored.alerts.into_iter().map(|a| (a.id, a)).collect(),
+        })
+    }
+
+    /// Writes the file atomically via a temporary file
+    fn save(&self) {
+        let result = (|| -> Result<(), Box<dyn Error>> {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let stored = StoredAlerts {
+                next_id: self.next_id,
+                alerts: self.alerts.values().cloned().collect(),
+            };
+            let tmp = self.path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_string_pretty(&stored)?)?;
+            fs::rename(tmp, &self.path)?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            error!("Error saving alerts to {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Adds an alert and returns its ID
+    pub fn add(&mut self, chat_id: ChatId, ticker: &str, instrument_uid: &str, condition: AlertCondition, repeat: bool) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.alerts.insert(id, Alert {
+            id,
+            chat_id: chat_id.0,
+            ticker: ticker.to_string(),
+            instrument_uid: instrument_uid.to_string(),
+            condition,
+            repeat,
+            created_at: Utc::now(),
+            last_value: None,
+            active: false,
+            triggered_at: None,
+        });
+        self.save();
+        id
+    }
+
+    pub fn for_chat(&self, chat_id: ChatId) -> Vec<&Alert> {
+        self.alerts.values().filter(|a| a.chat_id == chat_id.0).collect()
+    }
+
+    /// Removes an alert of the chat, returns false if the chat has no such alert
+    pub fn remove(&mut self, chat_id: ChatId, id: u64) -> bool {
+        if self.alerts.get(&id).is_none_or(|a| a.chat_id != chat_id.0) {
+            return false;
+        }
+        self.alerts.remove(&id);
+        self.save();
+        true
+    }
+
+    /// Removes every alert of the chat and returns how many were removed
+    pub fn clear(&mut self, chat_id: ChatId) -> usize {
+        let before = self.alerts.len();
+        self.alerts.retain(|_, a| a.chat_id != chat_id.0);
+        let removed = before - self.alerts.len();
+        if removed > 0 {
+            self.save();
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.alerts.len()
+    }
+}
+
+/// Market data needed to evaluate the alerts of one check
+#[derive(Default)]
+struct Observations {
+    prices: HashMap<String, f64>,
+    day_opens: HashMap<String, f64>,
+    rsi: HashMap<(String, i32), f64>,
+}
+
+impl Observations {
+    /// Value the condition of the alert is compared with, None if the data is missing
+    fn value(&self, alert: &Alert) -> Option<f64> {
+        let uid = &alert.instrument_uid;
+        match alert.condition {
+            AlertCondition::Above { .. } | AlertCondition::Below { .. } | AlertCondition::Cross { .. } => {
+                self.prices.get(uid).copied()
+            }
+            AlertCondition::Move { .. } => {
+                let open = *self.day_opens.get(uid).filter(|open| **open > 0.0)?;
+                Some((self.prices.get(uid)? / open - 1.0) * 100.0)
+            }
+            AlertCondition::RsiAbove { length, .. } | AlertCondition::RsiBelow { length, .. } => {
+                self.rsi.get(&(uid.clone(), length)).copied()
+            }
+        }
+    }
+}
+
+impl Alert {
+    /// Records the observed value and returns true if the alert fires:
+    /// crossings fire whenever the value moves to the other side of the level since the previous check,
+    /// other conditions fire when they start to hold
+    fn observe(&mut self, value: f64) -> bool {
+        let fires = match self.condition {
+            AlertCondition::Cross { price } => self.last_value.is_some_and(|previous| (previous < price) != (value < price)),
+            condition => {
+                let holds = condition.holds(value);
+                let fires = holds && !self.active;
+                self.active = holds;
+                fires
+            }
+        };
+        self.last_value = Some(value);
+        fires
+    }
+}
+
+impl AlertStore {
+    /// Applies the observations to the alerts of the snapshot and returns the fired ones,
+    /// one-shot alerts are removed unless `keep_fired` is set
+    fn apply(&mut self, snapshot: Vec<Alert>, observations: &Observations, keep_fired: bool) -> Vec<TriggeredAlert> {
+        let mut triggered = Vec::new();
+        for checked in snapshot {
+            let Some(value) = observations.value(&checked) else {
+                continue;
+            };
+            // The alert may have been deleted while market data was loading
+            let Some(alert) = self.alerts.get_mut(&checked.id) else {
+                continue;
+            };
+            if !alert.observe(value) {
+                continue;
+            }
+
+            info!("Alert {} triggered for chat {}: {} {}", alert.id, alert.chat_id, alert.ticker, alert.condition);
+            alert.triggered_at = Some(Utc::now());
+            triggered.push(TriggeredAlert {
+                alert: alert.clone(),
+                last_price: observations.prices.get(&alert.instrument_uid).copied().unwrap_or_default(),
+                value,
+            });
+            if !alert.repeat && !keep_fired {
+                self.alerts.remove(&checked.id);
+            }
+        }
+        triggered
+    }
+}
+
+/// Checks all alerts against the last prices and indicators,
+/// removes triggered one-shot alerts and returns the alerts to notify about.
+/// A dry run keeps one-shot alerts and doesn't write the alerts file
+pub async fn check_alerts(
+    client: &reqwest::Client,
+    token: &str,
+    alerts: &Alerts,
+    interval: IndicatorInterval,
+    dry_run: bool,
+) -> Result<Vec<TriggeredAlert>, Box<dyn Error>> {
+    let snapshot: Vec<Alert> = alerts.lock().await.alerts.values().cloned().collect();
+    if snapshot.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let observations = observe(client, token, &snapshot, interval).await?;
+
+    let mut store = alerts.lock().await;
+    let triggered = store.apply(snapshot, &observations, dry_run);
+    if !dry_run {
+        store.save();
+    }
+
+    Ok(triggered)
+}
+
+/// Loads last prices for every alert, day opens and RSI only where alerts need them
+async fn observe(
+    client: &reqwest::Client,
+    token: &str,
+    alerts: &[Alert],
+    interval: IndicatorInterval,
+) -> Result<Observations, Box<dyn Error>> {
+    let mut observations = Observations::default();
+
+    let mut uids: Vec<String> = alerts.iter().map(|a| a.instrument_uid.clone()).collect();
+    uids.sort();
+    uids.dedup();
+    let request = GetLastPricesRequest::new(uids, LastPriceType::Unspecified, InstrumentStatus::Base);
+    for price in GetLastPricesResponse::get_last_prices(client, token, request).await?.last_prices {
+        observations.prices.insert(price.instrument_uid, price.price.to_f64());
+    }
+
+    for alert in alerts {
+        let uid = &alert.instrument_uid;
+        match alert.condition {
+            AlertCondition::Move { .. } if !observations.day_opens.contains_key(uid) => {
+                match day_open(client, token, uid).await {
+                    Ok(Some(open)) => {
+                        observations.day_opens.insert(uid.clone(), open);
+                    }
+                    Ok(None) => info!("No day candle yet for {}", alert.ticker),
+                    Err(e) => error!("Error getting day open for {}: {}", alert.ticker, e),
+                }
+            }
+            AlertCondition::RsiAbove { length, .. } | AlertCondition::RsiBelow { length, .. }
+                if !observations.rsi.contains_key(&(uid.clone(), length)) =>
+            {
+                match last_rsi(client, token, uid, interval, length).await {
+                    Ok(Some(rsi)) => {
+                        observations.rsi.insert((uid.clone(), length), rsi);
+                    }
+                    Ok(None) => info!("No RSI({}) data for {}", length, alert.ticker),
+                    Err(e) => error!("Error getting RSI({}) for {}: {}", length, alert.ticker, e),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(observations)
+}
+
+/// Open price of today's daily candle
+async fn day_open(client: &reqwest::Client, token: &str, uid: &str) -> Result<Option<f64>, Box<dyn Error>> {
+    let now = Utc::now();
+    let candles = GetCandlesResponse::get_candles_range(client, token, uid, now - Duration::days(1), now, CandleInterval::Day).await?;
+    Ok(candles
+        .iter()
+        .filter_map(|c| Some((DateTime::parse_from_rfc3339(&c.time).ok()?, c.open.to_f64())))
+        .filter(|(time, _)| time.with_timezone(&Utc).date_naive() == now.date_naive())
+        .map(|(_, open)| open)
+        .next_back())
+}
+
+/// Most recent RSI value of the interval
+async fn last_rsi(
+    client: &reqwest::Client,
+    token: &str,
+    uid: &str,
+    interval: IndicatorInterval,
+    length: i32,
+) -> Result<Option<f64>, Box<dyn Error>> {
+    let request = GetTechAnalysisRequest::new_auto_period(IndicatorType::RSI, uid, interval, TypeOfPrice::Close, length);
+    let response = GetTechAnalysisResponse::get_tech_analysis(client, token, request).await?;
+    Ok(response
+        .technical_indicators
+        .iter()
+        .rev()
+        .find_map(|i| i.signal.as_ref())
+        .map(|value| value.to_f64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Option<AlertCondition> {
+        AlertCondition::parse(&text.split_whitespace().collect::<Vec<_>>())
+    }
+
+    fn store(name: &str) -> AlertStore {
+        let path = std::env::temp_dir().join(format!("bcp_alerts_{}_{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        AlertStore::load(path.to_str().unwrap()).unwrap()
+    }
+
+    fn alert(condition: AlertCondition, repeat: bool) -> Alert {
+        Alert {
+            id: 1,
+            chat_id: 1,
+            ticker: "SBER".to_string(),
+            instrument_uid: "sber-uid".to_string(),
+            condition,
+            repeat,
+            created_at: Utc::now(),
+            last_value: None,
+            active: false,
+            triggered_at: None,
+        }
+    }
+
+    /// Fire results of observing the values one after another
+    fn fires(condition: AlertCondition, values: &[f64]) -> Vec<bool> {
+        let mut alert = alert(condition, true);
+        values.iter().map(|value| alert.observe(*value)).collect()
+    }
+
+    fn prices(price: f64) -> Observations {
+        Observations {
+            prices: HashMap::from([("sber-uid".to_string(), price)]),
+            ..Observations::default()
+        }
+    }
+
+    #[test]
+    fn parses_price_conditions() {
+        assert_eq!(parse("> 300"), Some(AlertCondition::Above { price: 300.0 }));
+        assert_eq!(parse("above 300.5"), Some(AlertCondition::Above { price: 300.5 }));
+        assert_eq!(parse("< 280,5"), Some(AlertCondition::Below { price: 280.5 }));
+        assert_eq!(parse("below 280"), Some(AlertCondition::Below { price: 280.0 }));
+        assert_eq!(parse("cross 300"), Some(AlertCondition::Cross { price: 300.0 }));
+        assert_eq!(parse("crosses 300"), Some(AlertCondition::Cross { price: 300.0 }));
+        assert_eq!(parse("move 3%"), Some(AlertCondition::Move { percentage: 3.0 }));
+        assert_eq!(parse("moves 1.5"), Some(AlertCondition::Move { percentage: 1.5 }));
+    }
+
+    #[test]
+    fn parses_rsi_conditions() {
+        assert_eq!(parse("rsi < 30"), Some(AlertCondition::RsiBelow { length: DEFAULT_RSI_LENGTH, value: 30.0 }));
+        assert_eq!(parse("rsi14 < 30"), Some(AlertCondition::RsiBelow { length: 14, value: 30.0 }));
+        assert_eq!(parse("rsi7 above 70"), Some(AlertCondition::RsiAbove { length: 7, value: 70.0 }));
+    }
+
+    #[test]
+    fn rejects_invalid_conditions() {
+        for text in [
+            "",
+            "> ",
+            "> abc",
+            "> 0",
+            "> -5",
+            "= 300",
+            "300",
+            "cross",
+            "rsi1 < 30",
+            "rsix < 30",
+            "rsi14 < 100",
+            "rsi14 = 30",
+            "ema14 < 30",
+            "> 300 400",
+        ] {
+            assert_eq!(parse(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn level_fires_when_condition_starts_to_hold() {
+        let above = AlertCondition::Above { price: 300.0 };
+        assert_eq!(fires(above, &[290.0, 300.0, 310.0, 295.0, 305.0]), [false, true, false, false, true]);
+        // Holding on the first check fires immediately
+        assert_eq!(fires(above, &[310.0, 320.0]), [true, false]);
+
+        let below = AlertCondition::Below { price: 280.0 };
+        assert_eq!(fires(below, &[290.0, 270.0, 260.0, 285.0, 280.0]), [false, true, false, false, true]);
+    }
+
+    #[test]
+    fn move_fires_in_both_directions() {
+        let condition = AlertCondition::Move { percentage: 3.0 };
+        assert_eq!(fires(condition, &[1.0, -3.5, -4.0, 0.5, 3.0]), [false, true, false, false, true]);
+    }
+
+    #[test]
+    fn cross_fires_on_every_side_change() {
+        let condition = AlertCondition::Cross { price: 300.0 };
+        // Nothing to compare with on the first check
+        assert_eq!(fires(condition, &[310.0, 305.0, 295.0, 290.0, 300.0, 299.0]), [false, false, true, false, true, true]);
+    }
+
+    #[test]
+    fn one_shot_alert_is_removed_after_firing() {
+        let mut store = store("one_shot");
+        store.add(ChatId(1), "SBER", "sber-uid", AlertCondition::Above { price: 300.0 }, false);
+
+        let snapshot: Vec<Alert> = store.alerts.values().cloned().collect();
+        assert!(store.apply(snapshot, &prices(290.0), false).is_empty());
+        assert_eq!(store.len(), 1);
+
+        let snapshot: Vec<Alert> = store.alerts.values().cloned().collect();
+        let triggered = store.apply(snapshot, &prices(305.0), false);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].last_price, 305.0);
+        assert_eq!(triggered[0].value, 305.0);
+        assert_eq!(store.len(), 0);
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn repeating_alert_fires_again_after_condition_stopped_holding() {
+        let mut store = store("repeat");
+        store.add(ChatId(1), "SBER", "sber-uid", AlertCondition::Above { price: 300.0 }, true);
+
+        let mut check = |price: f64| {
+            let snapshot: Vec<Alert> = store.alerts.values().cloned().collect();
+            store.apply(snapshot, &prices(price), false).len()
+        };
+        assert_eq!([check(305.0), check(310.0), check(290.0), check(301.0)], [1, 0, 0, 1]);
+        assert_eq!(store.len(), 1);
+        assert!(store.alerts[&1].triggered_at.is_some());
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn dry_run_keeps_fired_one_shot_alerts() {
+        let mut store = store("dry_run");
+        store.add(ChatId(1), "SBER", "sber-uid", AlertCondition::Cross { price: 300.0 }, false);
+
+        for price in [290.0, 310.0] {
+            let snapshot: Vec<Alert> = store.alerts.values().cloned().collect();
+            store.apply(snapshot, &prices(price), true);
+        }
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.alerts[&1].last_value, Some(310.0));
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn missing_data_and_deleted_alerts_are_skipped() {
+        let mut store = store("missing");
+        let id = store.add(ChatId(1), "SBER", "sber-uid", AlertCondition::Move { percentage: 1.0 }, false);
+
+        // No day open for the move alert
+        let snapshot: Vec<Alert> = store.alerts.values().cloned().collect();
+        assert!(store.apply(snapshot.clone(), &prices(305.0), false).is_empty());
+        assert_eq!(store.alerts[&id].last_value, None);
+
+        store.remove(ChatId(1), id);
+        let mut observations = prices(305.0);
+        observations.day_opens.insert("sber-uid".to_string(), 300.0);
+        assert!(store.apply(snapshot, &observations, false).is_empty());
+        let _ = fs::remove_file(&store.path);
+    }
+}
@@ -0,0 +1,693 @@
+use crate::bot::access::{self, PendingRequests};
+use crate::bot::alerts::{AlertCondition, Alerts};
+use crate::bot::confirmation::SharedTrades;
+use crate::bot::notifier::Subscribers;
+use crate::bot::signal::Signal;
+use crate::bot::status::{format_duration, SharedStatus};
+use crate::bot::messages::Language;
+use crate::bot::subscribers::Preferences;
+use crate::bot::trade::EmaCrossStrategy;
+use crate::config::SharedConfig;
+use crate::instruments::cache::{CachedInstrument, SharedInstruments};
+use crate::instruments::lookup::InstrumentLookup;
+use chrono::Utc;
+use teloxide::prelude::*;
+use teloxide::utils::command::BotCommands;
+use tracing::{error, info};
+
+/// Telegram message length limit
+const MAX_MESSAGE_LENGTH: usize = 4096;
+
+const NOT_SUBSCRIBED: &str = "You are not subscribed. Send /start first";
+
+/// Maximum number of alerts per chat
+const MAX_ALERTS: usize = 20;
+
+const ALERT_USAGE: &str = "Usage: /alert TICKER CONDITION [repeat]\n\
+    /alert SBER > 300\n\
+    /alert SBER < 280\n\
+    /alert SBER cross 300\n\
+    /alert GAZP move 3\n\
+    /alert LKOH rsi14 < 30 repeat";
+
+/// Maximum number of /find matches in a reply
+const FIND_RESULTS: usize = 10;
+
+/// Strategy names accepted by /strategies
+const KNOWN_STRATEGIES: &[&str] = &[EmaCrossStrategy::NAME];
+
+/// These commands are supported:
+#[derive(BotCommands, Clone, Debug)]
+#[command(rename_rule = "lowercase")]
+pub enum Command {
+    /// Subscribe to trading signals
+    Start,
+    /// Unsubscribe from trading signals
+    Stop,
+    /// Scanner uptime, last scan and errors
+    Status,
+    /// Current universe with last price and EMA state
+    List,
+    /// Evaluate an instrument now, e.g. /signal SBER
+    Signal(String),
+    /// Search instruments by ticker, name or ISIN, e.g. /find sber
+    Find(String),
+    /// Add tickers to your watchlist, e.g. /watch SBER GAZP
+    Watch(String),
+    /// Remove tickers from your watchlist, /unwatch all clears it
+    Unwatch(String),
+    /// Show your watchlist and signal filters
+    Watchlist,
+    /// Signal directions to receive: buy, sell or both
+    Directions(String),
+    /// Strategies to receive signals from, e.g. /strategies ema_cross or all
+    Strategies(String),
+    /// Minimum EMA difference in percent, e.g. /strength 0.5
+    Strength(String),
+    /// Language of signal messages: en or ru
+    Language(String),
+    /// Price or RSI alert, e.g. /alert SBER > 300 or /alert LKOH rsi14 < 30 repeat
+    Alert(String),
+    /// Show your alerts
+    Alerts,
+    /// Delete an alert by ID, /delalert all deletes every alert
+    Delalert(String),
+    /// Pause market scanning (admins)
+    Pause,
+    /// Resume market scanning and release the kill switch (admins)
+    Resume,
+    /// Stop scanning and signal delivery immediately (admins)
+    Kill,
+    /// Reload the config file before the next scan (admins)
+    Reload,
+    /// Subscription requests waiting for approval (admins)
+    Pending,
+    /// Show this help
+    Help,
+}
+
+impl Command {
+    /// Control commands available to admins only
+    pub fn is_admin_only(&self) -> bool {
+        matches!(
+            self,
+            Command::Pause | Command::Resume | Command::Kill | Command::Reload | Command::Pending
+        )
+    }
+}
+
+/// Shared state available to command handlers
+#[derive(Clone)]
+pub struct CommandContext {
+    pub subscribers: Subscribers,
+    pub status: SharedStatus,
+    pub client: reqwest::Client,
+    pub config: SharedConfig,
+    pub pending: PendingRequests,
+    pub trades: SharedTrades,
+    pub alerts: Alerts,
+    pub instruments: SharedInstruments,
+}
+
+pub async fn handle_command(
+    bot: Bot,
+    message: Message,
+    command: Command,
+    context: CommandContext,
+) -> ResponseResult<()> {
+    let chat_id = message.chat.id;
+    info!("Received command {:?} from chat {}", command, chat_id);
+
+    if !matches!(command, Command::Start | Command::Help) && !access::is_allowed(&context, &message).await {
+        return send_long_message(&bot, chat_id, "⛔ Access denied. Send /start to request access").await;
+    }
+    if command.is_admin_only() && !access::is_admin(&context, &message).await {
+        info!("Denied {:?} to user {} in chat {}", command, access::sender_id(&message), chat_id);
+        return send_long_message(&bot, chat_id, "⛔ This command is available to admins only").await;
+    }
+
+    let reply = match command {
+        Command::Start => {
+            if context.subscribers.lock().await.contains(chat_id) {
+                "You are already subscribed to trading signals".to_string()
+            } else {
+                // Subscriptions are checked per chat, an admin subscribing a chat approves it
+                let telegram = context.config.read().await.telegram.clone();
+                let approved = !telegram.is_allowed(chat_id.0) && telegram.is_admin(access::sender_id(&message));
+                if telegram.is_allowed(chat_id.0) || approved {
+                    context.subscribers.lock().await.insert(chat_id, approved);
+                    info!("New subscriber: {}", chat_id);
+                    "✅ You have subscribed to trading signals!".to_string()
+                } else {
+                    access::request_approval(&bot, &context, &message).await
+                }
+            }
+        }
+        Command::Stop => {
+            context.subscribers.lock().await.remove(chat_id);
+            info!("Subscriber left: {}", chat_id);
+            "You have unsubscribed from trading signals".to_string()
+        }
+        Command::Status => status_text(&context).await,
+        Command::List => list_text(&context).await,
+        Command::Signal(ticker) => signal_text(&context, ticker.trim()).await,
+        Command::Find(query) => find_text(&context, query.trim()).await,
+        Command::Watch(tickers) => watch(&context, chat_id, &tickers).await,
+        Command::Unwatch(tickers) => {
+            let tickers = parse_tickers(&tickers);
+            update_preferences(&context, chat_id, |preferences| {
+                if tickers.iter().any(|t| t == "ALL") {
+                    preferences.watchlist.clear();
+                } else {
+                    for ticker in &tickers {
+                        preferences.watchlist.remove(ticker);
+                    }
+                }
+            })
+            .await
+        }
+        Command::Watchlist => match context.subscribers.lock().await.preferences(chat_id) {
+            Some(preferences) => preferences_text(preferences),
+            None => NOT_SUBSCRIBED.to_string(),
+        },
+        Command::Directions(directions) => {
+            let (buy, sell) = match directions.trim().to_lowercase().as_str() {
+                "buy" => (true, false),
+                "sell" => (false, true),
+                "both" | "all" => (true, true),
+                _ => return send_long_message(&bot, chat_id, "Usage: /directions buy|sell|both").await,
+            };
+            update_preferences(&context, chat_id, |preferences| {
+                preferences.buy = buy;
+                preferences.sell = sell;
+            })
+            .await
+        }
+        Command::Strategies(strategies) => {
+            let strategies: Vec<String> = strategies.split_whitespace().map(|s| s.to_lowercase()).collect();
+            if let Some(unknown) = strategies.iter().find(|s| *s != "all" && !KNOWN_STRATEGIES.contains(&s.as_str())) {
+                return send_long_message(
+                    &bot,
+                    chat_id,
+                    &format!("Unknown strategy {}. Available: {}", unknown, KNOWN_STRATEGIES.join(", ")),
+                )
+                .await;
+            }
+            update_preferences(&context, chat_id, |preferences| {
+                preferences.strategies.clear();
+                if !strategies.iter().any(|s| s == "all") {
+                    preferences.strategies.extend(strategies);
+                }
+            })
+            .await
+        }
+        Command::Strength(value) => match value.trim().replace(',', ".").parse::<f64>() {
+            Ok(min_strength) if min_strength >= 0.0 => {
+                update_preferences(&context, chat_id, |preferences| preferences.min_strength = min_strength).await
+            }
+            _ => "Usage: /strength 0.5 (minimum EMA difference in percent)".to_string(),
+        },
+        Command::Language(code) => match Language::parse(&code) {
+            Some(language) => {
+                let updated = context
+                    .subscribers
+                    .lock()
+                    .await
+                    .update_preferences(chat_id, |preferences| preferences.language = language);
+                if updated {
+                    language.catalog().language_set.to_string()
+                } else {
+                    NOT_SUBSCRIBED.to_string()
+                }
+            }
+            None => format!(
+                "Usage: /language {}",
+                Language::ALL.iter().map(|l| l.code()).collect::<Vec<_>>().join("|")
+            ),
+        },
+        Command::Alert(text) => add_alert(&context, chat_id, &text).await,
+        Command::Alerts => {
+            let alerts = context.alerts.lock().await;
+            let alerts = alerts.for_chat(chat_id);
+            if alerts.is_empty() {
+                "You have no alerts. Add one with /alert".to_string()
+            } else {
+                alerts
+                    .iter()
+                    .map(|a| format!("#{} {} {}{}", a.id, a.ticker, a.condition, if a.repeat { " (repeat)" } else { "" }))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        Command::Delalert(id) => {
+            let id = id.trim().trim_start_matches('#');
+            if id.eq_ignore_ascii_case("all") {
+                format!("Deleted {} alerts", context.alerts.lock().await.clear(chat_id))
+            } else {
+                match id.parse::<u64>() {
+                    Ok(id) if context.alerts.lock().await.remove(chat_id, id) => format!("Alert #{} deleted", id),
+                    Ok(id) => format!("You have no alert #{}", id),
+                    Err(_) => "Usage: /delalert ID|all".to_string(),
+                }
+            }
+        }
+        Command::Pause => {
+            context.status.write().await.paused = true;
+            info!("Scanning paused by chat {}", chat_id);
+            "⏸ Scanning paused".to_string()
+        }
+        Command::Resume => {
+            let mut status = context.status.write().await;
+            status.paused = false;
+            status.kill_switch = false;
+            info!("Scanning resumed by chat {}", chat_id);
+            "▶️ Scanning resumed".to_string()
+        }
+        Command::Kill => {
+            let mut status = context.status.write().await;
+            status.kill_switch = true;
+            status.paused = true;
+            info!("Kill switch engaged by user {} in chat {}", access::sender_id(&message), chat_id);
+            "🛑 Kill switch engaged: scanning and signal delivery stopped. Send /resume to continue".to_string()
+        }
+        Command::Reload => {
+            context.status.write().await.reload_requested = true;
+            info!("Config reload requested by chat {}", chat_id);
+            "🔄 Config will be reloaded before the next scan".to_string()
+        }
+        Command::Pending => {
+            let pending = context.pending.lock().await;
+            if pending.is_empty() {
+                "No pending subscription requests".to_string()
+            } else {
+                let mut requests: Vec<_> = pending.values().collect();
+                requests.sort_by_key(|r| r.requested_at);
+                requests
+                    .iter()
+                    .map(|r| format!("{} ({}) {}", r.name, r.chat_id, r.requested_at.format("%Y-%m-%d %H:%M UTC")))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        Command::Help => Command::descriptions().to_string(),
+    };
+
+    send_long_message(&bot, chat_id, &reply).await
+}
+
+fn parse_tickers(text: &str) -> Vec<String> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_uppercase())
+        .collect()
+}
+
+/// Applies the change to the chat preferences and replies with the resulting settings
+async fn update_preferences(
+    context: &CommandContext,
+    chat_id: ChatId,
+    update: impl FnOnce(&mut Preferences),
+) -> String {
+    let mut subscribers = context.subscribers.lock().await;
+    if !subscribers.update_preferences(chat_id, update) {
+        return NOT_SUBSCRIBED.to_string();
+    }
+    subscribers.preferences(chat_id).map(preferences_text).unwrap_or_default()
+}
+
+/// Adds tickers known to the instrument cache to the watchlist
+async fn watch(context: &CommandContext, chat_id: ChatId, text: &str) -> String {
+    let tickers = parse_tickers(text);
+    if tickers.is_empty() {
+        return "Usage: /watch SBER GAZP".to_string();
+    }
+
+    let mut known = Vec::new();
+    let mut unknown = Vec::new();
+    for ticker in tickers {
+        match find_instrument(context, &ticker).await {
+            Some(instrument) => known.push(instrument.ticker.to_uppercase()),
+            None => unknown.push(ticker),
+        }
+    }
+    let not_scanned: Vec<String> = {
+        let status = context.status.read().await;
+        known.iter().filter(|t| status.find_by_ticker(t).is_none()).cloned().collect()
+    };
+
+    let mut reply = update_preferences(context, chat_id, |preferences| preferences.watchlist.extend(known)).await;
+    if !unknown.is_empty() {
+        reply.push_str(&format!("\nUnknown instruments: {}, try /find", unknown.join(", ")));
+    }
+    if !not_scanned.is_empty() {
+        reply.push_str(&format!("\nNot scanned with the current filter: {}", not_scanned.join(", ")));
+    }
+    reply
+}
+
+/// Finds an instrument in the cache by ticker, preferring the scanned class codes
+async fn find_instrument(context: &CommandContext, ticker: &str) -> Option<CachedInstrument> {
+    let filter = context.config.read().await.filter.clone();
+    let instruments = context.instruments.read().await;
+    instruments
+        .by_ticker(ticker, &filter.class_codes())
+        .or_else(|| instruments.iter().find(|i| i.ticker.eq_ignore_ascii_case(ticker)))
+        .cloned()
+}
+
+/// Parses and stores an alert for any instrument of the cache
+async fn add_alert(context: &CommandContext, chat_id: ChatId, text: &str) -> String {
+    let text = text.to_lowercase().replace('<', " < ").replace('>', " > ");
+    let mut tokens: Vec<&str> = text.split_whitespace().collect();
+    let repeat = tokens.last() == Some(&"repeat");
+    if repeat {
+        tokens.pop();
+    }
+    let Some((ticker, condition)) = tokens.split_first() else {
+        return ALERT_USAGE.to_string();
+    };
+    let Some(condition) = AlertCondition::parse(condition) else {
+        return ALERT_USAGE.to_string();
+    };
+
+    let (ticker, uid) = match find_instrument(context, ticker).await {
+        Some(instrument) => (instrument.ticker, instrument.uid),
+        None => return format!("Unknown instrument {}, try /find", ticker.to_uppercase()),
+    };
+
+    let mut alerts = context.alerts.lock().await;
+    if alerts.for_chat(chat_id).len() >= MAX_ALERTS {
+        return format!("You already have {} alerts, delete some with /delalert", MAX_ALERTS);
+    }
+    let id = alerts.add(chat_id, &ticker, &uid, condition, repeat);
+    info!("Chat {} added alert #{}: {} {}", chat_id, id, ticker, condition);
+    format!(
+        "🔔 Alert #{} added: {} {}{}",
+        id,
+        ticker,
+        condition,
+        if repeat { ", repeating" } else { "" }
+    )
+}
+
+fn preferences_text(preferences: &Preferences) -> String {
+    let join = |set: &std::collections::BTreeSet<String>| {
+        if set.is_empty() {
+            "all".to_string()
+        } else {
+            set.iter().cloned().collect::<Vec<_>>().join(", ")
+        }
+    };
+    let directions = match (preferences.buy, preferences.sell) {
+        (true, true) => "buy, sell",
+        (true, false) => "buy",
+        (false, true) => "sell",
+        (false, false) => "none",
+    };
+
+    format!(
+        "Watchlist: {}\n\
+        Strategies: {}\n\
+        Directions: {}\n\
+        Minimum strength: {}%\n\
+        Language: {}",
+        join(&preferences.watchlist),
+        join(&preferences.strategies),
+        directions,
+        preferences.min_strength,
+        preferences.language.code()
+    )
+}
+
+async fn status_text(context: &CommandContext) -> String {
+    let status = context.status.read().await;
+    let now = Utc::now();
+    let last_scan = match status.last_scan {
+        Some(time) => format!("{} ({} ago)", time.format("%Y-%m-%d %H:%M:%S UTC"), format_duration(now - time)),
+        None => "never".to_string(),
+    };
+
+    format!(
+        "Scanner: {}\n\
+        Uptime: {}\n\
+        Last scan: {}\n\
+        Instruments scanned: {}\n\
+        Errors: {}{}\n\
+        Subscribers: {}\n\
+        Alerts: {}",
+        if status.kill_switch {
+            "stopped by kill switch"
+        } else if status.paused {
+            "paused"
+        } else {
+            "running"
+        },
+        format_duration(now - status.started_at),
+        last_scan,
+        status.instruments_scanned,
+        status.errors,
+        status.last_error.as_ref().map(|e| format!("\nLast error: {}", e)).unwrap_or_default(),
+        context.subscribers.lock().await.len(),
+        context.alerts.lock().await.len()
+    )
+}
+
+async fn list_text(context: &CommandContext) -> String {
+    let status = context.status.read().await;
+    if status.instruments.is_empty() {
+        return "No instruments scanned yet".to_string();
+    }
+
+    let mut text = format!("Universe ({} instruments):\n", status.instruments.len());
+    for state in status.instruments.values() {
+        text.push_str(&format!(
+            "{} {:.4} | EMA {:.4}/{:.4} ({:+.2}%) {}\n",
+            state.ticker,
+            state.last_price,
+            state.short_ema,
+            state.long_ema,
+            state.spread(),
+            match state.last_signal {
+                Some(Signal::Buy) => "🟢",
+                Some(Signal::Sell) => "🔴",
+                _ => "⚪",
+            }
+        ));
+    }
+    text
+}
+
+/// Evaluates the strategy for the instrument on demand with a freshly warmed up state
+async fn signal_text(context: &CommandContext, ticker: &str) -> String {
+    if ticker.is_empty() {
+        return "Usage: /signal TICKER".to_string();
+    }
+
+    let Some(instrument) = find_instrument(context, ticker).await else {
+        return format!("Unknown instrument {}, try /find", ticker.to_uppercase());
+    };
+
+    let config = context.config.read().await.clone();
+    let strategy_config = config.strategy_for(&instrument.ticker, &instrument.class_code, instrument.sector());
+    let mut strategy = EmaCrossStrategy::new(
+        instrument.uid.clone(),
+        instrument.ticker.to_uppercase(),
+        strategy_config.short_ema_length,
+        strategy_config.long_ema_length,
+        strategy_config.interval,
+        strategy_config.hysteresis_percentage,
+        strategy_config.hysteresis_periods,
+    );
+
+    let result = strategy
+        .get_trade_signal(&context.client, &config.t_token)
+        .await
+        .map_err(|e| e.to_string());
+
+    match result {
+        Ok(signal) => {
+            let difference = if strategy.get_last_long() != 0.0 {
+                (strategy.get_last_short() - strategy.get_last_long()) / strategy.get_last_long() * 100.0
+            } else {
+                0.0
+            };
+            format!(
+                "{}\n\
+                Signal: {:?}\n\
+                Last signal: {:?}\n\
+                Last Price: {:.4}\n\
+                Short EMA: {:.6}\n\
+                Long EMA: {:.6}\n\
+                Difference: {:.4}%",
+                strategy.get_ticker(),
+                signal,
+                strategy.get_last_signal(),
+                strategy.get_last_price(),
+                strategy.get_last_short(),
+                strategy.get_last_long(),
+                difference
+            )
+        }
+        Err(e) => {
+            error!("Error evaluating {} on demand: {}", ticker, e);
+            format!("Error evaluating {}: {}", ticker, e)
+        }
+    }
+}
+
+/// Searches instruments with FindInstrument and describes the first matches
+async fn find_text(context: &CommandContext, query: &str) -> String {
+    if query.is_empty() {
+        return "Usage: /find QUERY, e.g. /find sber".to_string();
+    }
+
+    let token = context.config.read().await.t_token.clone();
+    let results = match InstrumentLookup::new(&context.client, &token).search(query, FIND_RESULTS).await {
+        Ok(results) => results,
+        Err(e) => {
+            error!("Error searching instruments for {}: {}", query, e);
+            return format!("Error searching instruments: {}", e);
+        }
+    };
+    if results.instruments.is_empty() {
+        return format!("Nothing found for {}", query);
+    }
+
+    let mut text = if results.total > results.instruments.len() {
+        format!("First {} of {} matches:\n", results.instruments.len(), results.total)
+    } else {
+        format!("{} matches:\n", results.total)
+    };
+    for instrument in &results.instruments {
+        text.push_str(&format!(
+            "{} {} | {} | lot {} | {} | API {}\n",
+            instrument.ticker,
+            instrument.name,
+            instrument.class_code,
+            instrument.lot,
+            instrument.status_text(),
+            if instrument.api_trade_available { "✅" } else { "❌" }
+        ));
+    }
+    text
+}
+
+/// Sends text split into chunks by lines to fit the Telegram message limit
+pub async fn send_long_message(bot: &Bot, chat_id: ChatId, text: &str) -> ResponseResult<()> {
+    for chunk in split_message(text, MAX_MESSAGE_LENGTH) {
+        bot.send_message(chat_id, chunk).await?;
+    }
+    Ok(())
+}
+
+/// Splits text into chunks of at most `max` bytes at line breaks,
+/// lines longer than `max` are split between characters
+fn split_message(text: &str, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    for line in text.lines() {
+        if chunk.len() + line.len() + 1 > max && !chunk.is_empty() {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+        if line.len() < max {
+            chunk.push_str(line);
+            chunk.push('\n');
+            continue;
+        }
+
+        for c in line.chars() {
+            if chunk.len() + c.len_utf8() > max {
+                chunks.push(std::mem::take(&mut chunk));
+            }
+            chunk.push(c);
+        }
+        // The next line starts a new chunk instead of continuing this one without a line break
+        chunks.push(std::mem::take(&mut chunk));
+    }
+    if !chunk.trim().is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tickers_are_split_by_spaces_and_commas() {
+        assert_eq!(parse_tickers("sber, GAZP  lkoh,,"), vec!["SBER", "GAZP", "LKOH"]);
+        assert!(parse_tickers(" , ").is_empty());
+    }
+
+    #[test]
+    fn preferences_text_lists_settings() {
+        let defaults = preferences_text(&Preferences::default());
+        assert_eq!(
+            defaults,
+            "Watchlist: all\nStrategies: all\nDirections: buy, sell\nMinimum strength: 0%\nLanguage: en"
+        );
+
+        let preferences = Preferences {
+            watchlist: ["SBER".to_string(), "GAZP".to_string()].into(),
+            strategies: [EmaCrossStrategy::NAME.to_string()].into(),
+            buy: false,
+            sell: true,
+            min_strength: 0.5,
+            language: Language::Ru,
+        };
+        assert_eq!(
+            preferences_text(&preferences),
+            "Watchlist: GAZP, SBER\nStrategies: ema_cross\nDirections: sell\nMinimum strength: 0.5%\nLanguage: ru"
+        );
+    }
+
+    #[test]
+    fn control_commands_are_admin_only() {
+        let cases = [
+            ("/pause", true),
+            ("/resume", true),
+            ("/kill", true),
+            ("/reload", true),
+            ("/pending", true),
+            ("/start", false),
+            ("/stop", false),
+            ("/status", false),
+            ("/list", false),
+            ("/signal SBER", false),
+            ("/find sber", false),
+            ("/watch SBER", false),
+            ("/alert SBER > 300", false),
+            ("/delalert all", false),
+            ("/help", false),
+        ];
+        for (text, admin_only) in cases {
+            let command = Command::parse(text, "bot").unwrap();
+            assert_eq!(command.is_admin_only(), admin_only, "{}", text);
+        }
+    }
+
+    #[test]
+    fn short_messages_are_kept_whole() {
+        assert_eq!(split_message("one\ntwo", 100), vec!["one\ntwo\n"]);
+        assert!(split_message("\n\n", 100).is_empty());
+    }
+
+    #[test]
+    fn messages_are_split_at_line_breaks() {
+        let chunks = split_message("aaaa\nbbbb\ncccc", 10);
+        assert_eq!(chunks, vec!["aaaa\nbbbb\n", "cccc\n"]);
+    }
+
+    #[test]
+    fn overlong_lines_are_split_between_characters() {
+        let line = "я".repeat(15);
+        let text = format!("head\n{}\ntail", line);
+        let chunks = split_message(&text, 10);
+
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 10), "{:?}", chunks);
+        assert_eq!(chunks.first().unwrap(), "head\n");
+        assert_eq!(chunks.last().unwrap(), "tail\n");
+        assert_eq!(chunks.concat(), format!("head\n{}tail\n", line));
+    }
+}
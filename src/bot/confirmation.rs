@@ -0,0 +1,416 @@
+use crate::bot::commands::CommandContext;
+use crate::bot::signal::{SignalEvent, TradeSignal};
+use crate::config::{SharedConfig, TradingConfig};
+use crate::instruments::lookup::InstrumentLookup;
+use crate::market_data_service::get_last_prices::{GetLastPricesRequest, GetLastPricesResponse, InstrumentStatus, LastPriceType};
+use crate::operations_service::get_portfolio::{GetPortfolioRequest, GetPortfolioResponse};
+use crate::orders_service::post_order::{OrderDirection, PostOrderRequest, PostOrderResponse};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode};
+use teloxide::utils::html;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+/// Button pressed under a signal message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TradeAction {
+    OneLot,
+    Sized,
+    Ignore,
+}
+
+impl TradeAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TradeAction::OneLot => "one",
+            TradeAction::Sized => "sized",
+            TradeAction::Ignore => "ignore",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "one" => Some(TradeAction::OneLot),
+            "sized" => Some(TradeAction::Sized),
+            "ignore" => Some(TradeAction::Ignore),
+            _ => None,
+        }
+    }
+}
+
+/// Signal waiting for a trade decision
+#[derive(Debug, Clone)]
+pub struct PendingTrade {
+    pub id: u64,
+    pub event: SignalEvent,
+    pub created_at: DateTime<Utc>,
+    /// Messages showing the trade buttons
+    pub messages: Vec<(ChatId, MessageId)>,
+}
+
+/// Sent message with trade buttons, a digest has buttons of several trades
+#[derive(Debug, Clone)]
+pub struct SignalMessage {
+    /// HTML text, results are appended to it
+    pub text: String,
+    /// The text is the caption of a chart
+    pub photo: bool,
+    pub digest: bool,
+    /// Trades whose buttons are still shown as (trade ID, ticker)
+    pub trades: Vec<(u64, String)>,
+}
+
+/// Edit of a signal message after a trade was handled
+pub struct MessageEdit {
+    pub chat_id: ChatId,
+    pub message_id: MessageId,
+    pub text: String,
+    pub photo: bool,
+    pub keyboard: Option<InlineKeyboardMarkup>,
+}
+
+#[derive(Debug, Default)]
+pub struct PendingTrades {
+    next_id: u64,
+    trades: HashMap<u64, PendingTrade>,
+    messages: HashMap<(ChatId, MessageId), SignalMessage>,
+}
+
+pub type SharedTrades = Arc<Mutex<PendingTrades>>;
+
+impl PendingTrades {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a signal and returns its ID for the button data
+    pub fn insert(&mut self, event: SignalEvent) -> u64 {
+        self.next_id += 1;
+        self.trades.insert(self.next_id, PendingTrade {
+            id: self.next_id,
+            event,
+            created_at: Utc::now(),
+            messages: Vec::new(),
+        });
+        self.next_id
+    }
+
+    /// Remembers a sent message with buttons of the given trades
+    pub fn add_message(&mut self, chat_id: ChatId, message_id: MessageId, message: SignalMessage) {
+        for (id, _) in &message.trades {
+            if let Some(trade) = self.trades.get_mut(id) {
+                trade.messages.push((chat_id, message_id));
+            }
+        }
+        self.messages.insert((chat_id, message_id), message);
+    }
+
+    /// Removes the trade so that it can be handled only once
+    pub fn take(&mut self, id: u64) -> Option<PendingTrade> {
+        self.trades.remove(&id)
+    }
+
+    /// Removes trades older than the timeout
+    pub fn take_expired(&mut self, timeout: chrono::Duration) -> Vec<PendingTrade> {
+        let now = Utc::now();
+        let expired: Vec<u64> = self.trades
+            .iter()
+            .filter(|(_, trade)| now - trade.created_at >= timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        expired.into_iter().filter_map(|id| self.trades.remove(&id)).collect()
+    }
+
+    /// Appends the result to every message of the trade and removes its buttons
+    pub fn resolve(&mut self, trade: &PendingTrade, result: &str) -> Vec<MessageEdit> {
+        let mut edits = Vec::new();
+        for key in &trade.messages {
+            let Some(message) = self.messages.get_mut(key) else {
+                continue;
+            };
+            if message.digest {
+                message.text.push_str(&format!("\n<b>{}</b>: {}", html::escape(&trade.event.ticker), html::escape(result)));
+            } else {
+                message.text.push_str(&format!("\n\n{}", html::escape(result)));
+            }
+            message.trades.retain(|(id, _)| *id != trade.id);
+
+            edits.push(MessageEdit {
+                chat_id: key.0,
+                message_id: key.1,
+                text: message.text.clone(),
+                photo: message.photo,
+                keyboard: (!message.trades.is_empty()).then(|| keyboard(&message.trades)),
+            });
+            if message.trades.is_empty() {
+                self.messages.remove(key);
+            }
+        }
+        edits
+    }
+}
+
+/// Inline buttons for trades given as (trade ID, ticker), one row per trade
+pub fn keyboard(trades: &[(u64, String)]) -> InlineKeyboardMarkup {
+    let rows = trades.iter().map(|(id, ticker)| {
+        let button = |text: &str, action: TradeAction| {
+            // Digest rows are prefixed with the ticker
+            let text = if trades.len() > 1 { format!("{} {}", ticker, text) } else { text.to_string() };
+            InlineKeyboardButton::callback(text, format!("trade:{}:{}", id, action.as_str()))
+        };
+        vec![
+            button("Execute 1 lot", TradeAction::OneLot),
+            button("Execute sized", TradeAction::Sized),
+            button("Ignore", TradeAction::Ignore),
+        ]
+    });
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Returns true for callback data produced by `keyboard`
+pub fn is_trade_callback(query: &CallbackQuery) -> bool {
+    query.data.as_deref().is_some_and(|data| data.starts_with("trade:"))
+}
+
+/// Handles trade buttons: places the order and edits every copy of the signal message with the result
+pub async fn handle_trade(bot: Bot, query: CallbackQuery, context: CommandContext) -> ResponseResult<()> {
+    let Some((id, action)) = query
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix("trade:"))
+        .and_then(|data| data.split_once(':'))
+        .and_then(|(id, action)| Some((id.parse::<u64>().ok()?, TradeAction::parse(action)?)))
+    else {
+        bot.answer_callback_query(query.id).await?;
+        return Ok(());
+    };
+
+    let config = context.config.read().await.clone();
+    if !config.telegram.is_admin(query.from.id.0 as i64) {
+        bot.answer_callback_query(query.id).text("You are not allowed to trade").await?;
+        return Ok(());
+    }
+    let Some(trading) = config.trading.clone() else {
+        bot.answer_callback_query(query.id).text("Trading is disabled").await?;
+        return Ok(());
+    };
+    if action != TradeAction::Ignore && context.status.read().await.kill_switch {
+        bot.answer_callback_query(query.id).text("Kill switch is engaged").await?;
+        return Ok(());
+    }
+
+    let Some(trade) = context.trades.lock().await.take(id) else {
+        bot.answer_callback_query(query.id).text("The signal has expired or was already handled").await?;
+        return Ok(());
+    };
+
+    let user = query.from.full_name();
+    if Utc::now() - trade.created_at >= chrono::Duration::minutes(trading.confirmation_timeout_minutes) {
+        bot.answer_callback_query(query.id).text("The signal has expired").await?;
+        finish(&bot, &context.trades, &trade, "⌛ Expired").await;
+        return Ok(());
+    }
+
+    let result = match action {
+        TradeAction::Ignore => {
+            bot.answer_callback_query(query.id).await?;
+            info!("Signal for {} ignored by {}", trade.event.ticker, user);
+            format!("🚫 Ignored by {}", user)
+        }
+        TradeAction::OneLot | TradeAction::Sized => {
+            bot.answer_callback_query(query.id).text("Placing order...").await?;
+            let order_id = format!("bcp-{}-{}", id, trade.created_at.timestamp_millis());
+            let lot = context.instruments.read().await.by_uid(&trade.event.instrument_uid).map(|i| i.lot);
+            match execute(&context.client, &config.t_token, &trading, &trade.event, action, lot, &order_id)
+                .await
+                .map_err(|e| e.to_string())
+            {
+                Ok(fill) => format!("✅ {} by {}", fill, user),
+                Err(e) => {
+                    error!("Error executing {} signal for {}: {}", action.as_str(), trade.event.ticker, e);
+                    format!("⚠️ Order by {} failed: {}", user, e)
+                }
+            }
+        }
+    };
+
+    finish(&bot, &context.trades, &trade, &result).await;
+    Ok(())
+}
+
+/// Places a market order for the signal and describes the fill,
+/// the lot size is looked up with GetInstrumentBy if the instrument cache doesn't know it
+async fn execute(
+    client: &reqwest::Client,
+    token: &str,
+    trading: &TradingConfig,
+    event: &SignalEvent,
+    action: TradeAction,
+    lot: Option<i32>,
+    order_id: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let direction = match event.signal {
+        TradeSignal::Buy => OrderDirection::Buy,
+        TradeSignal::Sell => OrderDirection::Sell,
+        TradeSignal::Hold => return Err("Nothing to execute for a hold signal".into()),
+    };
+
+    let lot = match lot {
+        Some(lot) => lot.max(1),
+        None if action == TradeAction::Sized || direction == OrderDirection::Sell => {
+            InstrumentLookup::new(client, token).by_uid(&event.instrument_uid).await?.lot.max(1)
+        }
+        None => 1,
+    };
+
+    let lots = match action {
+        TradeAction::Sized => {
+            let lot = lot as f64;
+
+            let prices = GetLastPricesResponse::get_last_prices(
+                client,
+                token,
+                GetLastPricesRequest::new(vec![event.instrument_uid.clone()], LastPriceType::Unspecified, InstrumentStatus::Base),
+            )
+            .await?;
+            let price = prices.last_prices.first().map(|p| p.price.to_f64()).unwrap_or(event.last_price);
+            if price <= 0.0 {
+                return Err("No last price for sizing".into());
+            }
+
+            let lots = (trading.position_amount / (price * lot)).floor() as i64;
+            if lots < 1 {
+                return Err(format!("{} is less than one lot ({:.2})", trading.position_amount, price * lot).into());
+            }
+            lots
+        }
+        _ => 1,
+    };
+
+    // Sells only close the held position, they never open a short
+    let lots = if direction == OrderDirection::Sell {
+        let portfolio = GetPortfolioResponse::get_portfolio(
+            client,
+            token,
+            GetPortfolioRequest::new(&trading.account_id),
+            trading.sandbox,
+        )
+        .await?;
+        let held = portfolio
+            .positions
+            .iter()
+            .find(|p| p.instrument_uid == event.instrument_uid)
+            .map_or(0.0, |p| p.quantity.to_f64());
+        sell_lots(lots, held, lot).map_err(|e| format!("{} for {}", e, event.ticker))?
+    } else {
+        lots
+    };
+
+    let request = PostOrderRequest::new_market(&event.instrument_uid, lots, direction, &trading.account_id, order_id);
+    let response = PostOrderResponse::post_order(client, token, request, trading.sandbox).await?;
+
+    let money = |value: &Option<crate::models::structs::MoneyValue>| {
+        value
+            .as_ref()
+            .map(|v| format!("{:.2} {}", v.to_f64(), v.currency.to_uppercase()))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    Ok(format!(
+        "{:?} {} x{}: {} {}/{} lots, price {}, amount {}, commission {}",
+        direction,
+        event.ticker,
+        lots,
+        response.execution_report_status,
+        response.lots_executed,
+        response.lots_requested,
+        money(&response.executed_order_price),
+        money(&response.total_order_amount),
+        money(&response.executed_commission)
+    ))
+}
+
+/// Lots to sell capped at the held quantity in units, an error if less than one lot is held
+fn sell_lots(requested: i64, held_quantity: f64, lot: i32) -> Result<i64, String> {
+    let held_lots = (held_quantity / lot.max(1) as f64).floor() as i64;
+    if held_lots < 1 {
+        return Err("No long position to sell".to_string());
+    }
+    Ok(requested.min(held_lots))
+}
+
+/// Writes the result into every message of the trade
+async fn finish(bot: &Bot, trades: &SharedTrades, trade: &PendingTrade, result: &str) {
+    let edits = trades.lock().await.resolve(trade, result);
+    for edit in edits {
+        let result = match (edit.photo, edit.keyboard) {
+            (true, Some(keyboard)) => {
+                bot.edit_message_caption(edit.chat_id, edit.message_id)
+                    .caption(edit.text)
+                    .parse_mode(ParseMode::Html)
+                    .reply_markup(keyboard)
+                    .await
+            }
+            (true, None) => {
+                bot.edit_message_caption(edit.chat_id, edit.message_id)
+                    .caption(edit.text)
+                    .parse_mode(ParseMode::Html)
+                    .await
+            }
+            (false, Some(keyboard)) => {
+                bot.edit_message_text(edit.chat_id, edit.message_id, edit.text)
+                    .parse_mode(ParseMode::Html)
+                    .reply_markup(keyboard)
+                    .await
+            }
+            (false, None) => {
+                bot.edit_message_text(edit.chat_id, edit.message_id, edit.text)
+                    .parse_mode(ParseMode::Html)
+                    .await
+            }
+        };
+        if let Err(err) = result {
+            error!("Error editing signal message in chat {}: {}", edit.chat_id, err);
+        }
+    }
+}
+
+/// Periodically expires signals nobody acted on
+pub async fn expire_trades(bot: Bot, trades: SharedTrades, config: SharedConfig) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        let Some(timeout) = config.read().await.trading.as_ref().map(|t| t.confirmation_timeout_minutes) else {
+            continue;
+        };
+        let expired = trades.lock().await.take_expired(chrono::Duration::minutes(timeout));
+        for trade in expired {
+            info!("Signal for {} expired without a decision", trade.event.ticker);
+            finish(&bot, &trades, &trade, "⌛ Expired").await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sell_is_capped_at_held_lots() {
+        // 25 shares with 10 per lot are 2 lots
+        assert_eq!(sell_lots(5, 25.0, 10), Ok(2));
+        assert_eq!(sell_lots(1, 25.0, 10), Ok(1));
+    }
+
+    #[test]
+    fn sell_without_position_is_refused() {
+        assert!(sell_lots(1, 0.0, 10).is_err());
+        // Less than one lot can't be sold with a lot order
+        assert!(sell_lots(1, 5.0, 10).is_err());
+        // Short positions have a negative quantity
+        assert!(sell_lots(1, -10.0, 10).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+use crate::bot::confirmation::{self, SharedTrades, SignalMessage};
+use crate::bot::notifier::Subscribers;
+use crate::config::{SharedConfig, TelegramConfig};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::{InputFile, ParseMode};
+use teloxide::RequestError;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+/// Message waiting in the outbound queue
+#[derive(Debug, Clone)]
+pub struct Outgoing {
+    pub chat_id: ChatId,
+    /// HTML text, the caption if a chart is attached
+    pub text: String,
+    pub chart: Option<Arc<Vec<u8>>>,
+    /// Trades with buttons as (trade ID, ticker)
+    pub trades: Vec<(u64, String)>,
+    /// Several signals in one message
+    pub digest: bool,
+    attempts: u32,
+}
+
+impl Outgoing {
+    pub fn new(chat_id: ChatId, text: String, chart: Option<Arc<Vec<u8>>>, trades: Vec<(u64, String)>, digest: bool) -> Self {
+        Self {
+            chat_id,
+            text,
+            chart,
+            trades,
+            digest,
+            attempts: 0,
+        }
+    }
+}
+
+/// Outbound Telegram queue respecting global and per-chat rate limits
+#[derive(Clone)]
+pub struct DeliveryQueue {
+    sender: mpsc::UnboundedSender<Outgoing>,
+    /// Messages queued and not yet sent or dropped
+    pending: Arc<AtomicUsize>,
+}
+
+impl DeliveryQueue {
+    /// Starts the worker sending queued messages
+    pub fn start(bot: Bot, subscribers: Subscribers, trades: SharedTrades, config: SharedConfig) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let pending = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(run(bot, receiver, subscribers, trades, config, pending.clone()));
+        Self { sender, pending }
+    }
+
+    pub fn send(&self, outgoing: Outgoing) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.sender.send(outgoing).is_err() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            error!("Delivery queue is closed, message dropped");
+        }
+    }
+
+    /// Waits until every queued message has been sent or dropped
+    pub async fn wait_idle(&self) {
+        while self.pending.load(Ordering::SeqCst) > 0 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+/// Rate limits of the queue
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    global_interval: Duration,
+    chat_interval: Duration,
+    group_interval: Duration,
+    max_retries: u32,
+}
+
+impl Limits {
+    fn new(telegram: &TelegramConfig) -> Self {
+        Self {
+            global_interval: Duration::from_secs_f64(1.0 / telegram.messages_per_second.max(1) as f64),
+            chat_interval: Duration::from_millis(telegram.chat_interval_ms),
+            group_interval: Duration::from_millis(telegram.group_interval_ms),
+            max_retries: telegram.max_retries,
+        }
+    }
+}
+
+/// How a failed message is retried
+#[derive(Debug, Clone, Copy)]
+enum Retry {
+    /// Telegram asked to wait before sending to the chat again
+    After(Duration),
+    /// Exponential back-off after a network error
+    Backoff,
+}
+
+/// What the worker does next
+#[derive(Debug)]
+enum Next {
+    Send(Outgoing),
+    /// Nothing can be sent before this time
+    Wait(Instant),
+    Idle,
+}
+
+/// Queued messages with the earliest time the next one may be sent, globally and per chat
+struct Schedule {
+    queue: VecDeque<Outgoing>,
+    next_global: Instant,
+    next_chat: HashMap<ChatId, Instant>,
+}
+
+impl Schedule {
+    fn new(now: Instant) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            next_global: now,
+            next_chat: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, outgoing: Outgoing) {
+        self.queue.push_back(outgoing);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Takes the first message whose chat is ready, which keeps the order within each chat,
+    /// and reserves the global and per-chat intervals for it
+    fn next(&mut self, now: Instant, limits: &Limits) -> Next {
+        if self.queue.is_empty() {
+            return Next::Idle;
+        }
+        if self.next_global > now {
+            return Next::Wait(self.next_global);
+        }
+
+        let ready = |outgoing: &Outgoing| self.next_chat.get(&outgoing.chat_id).is_none_or(|next| *next <= now);
+        let Some(outgoing) = self.queue.iter().position(ready).and_then(|position| self.queue.remove(position)) else {
+            let earliest = self
+                .queue
+                .iter()
+                .filter_map(|outgoing| self.next_chat.get(&outgoing.chat_id))
+                .min()
+                .copied()
+                .unwrap_or(now);
+            return Next::Wait(earliest);
+        };
+
+        self.next_global = now + limits.global_interval;
+        // Negative IDs are groups and channels with stricter limits
+        let interval = if outgoing.chat_id.0 < 0 { limits.group_interval } else { limits.chat_interval };
+        self.next_chat.insert(outgoing.chat_id, now + interval);
+        Next::Send(outgoing)
+    }
+
+    /// Puts a failed message back at the front of the queue, returns it if it has no retries left
+    fn retry(&mut self, mut outgoing: Outgoing, retry: Retry, now: Instant, limits: &Limits) -> Option<Outgoing> {
+        if outgoing.attempts >= limits.max_retries {
+            return Some(outgoing);
+        }
+        outgoing.attempts += 1;
+        match retry {
+            Retry::After(delay) => {
+                let retry_at = now + delay;
+                self.next_chat.insert(outgoing.chat_id, retry_at);
+                self.next_global = self.next_global.max(retry_at);
+            }
+            Retry::Backoff => {
+                self.next_chat.insert(outgoing.chat_id, now + Duration::from_secs(1 << outgoing.attempts));
+            }
+        }
+        self.queue.push_front(outgoing);
+        None
+    }
+}
+
+async fn run(
+    bot: Bot,
+    mut receiver: mpsc::UnboundedReceiver<Outgoing>,
+    subscribers: Subscribers,
+    trades: SharedTrades,
+    config: SharedConfig,
+    pending: Arc<AtomicUsize>,
+) {
+    let mut schedule = Schedule::new(Instant::now());
+
+    loop {
+        if schedule.is_empty() {
+            match receiver.recv().await {
+                Some(outgoing) => schedule.push(outgoing),
+                None => break,
+            }
+        }
+        while let Ok(outgoing) = receiver.try_recv() {
+            schedule.push(outgoing);
+        }
+
+        let limits = Limits::new(&config.read().await.telegram);
+        let outgoing = match schedule.next(Instant::now(), &limits) {
+            Next::Send(outgoing) => outgoing,
+            Next::Wait(until) => {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(until) => {}
+                    Some(outgoing) = receiver.recv() => schedule.push(outgoing),
+                }
+                continue;
+            }
+            Next::Idle => continue,
+        };
+
+        let err = match send(&bot, &outgoing).await {
+            Ok(message) => {
+                if !outgoing.trades.is_empty() {
+                    trades.lock().await.add_message(outgoing.chat_id, message.id, SignalMessage {
+                        text: outgoing.text,
+                        photo: outgoing.chart.is_some(),
+                        digest: outgoing.digest,
+                        trades: outgoing.trades,
+                    });
+                }
+                pending.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            Err(err) => err,
+        };
+
+        let retry = match &err {
+            RequestError::RetryAfter(seconds) => {
+                warn!("Rate limited by Telegram for chat {}, retry after {}s", outgoing.chat_id, seconds.seconds());
+                Some(Retry::After(seconds.duration()))
+            }
+            RequestError::Network(e) => {
+                warn!("Network error sending to chat {}: {}, retrying", outgoing.chat_id, e);
+                Some(Retry::Backoff)
+            }
+            _ => None,
+        };
+        let dropped = match retry {
+            Some(retry) => schedule.retry(outgoing, retry, Instant::now(), &limits),
+            None => Some(outgoing),
+        };
+        if let Some(outgoing) = dropped {
+            error!("Error sending message to chat {}: {}", outgoing.chat_id, err);
+            subscribers.lock().await.prune_on_error(outgoing.chat_id, &err);
+            pending.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    info!("Delivery queue stopped");
+}
+
+/// Sends the text or the chart with the text as caption, with trade buttons if there are trades
+async fn send(bot: &Bot, outgoing: &Outgoing) -> Result<Message, RequestError> {
+    let keyboard = (!outgoing.trades.is_empty()).then(|| confirmation::keyboard(&outgoing.trades));
+    match (&outgoing.chart, keyboard) {
+        (Some(chart), Some(keyboard)) => {
+            bot.send_photo(outgoing.chat_id, chart_file(chart))
+                .caption(outgoing.text.as_str())
+                .parse_mode(ParseMode::Html)
+                .reply_markup(keyboard)
+                .await
+        }
+        (Some(chart), None) => {
+            bot.send_photo(outgoing.chat_id, chart_file(chart))
+                .caption(outgoing.text.as_str())
+                .parse_mode(ParseMode::Html)
+                .await
+        }
+        (None, Some(keyboard)) => {
+            bot.send_message(outgoing.chat_id, outgoing.text.as_str())
+                .parse_mode(ParseMode::Html)
+                .reply_markup(keyboard)
+                .await
+        }
+        (None, None) => {
+            bot.send_message(outgoing.chat_id, outgoing.text.as_str())
+                .parse_mode(ParseMode::Html)
+                .await
+        }
+    }
+}
+
+fn chart_file(chart: &[u8]) -> InputFile {
+    InputFile::memory(chart.to_vec()).file_name("chart.png")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        global_interval: Duration::from_millis(100),
+        chat_interval: Duration::from_secs(1),
+        group_interval: Duration::from_secs(3),
+        max_retries: 2,
+    };
+
+    fn message(chat_id: i64, text: &str) -> Outgoing {
+        Outgoing::new(ChatId(chat_id), text.to_string(), None, Vec::new(), false)
+    }
+
+    fn sent(next: Next) -> String {
+        match next {
+            Next::Send(outgoing) => format!("{}:{}", outgoing.chat_id, outgoing.text),
+            other => panic!("expected a message, got {:?}", other),
+        }
+    }
+
+    fn waits_until(next: Next) -> Instant {
+        match next {
+            Next::Wait(until) => until,
+            other => panic!("expected to wait, got {:?}", other),
+        }
+    }
+
+    fn schedule(start: Instant, messages: &[(i64, &str)]) -> Schedule {
+        let mut schedule = Schedule::new(start);
+        for &(chat_id, text) in messages {
+            schedule.push(message(chat_id, text));
+        }
+        schedule
+    }
+
+    #[test]
+    fn global_interval_between_any_messages() {
+        let start = Instant::now();
+        let mut schedule = schedule(start, &[(1, "a"), (2, "b")]);
+
+        assert_eq!(sent(schedule.next(start, &LIMITS)), "1:a");
+        assert_eq!(waits_until(schedule.next(start, &LIMITS)), start + LIMITS.global_interval);
+        assert_eq!(sent(schedule.next(start + LIMITS.global_interval, &LIMITS)), "2:b");
+        assert!(matches!(schedule.next(start + Duration::from_secs(10), &LIMITS), Next::Idle));
+    }
+
+    #[test]
+    fn other_chats_go_ahead_while_a_chat_waits_and_each_chat_keeps_its_order() {
+        let start = Instant::now();
+        let mut schedule = schedule(start, &[(1, "a1"), (1, "a2"), (2, "b1"), (1, "a3"), (2, "b2")]);
+
+        let mut now = start;
+        let mut order = Vec::new();
+        while !schedule.is_empty() {
+            match schedule.next(now, &LIMITS) {
+                Next::Send(outgoing) => order.push(format!("{}:{}", outgoing.chat_id, outgoing.text)),
+                Next::Wait(until) => now = until,
+                Next::Idle => break,
+            }
+        }
+
+        assert_eq!(order, vec!["1:a1", "2:b1", "1:a2", "2:b2", "1:a3"]);
+        // Three messages to chat 1 need two chat intervals
+        assert_eq!(now, start + LIMITS.chat_interval * 2);
+    }
+
+    #[test]
+    fn groups_wait_longer_than_private_chats() {
+        let start = Instant::now();
+        let mut schedule = schedule(start, &[(-100, "g1"), (-100, "g2")]);
+
+        assert_eq!(sent(schedule.next(start, &LIMITS)), "-100:g1");
+        let later = start + LIMITS.chat_interval;
+        assert_eq!(waits_until(schedule.next(later, &LIMITS)), start + LIMITS.group_interval);
+        assert_eq!(sent(schedule.next(start + LIMITS.group_interval, &LIMITS)), "-100:g2");
+    }
+
+    #[test]
+    fn retry_after_goes_first_and_holds_every_chat() {
+        let start = Instant::now();
+        let mut schedule = schedule(start, &[(1, "a1"), (1, "a2"), (2, "b1")]);
+
+        let Next::Send(failed) = schedule.next(start, &LIMITS) else {
+            panic!("expected a message");
+        };
+        let delay = Duration::from_secs(5);
+        assert!(schedule.retry(failed, Retry::After(delay), start, &LIMITS).is_none());
+
+        // Nothing is sent until Telegram allows it, then the failed message goes before the rest of its chat
+        assert_eq!(waits_until(schedule.next(start + LIMITS.global_interval, &LIMITS)), start + delay);
+        assert_eq!(sent(schedule.next(start + delay, &LIMITS)), "1:a1");
+        let next = start + delay + LIMITS.global_interval;
+        assert_eq!(sent(schedule.next(next, &LIMITS)), "2:b1");
+    }
+
+    #[test]
+    fn network_errors_back_off_per_chat() {
+        let start = Instant::now();
+        let mut schedule = schedule(start, &[(1, "a1"), (2, "b1")]);
+
+        let Next::Send(failed) = schedule.next(start, &LIMITS) else {
+            panic!("expected a message");
+        };
+        assert!(schedule.retry(failed, Retry::Backoff, start, &LIMITS).is_none());
+
+        // The other chat isn't held back
+        let next = start + LIMITS.global_interval;
+        assert_eq!(sent(schedule.next(next, &LIMITS)), "2:b1");
+        assert_eq!(waits_until(schedule.next(next + LIMITS.global_interval, &LIMITS)), start + Duration::from_secs(2));
+    }
+
+    #[test]
+    fn messages_are_dropped_after_max_retries() {
+        let start = Instant::now();
+        let mut schedule = schedule(start, &[(1, "a1")]);
+        let mut now = start;
+
+        for attempt in 0..=LIMITS.max_retries {
+            let failed = loop {
+                match schedule.next(now, &LIMITS) {
+                    Next::Send(outgoing) => break outgoing,
+                    Next::Wait(until) => now = until,
+                    Next::Idle => panic!("message dropped after {} attempts", attempt),
+                }
+            };
+            let dropped = schedule.retry(failed, Retry::After(Duration::from_secs(1)), now, &LIMITS);
+            assert_eq!(dropped.is_some(), attempt == LIMITS.max_retries);
+        }
+        assert!(schedule.is_empty());
+    }
+}
@@ -0,0 +1,292 @@
+use crate::bot::alerts::{AlertCondition, TriggeredAlert};
+use crate::bot::signal::{SignalEvent, TradeSignal};
+use serde::{Deserialize, Serialize};
+use teloxide::utils::html;
+
+/// Language of messages sent to a chat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Ru,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::En, Language::Ru];
+
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|language| language.code() == code.trim().to_lowercase())
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Ru => "ru",
+        }
+    }
+
+    pub fn catalog(&self) -> &'static Catalog {
+        match self {
+            Language::En => &EN,
+            Language::Ru => &RU,
+        }
+    }
+}
+
+/// Message texts of one language
+pub struct Catalog {
+    pub buy_signal: &'static str,
+    pub sell_signal: &'static str,
+    pub instrument: &'static str,
+    pub strategy: &'static str,
+    pub last_price: &'static str,
+    pub recommendation: &'static str,
+    pub buy: &'static str,
+    pub sell: &'static str,
+    pub short_ema: &'static str,
+    pub long_ema: &'static str,
+    pub difference: &'static str,
+    pub language_set: &'static str,
+    /// Title of a digest with several signals
+    pub signals: &'static str,
+    pub alert: &'static str,
+    pub condition: &'static str,
+    pub change: &'static str,
+}
+
+const EN: Catalog = Catalog {
+    buy_signal: "BUY SIGNAL",
+    sell_signal: "SELL SIGNAL",
+    instrument: "Instrument",
+    strategy: "Strategy",
+    last_price: "Last Price",
+    recommendation: "Recommendation",
+    buy: "BUY",
+    sell: "SELL",
+    short_ema: "Short EMA",
+    long_ema: "Long EMA",
+    difference: "Difference",
+    language_set: "Language: English",
+    signals: "Signals",
+    alert: "ALERT",
+    condition: "Condition",
+    change: "Change",
+};
+
+const RU: Catalog = Catalog {
+    buy_signal: "СИГНАЛ НА ПОКУПКУ",
+    sell_signal: "СИГНАЛ НА ПРОДАЖУ",
+    instrument: "Инструмент",
+    strategy: "Стратегия",
+    last_price: "Последняя цена",
+    recommendation: "Рекомендация",
+    buy: "ПОКУПАТЬ",
+    sell: "ПРОДАВАТЬ",
+    short_ema: "Короткая EMA",
+    long_ema: "Длинная EMA",
+    difference: "Разница",
+    language_set: "Язык: русский",
+    signals: "Сигналы",
+    alert: "ОПОВЕЩЕНИЕ",
+    condition: "Условие",
+    change: "Изменение",
+};
+
+/// Number of decimals of the price increment, e.g. 2 for 0.01 and 1 for 0.5
+fn decimals(increment: f64) -> usize {
+    (0..=9)
+        .find(|&digits| {
+            let scaled = increment * 10f64.powi(digits);
+            (scaled - scaled.round()).abs() < 1e-6
+        })
+        .unwrap_or(9) as usize
+}
+
+/// Formats the price rounded to the instrument price increment,
+/// falls back to a precision depending on the price if the increment is unknown
+pub fn format_price(price: f64, increment: Option<f64>) -> String {
+    match increment.filter(|increment| *increment > 0.0) {
+        Some(increment) => format!("{:.*}", decimals(increment), (price / increment).round() * increment),
+        None if price >= 1000.0 => format!("{:.2}", price),
+        None if price >= 100.0 => format!("{:.3}", price),
+        None if price >= 10.0 => format!("{:.4}", price),
+        None => format!("{:.6}", price),
+    }
+}
+
+/// Formats an indicator value with two more decimals than prices
+fn format_indicator(value: f64, increment: Option<f64>) -> String {
+    match increment.filter(|increment| *increment > 0.0) {
+        Some(increment) => format!("{:.*}", decimals(increment) + 2, value),
+        None => format!("{:.6}", value),
+    }
+}
+
+/// HTML signal message, None for Hold
+pub fn signal_message(language: Language, event: &SignalEvent) -> Option<String> {
+    let text = language.catalog();
+    let (icon, title, recommendation) = match event.signal {
+        TradeSignal::Buy => ("🟢", text.buy_signal, text.buy),
+        TradeSignal::Sell => ("🔴", text.sell_signal, text.sell),
+        TradeSignal::Hold => return None,
+    };
+
+    Some(format!(
+        "{} <b>{}</b>\n\
+        {}: <b>{}</b> (<code>{}</code>)\n\
+        {}: {}\n\
+        {}: <b>{}</b>\n\
+        {}: <b>{}</b>\n\
+        {}: {}\n\
+        {}: {}\n\
+        {}: {:.4}%",
+        icon,
+        title,
+        text.instrument,
+        html::escape(&event.ticker),
+        html::escape(&event.instrument_uid),
+        text.strategy,
+        html::escape(&event.strategy),
+        text.last_price,
+        format_price(event.last_price, event.price_increment),
+        text.recommendation,
+        recommendation,
+        text.short_ema,
+        format_indicator(event.short_ema, event.price_increment),
+        text.long_ema,
+        format_indicator(event.long_ema, event.price_increment),
+        text.difference,
+        event.ema_percentage()
+    ))
+}
+
+/// HTML message of a triggered alert
+pub fn alert_message(language: Language, triggered: &TriggeredAlert, increment: Option<f64>) -> String {
+    let text = language.catalog();
+    let alert = &triggered.alert;
+    let detail = match alert.condition {
+        AlertCondition::Move { .. } => format!("\n{}: {:+.2}%", text.change, triggered.value),
+        AlertCondition::RsiAbove { length, .. } | AlertCondition::RsiBelow { length, .. } => {
+            format!("\nRSI({}): {:.2}", length, triggered.value)
+        }
+        _ => String::new(),
+    };
+
+    format!(
+        "🔔 <b>{}</b> #{}\n\
+        {}: <b>{}</b>\n\
+        {}: {}\n\
+        {}: <b>{}</b>{}",
+        text.alert,
+        alert.id,
+        text.instrument,
+        html::escape(&alert.ticker),
+        text.condition,
+        html::escape(&alert.condition.to_string()),
+        text.last_price,
+        format_price(triggered.last_price, increment),
+        detail
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(ticker: &str, signal: TradeSignal, price_increment: Option<f64>) -> SignalEvent {
+        SignalEvent {
+            ticker: ticker.to_string(),
+            instrument_uid: "uid-1".to_string(),
+            strategy: "ema 9/21".to_string(),
+            signal,
+            short_ema: 101.0,
+            long_ema: 100.0,
+            last_price: 102.537,
+            price_increment,
+        }
+    }
+
+    #[test]
+    fn decimals_of_price_increments() {
+        for (increment, expected) in [(1.0, 0), (0.5, 1), (0.1, 1), (0.01, 2), (0.005, 3), (0.0025, 4), (0.000001, 6)] {
+            assert_eq!(decimals(increment), expected, "{}", increment);
+        }
+    }
+
+    #[test]
+    fn prices_are_rounded_to_the_increment() {
+        let cases = [
+            (102.537, Some(0.005), "102.535"),
+            (102.56, Some(0.1), "102.6"),
+            (102.5, Some(1.0), "103"),
+            (102.2, Some(0.5), "102.0"),
+            (0.123456, Some(0.0001), "0.1235"),
+            // Unknown or invalid increment, precision depends on the price
+            (1234.567, None, "1234.57"),
+            (123.4567, None, "123.457"),
+            (12.34567, None, "12.3457"),
+            (1.2345678, None, "1.234568"),
+            (1234.567, Some(0.0), "1234.57"),
+        ];
+        for (price, increment, expected) in cases {
+            assert_eq!(format_price(price, increment), expected, "{} {:?}", price, increment);
+        }
+    }
+
+    #[test]
+    fn english_buy_signal() {
+        let message = signal_message(Language::En, &event("SBER", TradeSignal::Buy, Some(0.01))).unwrap();
+        assert_eq!(
+            message,
+            "🟢 <b>BUY SIGNAL</b>\n\
+            Instrument: <b>SBER</b> (<code>uid-1</code>)\n\
+            Strategy: ema 9/21\n\
+            Last Price: <b>102.54</b>\n\
+            Recommendation: <b>BUY</b>\n\
+            Short EMA: 101.0000\n\
+            Long EMA: 100.0000\n\
+            Difference: 1.0000%"
+        );
+    }
+
+    #[test]
+    fn russian_sell_signal() {
+        let message = signal_message(Language::Ru, &event("SBER", TradeSignal::Sell, None)).unwrap();
+        assert_eq!(
+            message,
+            "🔴 <b>СИГНАЛ НА ПРОДАЖУ</b>\n\
+            Инструмент: <b>SBER</b> (<code>uid-1</code>)\n\
+            Стратегия: ema 9/21\n\
+            Последняя цена: <b>102.537</b>\n\
+            Рекомендация: <b>ПРОДАВАТЬ</b>\n\
+            Короткая EMA: 101.000000\n\
+            Длинная EMA: 100.000000\n\
+            Разница: 1.0000%"
+        );
+    }
+
+    #[test]
+    fn ticker_and_strategy_are_escaped() {
+        let mut event = event("<b>A&B</b>", TradeSignal::Buy, None);
+        event.strategy = "x < y".to_string();
+        let message = signal_message(Language::En, &event).unwrap();
+        assert!(message.contains("Instrument: <b>&lt;b&gt;A&amp;B&lt;/b&gt;</b>"), "{}", message);
+        assert!(message.contains("Strategy: x &lt; y"), "{}", message);
+    }
+
+    #[test]
+    fn hold_has_no_message() {
+        for language in Language::ALL {
+            assert!(signal_message(language, &event("SBER", TradeSignal::Hold, None)).is_none());
+        }
+    }
+
+    #[test]
+    fn languages_are_parsed_by_code() {
+        assert_eq!(Language::parse(" RU "), Some(Language::Ru));
+        assert_eq!(Language::parse("en"), Some(Language::En));
+        assert_eq!(Language::parse("de"), None);
+        assert_eq!(Language::Ru.catalog().language_set, "Язык: русский");
+    }
+}
@@ -1,6 +1,15 @@
+pub mod access;
+pub mod alerts;
+pub mod commands;
+pub mod confirmation;
+pub mod delivery;
+pub mod messages;
 pub mod notifier;
+pub mod report;
 pub mod trade;
 pub mod scanner;
 pub mod signal;
+pub mod status;
+pub mod subscribers;
 
 pub use scanner::MarketScanner;
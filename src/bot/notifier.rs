@@ -1,120 +1,284 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use teloxide::prelude::*;
 use teloxide::types::ChatId;
 use tokio::sync::Mutex;
-use crate::bot::signal::TradeSignal;
-use tracing::{info, error};
+use crate::bot::access::handle_approval;
+use crate::bot::confirmation::{expire_trades, handle_trade, is_trade_callback, PendingTrades, SharedTrades};
+use crate::config::SharedConfig;
+use crate::bot::commands::{handle_command, Command, CommandContext};
+use crate::bot::delivery::{DeliveryQueue, Outgoing};
+use crate::bot::alerts::TriggeredAlert;
+use crate::bot::report::Report;
+use crate::bot::messages::{alert_message, signal_message, Language};
+use crate::bot::subscribers::SubscriberStore;
+use crate::notifiers::{Notifier, NotifyFuture, SignalNotification};
+use teloxide::utils::command::BotCommands;
+use teloxide::utils::html;
+use tracing::error;
 
-pub type Subscribers = Arc<Mutex<HashSet<ChatId>>>;
+pub type Subscribers = Arc<Mutex<SubscriberStore>>;
+
+/// Maximum length of a digest message, leaves room for trade results
+const DIGEST_LENGTH: usize = 3500;
+/// Maximum number of signals with trade buttons in one digest
+const DIGEST_SIGNALS: usize = 20;
+/// Maximum length of a report message
+const REPORT_LENGTH: usize = 4000;
+/// Start of every report message
+const REPORT_ICON: &str = "📋 ";
 
 pub struct SignalNotifier {
     bot: Bot,
     subscribers: Subscribers,
+    config: SharedConfig,
+    trades: SharedTrades,
+    delivery: DeliveryQueue,
 }
 
 impl SignalNotifier {
-    pub fn new(token: &str) -> Self {
+    pub fn new(token: &str, subscribers: SubscriberStore, config: SharedConfig) -> Self {
+        let bot = Bot::new(token);
+        let subscribers = Arc::new(Mutex::new(subscribers));
+        let trades = Arc::new(Mutex::new(PendingTrades::new()));
+        let delivery = DeliveryQueue::start(bot.clone(), subscribers.clone(), trades.clone(), config.clone());
+
         SignalNotifier {
-            bot: Bot::new(token),
-            subscribers: Arc::new(Mutex::new(HashSet::new())),
+            bot,
+            subscribers,
+            config,
+            trades,
+            delivery,
         }
     }
 
-    async fn send_message(&self, chat_id: ChatId, text: &str) -> Result<(), teloxide::RequestError> {
-        self.bot.send_message(chat_id, text).await?;
-        Ok(())
-    }
+    /// Queues the signals of a scan, a chat receiving several signals gets them in one digest
+    async fn deliver(&self, batch: &[SignalNotification]) {
+        if batch.is_empty() {
+            return;
+        }
 
-    pub async fn notify_signal(&self, instrument: &str, signal: &TradeSignal, short_ema: f64, long_ema: f64, last_price: f64) {
-        let ema_diff = short_ema - long_ema;
-        let ema_percentage = ema_diff / long_ema * 100.0;
-
-        // Format price with appropriate precision
-        let price_str = if last_price >= 1000.0 {
-            format!("{:.2}", last_price)
-        } else if last_price >= 100.0 {
-            format!("{:.3}", last_price)
-        } else if last_price >= 10.0 {
-            format!("{:.4}", last_price)
-        } else {
-            format!("{:.6}", last_price)
-        };
+        // Only chats whose watchlist and filters accept the signal
+        let mut per_chat: BTreeMap<ChatId, (Language, Vec<(usize, String)>)> = BTreeMap::new();
+        {
+            let subs = self.subscribers.lock().await;
+            for (index, signal) in batch.iter().enumerate() {
+                for (chat_id, language) in subs.recipients(&signal.event) {
+                    if let Some(text) = signal_message(language, &signal.event) {
+                        per_chat.entry(chat_id).or_insert((language, Vec::new())).1.push((index, text));
+                    }
+                }
+            }
+        }
 
-        let message = match signal {
-            TradeSignal::Buy => format!(
-                "🟢 BUY SIGNAL\n\
-                Instrument: {}\n\
-                Last Price: {}\n\
-                Recommendation: BUY\n\
-                Short EMA: {:.6}\n\
-                Long EMA: {:.6}\n\
-                Difference: {:.6}%",
-                instrument, price_str, short_ema, long_ema, ema_percentage
-            ),
-            TradeSignal::Sell => format!(
-                "🔴 SELL SIGNAL\n\
-                Instrument: {}\n\
-                Last Price: {}\n\
-                Recommendation: SELL\n\
-                Short EMA: {:.6}\n\
-                Long EMA: {:.6}\n\
-                Difference: {:.6}%",
-                instrument, price_str, short_ema, long_ema, ema_percentage
-            ),
-            TradeSignal::Hold => {
-                info!(
-                    "HOLD POSITION\n\
-                    Instrument: {}\n\
-                    Last Price: {}\n\
-                    Short EMA: {:.6}\n\
-                    Long EMA: {:.6}\n\
-                    Difference: {:.6}%",
-                    instrument, price_str, short_ema, long_ema, ema_percentage
-                );
-                return; // Don't send Hold messages to Telegram
+        // Trade buttons are attached when semi-automatic trading is configured,
+        // trades are registered only for signals some chat receives
+        let trading = self.config.read().await.trading.is_some();
+        let mut trade_ids: Vec<Option<u64>> = vec![None; batch.len()];
+        if trading {
+            let mut trades = self.trades.lock().await;
+            for (_, messages) in per_chat.values() {
+                for (index, _) in messages {
+                    if trade_ids[*index].is_none() {
+                        trade_ids[*index] = Some(trades.insert(batch[*index].event.clone()));
+                    }
+                }
             }
-        };
+        }
 
-        let subs_snapshot = {
-            let subs = self.subscribers.lock().await;
-            subs.clone()
+        let trades_of = |indices: &[usize]| -> Vec<(u64, String)> {
+            indices
+                .iter()
+                .filter_map(|&i| trade_ids[i].map(|id| (id, batch[i].event.ticker.clone())))
+                .collect()
         };
 
-        for chat_id in subs_snapshot {
-            if let Err(err) = self.send_message(chat_id, &message).await {
-                error!("Error sending signal to chat {}: {}", chat_id, err);
+        for (chat_id, (language, mut messages)) in per_chat {
+            if messages.len() == 1 {
+                let (index, text) = messages.remove(0);
+                self.delivery.send(Outgoing::new(chat_id, text, batch[index].chart.clone(), trades_of(&[index]), false));
+                continue;
+            }
+
+            // Digest messages are split to fit the Telegram message limit
+            let mut chunk: Vec<usize> = Vec::new();
+            let mut texts: Vec<String> = Vec::new();
+            let mut length = 0;
+            for (index, text) in messages {
+                if !chunk.is_empty() && (length + text.len() > DIGEST_LENGTH || chunk.len() == DIGEST_SIGNALS) {
+                    self.send_digest(chat_id, language, batch, std::mem::take(&mut texts), &chunk, trades_of(&chunk));
+                    chunk.clear();
+                    length = 0;
+                }
+                length += text.len() + 2;
+                chunk.push(index);
+                texts.push(text);
+            }
+            if !chunk.is_empty() {
+                self.send_digest(chat_id, language, batch, texts, &chunk, trades_of(&chunk));
             }
         }
     }
 
-    pub async fn start_listener(&self) {
+    /// Queues a digest of the chunk, a digest with a single signal keeps its chart
+    fn send_digest(
+        &self,
+        chat_id: ChatId,
+        language: Language,
+        batch: &[SignalNotification],
+        texts: Vec<String>,
+        chunk: &[usize],
+        trades: Vec<(u64, String)>,
+    ) {
+        let text = format!(
+            "📊 <b>{}: {}</b>\n\n{}",
+            language.catalog().signals,
+            texts.len(),
+            texts.join("\n\n")
+        );
+        let chart = match chunk {
+            [index] => batch[*index].chart.clone(),
+            _ => None,
+        };
+        self.delivery.send(Outgoing::new(chat_id, text, chart, trades, true));
+    }
+
+    /// Queues a triggered alert to the chat that created it, in the chat language
+    pub async fn send_alert(&self, triggered: &TriggeredAlert, increment: Option<f64>) {
+        let chat_id = ChatId(triggered.alert.chat_id);
+        let language = self
+            .subscribers
+            .lock()
+            .await
+            .preferences(chat_id)
+            .map(|preferences| preferences.language)
+            .unwrap_or_default();
+        let text = alert_message(language, triggered, increment);
+        self.delivery.send(Outgoing::new(chat_id, text, None, Vec::new(), false));
+    }
+
+    /// Waits until the queued messages have been sent
+    pub async fn wait_delivered(&self) {
+        self.delivery.wait_idle().await;
+    }
+
+    pub fn subscribers(&self) -> Subscribers {
+        self.subscribers.clone()
+    }
+
+    pub fn trades(&self) -> SharedTrades {
+        self.trades.clone()
+    }
+
+    /// Starts handling Telegram commands in a background task
+    pub async fn start_listener(&self, context: CommandContext) {
         let bot = self.bot.clone();
-        let subscribers = self.subscribers.clone();
-        
+
+        if let Err(err) = bot.set_my_commands(Command::bot_commands()).await {
+            error!("Error registering bot commands: {}", err);
+        }
+
+        tokio::spawn(expire_trades(bot.clone(), self.trades.clone(), context.config.clone()));
+
         tokio::spawn(async move {
-            teloxide::repl(bot, move |message: Message, bot: Bot| {
-                let subscribers = subscribers.clone();
-                async move {
-                    if let Some(text) = message.text() {
-                        if text == "/start" {
-                            {
-                                let mut subs = subscribers.lock().await;
-                                subs.insert(message.chat.id);
-                                info!("New subscriber: {}", message.chat.id);
-                            }
-                            if let Err(err) = bot.send_message(
-                                message.chat.id,
-                                "✅ You have subscribed to trading signals!"
-                            ).await {
-                                error!("Error sending welcome message: {}", err);
-                            }
-                        }
+            let handler = dptree::entry()
+                .branch(
+                    Update::filter_message()
+                        .filter_command::<Command>()
+                        .endpoint(handle_command),
+                )
+                .branch(
+                    Update::filter_callback_query()
+                        .branch(dptree::filter(|query: CallbackQuery| is_trade_callback(&query)).endpoint(handle_trade))
+                        .branch(dptree::endpoint(handle_approval)),
+                );
+
+            Dispatcher::builder(bot, handler)
+                .dependencies(dptree::deps![context])
+                .default_handler(|_| async {})
+                .build()
+                .dispatch()
+                .await;
+        });
+    }
+}
+
+impl Notifier for SignalNotifier {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn send_signals<'a>(&'a self, signals: &'a [SignalNotification]) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            self.deliver(signals).await;
+            Ok(())
+        })
+    }
+
+    /// Sends the report to every subscriber with the EMA state of its watchlist
+    fn send_report<'a>(&'a self, report: &'a Report) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let watchlists = self.subscribers.lock().await.watchlists();
+            for (chat_id, watchlist) in watchlists {
+                // Long reports are split by lines to fit the Telegram message limit
+                let mut chunk = String::from(REPORT_ICON);
+                for line in report.text(Some(&watchlist)).lines() {
+                    let line = escape_line(line, REPORT_LENGTH - REPORT_ICON.len() - 1);
+                    if chunk.len() + line.len() + 1 > REPORT_LENGTH {
+                        self.delivery.send(Outgoing::new(chat_id, std::mem::take(&mut chunk), None, Vec::new(), false));
                     }
-                    respond(())
+                    chunk.push_str(&line);
+                    chunk.push('\n');
                 }
-            })
-            .await;
-        });
+                if !chunk.trim().is_empty() {
+                    self.delivery.send(Outgoing::new(chat_id, chunk, None, Vec::new(), false));
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Escapes the line for HTML, a line longer than `max` bytes is cut and ends with an ellipsis
+fn escape_line(line: &str, max: usize) -> String {
+    let escaped = html::escape(line);
+    if escaped.len() <= max {
+        return escaped;
+    }
+
+    // Cut by characters so no escaped entity is split
+    let mut cut = String::new();
+    let mut buffer = [0; 4];
+    for c in line.chars() {
+        let part = html::escape(c.encode_utf8(&mut buffer));
+        if cut.len() + part.len() + '…'.len_utf8() > max {
+            break;
+        }
+        cut.push_str(&part);
     }
-} 
\ No newline at end of file
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_lines_are_only_escaped() {
+        assert_eq!(escape_line("SBER <b> & GAZP", 100), "SBER &lt;b&gt; &amp; GAZP");
+    }
+
+    #[test]
+    fn long_lines_are_cut_to_fit() {
+        let line = "Last error: ".to_string() + &"ошибка & ".repeat(1000);
+        let max = REPORT_LENGTH - REPORT_ICON.len() - 1;
+        let escaped = escape_line(&line, max);
+        assert!(escaped.len() <= max);
+        assert!(escaped.starts_with("Last error: ошибка &amp; "));
+        assert!(escaped.ends_with('…'));
+        // Entities are never cut in half
+        assert_eq!(escape_line("<<<<", 10), "&lt;…");
+        assert_eq!(escape_line("<<<<", 11), "&lt;&lt;…");
+    }
+}
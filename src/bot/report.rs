@@ -0,0 +1,449 @@
+use crate::bot::messages::format_price;
+use crate::bot::signal::Signal;
+use crate::bot::status::{InstrumentState, ScannerStatus, SignalRecord};
+use crate::config::ReportsConfig;
+use crate::instruments::cache::InstrumentCache;
+use crate::operations_service::get_portfolio::GetPortfolioResponse;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Moscow Exchange time, UTC+3 without daylight saving
+pub fn moex_offset() -> FixedOffset {
+    FixedOffset::east_opt(3 * 3600).expect("valid offset")
+}
+
+/// Maximum number of lines in each report section
+const SECTION_LINES: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    Daily,
+    Weekly,
+}
+
+/// Open position with its unrealized P&L
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionReport {
+    pub ticker: String,
+    pub quantity: f64,
+    pub average_price: f64,
+    pub current_price: f64,
+    pub pnl: f64,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioReport {
+    pub total: f64,
+    pub currency: String,
+    pub pnl_percentage: f64,
+    pub positions: Vec<PositionReport>,
+}
+
+impl PortfolioReport {
+    /// Positions of the portfolio without currencies, tickers are taken from the instrument cache where known
+    pub fn new(portfolio: &GetPortfolioResponse, instruments: &InstrumentCache) -> Self {
+        let positions = portfolio
+            .positions
+            .iter()
+            .filter(|p| p.instrument_type != "currency")
+            .map(|p| {
+                let ticker = instruments
+                    .by_uid(&p.instrument_uid)
+                    .or_else(|| instruments.by_figi(&p.figi))
+                    .map(|i| i.ticker.clone())
+                    .unwrap_or_else(|| p.figi.clone());
+                PositionReport {
+                    ticker,
+                    quantity: p.quantity.to_f64(),
+                    average_price: p.average_position_price.as_ref().map(|m| m.to_f64()).unwrap_or_default(),
+                    current_price: p.current_price.as_ref().map(|m| m.to_f64()).unwrap_or_default(),
+                    pnl: p.expected_yield.as_ref().map(|q| q.to_f64()).unwrap_or_default(),
+                    currency: p.current_price.as_ref().map(|m| m.currency.to_uppercase()).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Self {
+            total: portfolio.total_amount_portfolio.as_ref().map(|m| m.to_f64()).unwrap_or_default(),
+            currency: portfolio
+                .total_amount_portfolio
+                .as_ref()
+                .map(|m| m.currency.to_uppercase())
+                .unwrap_or_default(),
+            pnl_percentage: portfolio.expected_yield.as_ref().map(|q| q.to_f64()).unwrap_or_default(),
+            positions,
+        }
+    }
+}
+
+/// Paper position with its P&L at the exit or last price
+#[derive(Debug, Clone, Serialize)]
+pub struct PaperPositionReport {
+    pub ticker: String,
+    pub strategy: String,
+    pub entry_price: f64,
+    /// Exit price of a closed position, last price of an open one
+    pub price: f64,
+    pub closed: bool,
+    pub pnl_percentage: f64,
+}
+
+/// Digest of signals, EMA state, positions and errors over a period
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub kind: ReportKind,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub signals: Vec<SignalRecord>,
+    /// Whole universe, each chat sees its watchlist
+    #[serde(skip)]
+    pub instruments: Vec<InstrumentState>,
+    /// Instruments with the smallest EMA spread
+    pub closest: Vec<InstrumentState>,
+    pub portfolio: Option<PortfolioReport>,
+    /// Open paper positions and those closed in the period
+    pub paper_positions: Vec<PaperPositionReport>,
+    /// Portfolio couldn't be loaded
+    pub portfolio_error: Option<String>,
+    pub errors: usize,
+    pub last_error: Option<String>,
+}
+
+impl Report {
+    /// Collects the report for the period ending now from the scanner state
+    pub fn new(kind: ReportKind, now: DateTime<Utc>, status: &ScannerStatus, closest: usize) -> Self {
+        let from = match kind {
+            // Since midnight in Moscow
+            ReportKind::Daily => now
+                .with_timezone(&moex_offset())
+                .date_naive()
+                .and_time(NaiveTime::MIN)
+                .and_local_timezone(moex_offset())
+                .single()
+                .map(|time| time.with_timezone(&Utc))
+                .unwrap_or(now - Duration::days(1)),
+            ReportKind::Weekly => now - Duration::days(7),
+        };
+
+        let instruments: Vec<InstrumentState> = status.instruments.values().cloned().collect();
+        let mut by_spread: Vec<InstrumentState> = instruments.iter().filter(|i| i.long_ema != 0.0).cloned().collect();
+        by_spread.sort_by(|a, b| a.spread().abs().total_cmp(&b.spread().abs()));
+        by_spread.truncate(closest);
+
+        let paper_positions = status
+            .paper_positions
+            .iter()
+            .filter(|position| position.closed_at.is_none_or(|closed_at| closed_at >= from))
+            .map(|position| {
+                let last_price = status.find_by_ticker(&position.ticker).map_or(position.entry_price, |i| i.last_price);
+                let price = position.exit_price.unwrap_or(last_price);
+                PaperPositionReport {
+                    ticker: position.ticker.clone(),
+                    strategy: position.strategy.clone(),
+                    entry_price: position.entry_price,
+                    price,
+                    closed: position.closed_at.is_some(),
+                    pnl_percentage: position.pnl_percentage(price),
+                }
+            })
+            .collect();
+
+        Self {
+            kind,
+            from,
+            to: now,
+            signals: status.signals.iter().filter(|s| s.time >= from).cloned().collect(),
+            instruments,
+            closest: by_spread,
+            portfolio: None,
+            paper_positions,
+            portfolio_error: None,
+            errors: status.error_times.iter().filter(|time| **time >= from).count(),
+            last_error: status.last_error.clone(),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let to = self.to.with_timezone(&moex_offset());
+        match self.kind {
+            ReportKind::Daily => format!("Daily report {}", to.format("%Y-%m-%d")),
+            ReportKind::Weekly => format!(
+                "Weekly report {} - {}",
+                self.from.with_timezone(&moex_offset()).format("%Y-%m-%d"),
+                to.format("%Y-%m-%d")
+            ),
+        }
+    }
+
+    /// Plain text report, the EMA state section lists the watchlist if one is given
+    pub fn text(&self, watchlist: Option<&BTreeSet<String>>) -> String {
+        let moex = |time: DateTime<Utc>| time.with_timezone(&moex_offset());
+        let mut text = self.title();
+
+        let _ = write!(text, "\n\nSignals: {}", self.signals.len());
+        for signal in self.signals.iter().rev().take(SECTION_LINES).rev() {
+            let time_format = if self.kind == ReportKind::Daily { "%H:%M" } else { "%m-%d %H:%M" };
+            let _ = write!(
+                text,
+                "\n{} {} {} at {} ({})",
+                moex(signal.time).format(time_format),
+                match signal.signal {
+                    Signal::Buy => "🟢 BUY",
+                    Signal::Sell => "🔴 SELL",
+                    Signal::Hold => "⚪ HOLD",
+                },
+                signal.ticker,
+                format_price(signal.price, None),
+                signal.strategy
+            );
+        }
+        if self.signals.len() > SECTION_LINES {
+            let _ = write!(text, "\n...and {} earlier", self.signals.len() - SECTION_LINES);
+        }
+
+        if let Some(watchlist) = watchlist.filter(|w| !w.is_empty()) {
+            text.push_str("\n\nWatchlist:");
+            let watched = self.instruments.iter().filter(|i| watchlist.contains(&i.ticker.to_uppercase()));
+            for state in watched.take(SECTION_LINES) {
+                let _ = write!(text, "\n{}", instrument_line(state));
+            }
+        }
+
+        if !self.closest.is_empty() {
+            text.push_str("\n\nClosest to crossover:");
+            for state in &self.closest {
+                let _ = write!(text, "\n{}", instrument_line(state));
+            }
+        }
+
+        if let Some(portfolio) = &self.portfolio {
+            let _ = write!(
+                text,
+                "\n\nPortfolio: {:.2} {}, P&L {:+.2}%",
+                portfolio.total, portfolio.currency, portfolio.pnl_percentage
+            );
+            for position in portfolio.positions.iter().take(SECTION_LINES) {
+                let _ = write!(
+                    text,
+                    "\n{} {} @ {} → {}, P&L {:+.2} {}",
+                    position.ticker,
+                    position.quantity,
+                    format_price(position.average_price, None),
+                    format_price(position.current_price, None),
+                    position.pnl,
+                    position.currency
+                );
+            }
+        } else if let Some(error) = &self.portfolio_error {
+            let _ = write!(text, "\n\nPortfolio unavailable: {}", error);
+        }
+
+        if !self.paper_positions.is_empty() {
+            let open = self.paper_positions.iter().filter(|p| !p.closed).count();
+            let _ = write!(
+                text,
+                "\n\nPaper positions: {} open, {} closed",
+                open,
+                self.paper_positions.len() - open
+            );
+            for position in self.paper_positions.iter().take(SECTION_LINES) {
+                let _ = write!(
+                    text,
+                    "\n{} {} → {}{}, P&L {:+.2}% ({})",
+                    position.ticker,
+                    format_price(position.entry_price, None),
+                    format_price(position.price, None),
+                    if position.closed { " closed" } else { "" },
+                    position.pnl_percentage,
+                    position.strategy
+                );
+            }
+        }
+
+        let _ = write!(text, "\n\nErrors: {}", self.errors);
+        if let Some(error) = self.last_error.as_ref().filter(|_| self.errors > 0) {
+            let _ = write!(text, "\nLast error: {}", error);
+        }
+        text
+    }
+}
+
+fn instrument_line(state: &InstrumentState) -> String {
+    format!(
+        "{} {} | EMA {:.4}/{:.4} ({:+.2}%)",
+        state.ticker,
+        format_price(state.last_price, None),
+        state.short_ema,
+        state.long_ema,
+        state.spread()
+    )
+}
+
+/// Reports scheduled after `since` and not later than `now`
+pub fn due_reports(config: &ReportsConfig, since: DateTime<Utc>, now: DateTime<Utc>) -> Vec<ReportKind> {
+    let is_due = |date: NaiveDate, time: NaiveTime| {
+        date.and_time(time)
+            .and_local_timezone(moex_offset())
+            .single()
+            .is_some_and(|at| at > since && at <= now)
+    };
+    let first = since.with_timezone(&moex_offset()).date_naive();
+    let last = now.with_timezone(&moex_offset()).date_naive();
+    let dates: Vec<NaiveDate> = first.iter_days().take_while(|date| *date <= last).collect();
+
+    let mut due = Vec::new();
+    if let Some(time) = config.daily {
+        let weekday = |date: &NaiveDate| !matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+        if dates.iter().filter(|date| weekday(date)).any(|date| is_due(*date, time)) {
+            due.push(ReportKind::Daily);
+        }
+    }
+    if let Some(weekly) = &config.weekly {
+        if dates.iter().filter(|date| date.weekday() == weekly.day).any(|date| is_due(*date, weekly.time)) {
+            due.push(ReportKind::Weekly);
+        }
+    }
+    due
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::status::PaperPosition;
+    use crate::config::WeeklyReportConfig;
+    use chrono::TimeZone;
+
+    fn config(daily: Option<&str>, weekly: Option<(Weekday, &str)>) -> ReportsConfig {
+        let time = |text: &str| NaiveTime::parse_from_str(text, "%H:%M").unwrap();
+        ReportsConfig {
+            daily: daily.map(time),
+            weekly: weekly.map(|(day, at)| WeeklyReportConfig { day, time: time(at) }),
+            ..ReportsConfig::default()
+        }
+    }
+
+    /// UTC time of a MOEX date and time
+    fn moex(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        moex_offset()
+            .with_ymd_and_hms(2024, 3, day, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    // 2024-03-01 is a Friday
+
+    #[test]
+    fn daily_report_on_weekdays_only() {
+        let config = config(Some("18:50"), None);
+        assert_eq!(due_reports(&config, moex(1, 18, 45), moex(1, 18, 55)), [ReportKind::Daily]);
+        assert!(due_reports(&config, moex(2, 18, 45), moex(2, 18, 55)).is_empty());
+        assert!(due_reports(&config, moex(3, 18, 45), moex(3, 18, 55)).is_empty());
+        assert_eq!(due_reports(&config, moex(4, 18, 45), moex(4, 18, 55)), [ReportKind::Daily]);
+    }
+
+    #[test]
+    fn report_time_is_moex_time() {
+        let config = config(Some("18:50"), None);
+        // 18:50 MSK is 15:50 UTC
+        assert_eq!(moex(1, 18, 50), Utc.with_ymd_and_hms(2024, 3, 1, 15, 50, 0).unwrap());
+        let utc = |hour, minute| Utc.with_ymd_and_hms(2024, 3, 1, hour, minute, 0).unwrap();
+        assert_eq!(due_reports(&config, utc(15, 49), utc(15, 51)), [ReportKind::Daily]);
+        assert!(due_reports(&config, utc(18, 49), utc(18, 51)).is_empty());
+    }
+
+    #[test]
+    fn window_excludes_start_and_includes_end() {
+        let config = config(Some("18:50"), None);
+        assert_eq!(due_reports(&config, moex(1, 18, 40), moex(1, 18, 50)), [ReportKind::Daily]);
+        assert!(due_reports(&config, moex(1, 18, 50), moex(1, 19, 0)).is_empty());
+        assert!(due_reports(&config, moex(1, 18, 0), moex(1, 18, 49)).is_empty());
+    }
+
+    #[test]
+    fn weekly_report_on_its_day() {
+        let config = config(None, Some((Weekday::Fri, "19:00")));
+        assert_eq!(due_reports(&config, moex(1, 18, 55), moex(1, 19, 5)), [ReportKind::Weekly]);
+        assert!(due_reports(&config, moex(7, 18, 55), moex(7, 19, 5)).is_empty());
+        assert_eq!(due_reports(&config, moex(8, 18, 55), moex(8, 19, 5)), [ReportKind::Weekly]);
+
+        // Weekly reports may be scheduled on weekends
+        let config = self::config(Some("19:00"), Some((Weekday::Sat, "19:00")));
+        assert_eq!(due_reports(&config, moex(2, 18, 55), moex(2, 19, 5)), [ReportKind::Weekly]);
+    }
+
+    #[test]
+    fn daily_and_weekly_together() {
+        let config = config(Some("18:50"), Some((Weekday::Fri, "19:00")));
+        assert_eq!(
+            due_reports(&config, moex(1, 18, 45), moex(1, 19, 5)),
+            [ReportKind::Daily, ReportKind::Weekly]
+        );
+    }
+
+    #[test]
+    fn window_spanning_midnight() {
+        // Monday 23:55 and Tuesday 00:05 MSK, the first is Monday 20:55 UTC
+        let late = config(Some("23:55"), None);
+        let early = config(Some("00:05"), None);
+        let (since, now) = (moex(4, 23, 50), moex(5, 0, 10));
+        assert_eq!(due_reports(&late, since, now), [ReportKind::Daily]);
+        assert_eq!(due_reports(&early, since, now), [ReportKind::Daily]);
+
+        // Friday night to Monday morning skips the weekend
+        let config = config(Some("18:50"), None);
+        assert!(due_reports(&config, moex(1, 19, 0), moex(4, 10, 0)).is_empty());
+        assert_eq!(due_reports(&config, moex(1, 19, 0), moex(4, 19, 0)), [ReportKind::Daily]);
+    }
+
+    #[test]
+    fn nothing_scheduled() {
+        assert!(due_reports(&config(None, None), moex(1, 0, 0), moex(8, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn paper_positions_of_the_period() {
+        let now = moex(5, 19, 0);
+        let position = |ticker: &str, opened: u32, entry_price, closed: Option<(u32, f64)>| PaperPosition {
+            ticker: ticker.to_string(),
+            strategy: "ema 9/21".to_string(),
+            opened_at: moex(opened, 11, 0),
+            entry_price,
+            closed_at: closed.map(|(day, _)| moex(day, 11, 0)),
+            exit_price: closed.map(|(_, price)| price),
+        };
+        let mut status = ScannerStatus::new();
+        // Closed the day before, closed today, still open
+        status.paper_positions = vec![
+            position("GAZP", 1, 150.0, Some((4, 160.0))),
+            position("SBER", 4, 100.0, Some((5, 110.0))),
+            position("LKOH", 5, 7000.0, None),
+        ];
+        status.instruments.insert("LKOH".to_string(), InstrumentState {
+            uid: "lkoh-uid".to_string(),
+            ticker: "LKOH".to_string(),
+            class_code: "TQBR".to_string(),
+            sector: None,
+            last_price: 6930.0,
+            short_ema: 7000.0,
+            long_ema: 6990.0,
+            last_signal: Some(Signal::Buy),
+        });
+
+        let report = Report::new(ReportKind::Daily, now, &status, 5);
+        let positions: Vec<(&str, f64, bool)> =
+            report.paper_positions.iter().map(|p| (p.ticker.as_str(), p.price, p.closed)).collect();
+        assert_eq!(positions, vec![("SBER", 110.0, true), ("LKOH", 6930.0, false)]);
+
+        let text = report.text(None);
+        assert!(text.contains("Paper positions: 1 open, 1 closed"), "{}", text);
+        assert!(text.contains("SBER 100.000 → 110.000 closed, P&L +10.00% (ema 9/21)"), "{}", text);
+        assert!(text.contains("LKOH 7000.00 → 6930.00, P&L -1.00% (ema 9/21)"), "{}", text);
+
+        // The weekly report also covers the position closed on Monday
+        assert_eq!(Report::new(ReportKind::Weekly, now, &status, 5).paper_positions.len(), 3);
+    }
+}
@@ -1,10 +1,24 @@
 use std::time::Duration;
-use std::collections::HashMap;
-use crate::config::Config;
-use crate::instruments::get_assets::{GetAssetsRequest, GetAssetsResponse, IntoUid};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::SystemTime;
+use crate::chart::signal_chart::{load_candles, SignalChart};
+use crate::config::{Config, SharedConfig};
+use crate::instruments::cache::{CachedInstrument, InstrumentCache, SharedInstruments};
+use crate::instruments::universe::Universe;
+use crate::bot::signal::{SignalEvent, TradeSignal};
 use crate::bot::trade::EmaCrossStrategy;
+use crate::bot::alerts::{check_alerts, AlertStore, Alerts};
+use crate::bot::commands::CommandContext;
 use crate::bot::notifier::SignalNotifier;
-use tracing::{error, info};
+use crate::bot::report::{due_reports, PortfolioReport, Report, ReportKind};
+use crate::bot::status::{InstrumentState, ScannerStatus, SharedStatus, SignalRecord};
+use crate::operations_service::get_portfolio::{GetPortfolioRequest, GetPortfolioResponse};
+use crate::bot::subscribers::SubscriberStore;
+use crate::bot::messages::format_price;
+use crate::notifiers::{self, Notifier, SignalNotification};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, error, info, warn};
 use tokio::sync::oneshot;
 use tokio::select;
 use crate::market_data_service::get_trading_statuses::GetTradingStatusesResponse;
@@ -13,25 +27,69 @@ use crate::market_data_service::get_trading_statuses::_check_instruments_availab
 pub struct MarketScanner {
     client: reqwest::Client,
     config: Config,
+    config_path: String,
+    /// Modification time of the config file when it was last loaded
+    config_modified: Option<SystemTime>,
+    shared_config: SharedConfig,
     notifier: SignalNotifier,
+    /// Webhook, Slack, Discord and email notifiers from config
+    notifiers: Vec<Box<dyn Notifier>>,
+    alerts: Alerts,
     scan_interval: Duration,
     strategies: HashMap<String, EmaCrossStrategy>,
+    /// Instrument metadata, refreshed when older than `instruments.refresh_hours`
+    instruments: SharedInstruments,
+    /// Selects the scanned instruments from the cache
+    universe: Universe,
+    status: SharedStatus,
+    /// Signals, alerts and reports are only logged
+    dry_run: bool,
 }
 
 impl MarketScanner {
-    pub fn new(config: Config) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(config: Config, config_path: &str, dry_run: bool) -> Result<Self, Box<dyn std::error::Error>> {
         let client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(30))
             .build()?;
 
-        let notifier = SignalNotifier::new(&config.telegram_token);
+        let mut subscribers = SubscriberStore::load(&config.telegram.subscribers_file)?;
+        // A dry run leaves the subscribers file as it is
+        if !dry_run {
+            subscribers.retain_allowed(&config.telegram);
+        }
+        let alerts = Arc::new(Mutex::new(AlertStore::load(&config.telegram.alerts_file)?));
+        let instruments = Arc::new(RwLock::new(InstrumentCache::load(&config.instruments.cache_file)?));
+        let mut status = ScannerStatus::new();
+        status.load_history(&config.reports.history_file)?;
+        let shared_config = Arc::new(RwLock::new(config.clone()));
+        let notifier = SignalNotifier::new(&config.telegram_token, subscribers, shared_config.clone());
+        let notifiers = notifiers::from_config(&client, &config.notifiers)?;
+        for extra in &notifiers {
+            info!("Signals are also sent to {}", extra.name());
+        }
+
+        if !config.telegram.is_restricted() {
+            warn!("Neither telegram.allowed_ids nor telegram.admin_ids are set: anyone can subscribe to the bot");
+        }
+        if config.telegram.admin_ids.is_empty() {
+            warn!("telegram.admin_ids is empty: control commands are disabled");
+        }
 
         Ok(Self { 
             client, 
             config: config.clone(),
+            config_path: config_path.to_string(),
+            config_modified: file_modified(config_path),
+            shared_config,
             notifier,
+            notifiers,
+            alerts,
             scan_interval: Duration::from_secs(config.scan_interval_seconds),
             strategies: HashMap::new(),
+            instruments,
+            universe: Universe::new(),
+            status: Arc::new(RwLock::new(status)),
+            dry_run,
         })
     }
 
@@ -39,7 +97,16 @@ impl MarketScanner {
         &mut self,
         mut shutdown: oneshot::Receiver<()>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        self.notifier.start_listener().await;
+        self.notifier.start_listener(CommandContext {
+            subscribers: self.notifier.subscribers(),
+            status: self.status.clone(),
+            client: self.client.clone(),
+            config: self.shared_config.clone(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            trades: self.notifier.trades(),
+            alerts: self.alerts.clone(),
+            instruments: self.instruments.clone(),
+        }).await;
         
         info!("Starting continuous market scanning with interval {:?}", self.scan_interval);
         
@@ -52,14 +119,36 @@ impl MarketScanner {
                 }
                 // Perform scanning
                 _ = async {
-                    match self.scan_market().await {
-                        Ok(_) => {
-                            info!("Scanning cycle completed successfully. Pause {:?}", self.scan_interval);
+                    // Reload on /reload or when the config file has been changed
+                    let requested = std::mem::take(&mut self.status.write().await.reload_requested);
+                    let modified = file_modified(&self.config_path);
+                    if requested || modified != self.config_modified {
+                        if !requested {
+                            info!("Config file {} has changed", self.config_path);
                         }
-                        Err(e) => {
-                            error!("Error during market scanning: {}. Pause {:?}", e, self.scan_interval);
+                        self.config_modified = modified;
+                        self.reload_config().await;
+                    }
+
+                    if self.status.read().await.paused {
+                        info!("Scanning is paused. Pause {:?}", self.scan_interval);
+                    } else {
+                        match self.scan_market().await {
+                            Ok(_) => {
+                                info!("Scanning cycle completed successfully. Pause {:?}", self.scan_interval);
+                            }
+                            Err(e) => {
+                                error!("Error during market scanning: {}. Pause {:?}", e, self.scan_interval);
+                                self.status.write().await.record_error(e.to_string());
+                            }
                         }
                     }
+                    // Alerts don't depend on scanning and are checked even while it is paused
+                    self.check_alerts().await;
+                    if !self.dry_run {
+                        self.send_due_reports().await;
+                        self.status.read().await.save_history(&self.config.reports.history_file);
+                    }
                     tokio::time::sleep(self.scan_interval).await;
                 } => {}
             }
@@ -69,99 +158,419 @@ impl MarketScanner {
         Ok(())
     }
 
-    /// Scans the market and returns trading signals for available instruments
-    async fn scan_market(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        info!("Starting market scanning cycle");
+    /// Runs one scan cycle, prints the signals and waits until they are delivered, a dry run only prints them
+    pub async fn scan_once(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let started = chrono::Utc::now();
+        self.scan_market().await?;
+
+        let status = self.status.read().await;
+        let signals: Vec<_> = status.signals.iter().filter(|s| s.time >= started).collect();
+        println!("Scanned {} instruments, {} signals", status.instruments_scanned, signals.len());
+        for signal in signals {
+            println!("{:?} {} at {} ({})", signal.signal, signal.ticker, format_price(signal.price, None), signal.strategy);
+        }
+        if !self.dry_run {
+            status.save_history(&self.config.reports.history_file);
+        }
+        drop(status);
 
-        let request = GetAssetsRequest::new(
-            self.config.assets.instrument_type.clone(),
-            self.config.assets.instrument_status,
-        );
+        self.notifier.wait_delivered().await;
+        Ok(())
+    }
 
-        let assets_response = match GetAssetsResponse::get_assets(&self.client, &self.config.t_token, request).await {
-            Ok(response) => {
-                info!("Successfully received asset data");
-                response
-            }
+    /// Re-reads and validates the config file, keeps the current config if it can't be loaded.
+    /// Changes apply from the next scan, where strategies keep their state unless EMA lengths or interval changed
+    async fn reload_config(&mut self) {
+        let mut config = match Config::new(&self.config_path) {
+            Ok(config) => config,
             Err(e) => {
-                error!("Error getting assets: {}", e);
-                return Err(e);
+                error!("Error reloading config from {}, keeping the current config: {}", self.config_path, e);
+                self.status.write().await.record_error(format!("Config reload: {}", e));
+                return;
             }
         };
 
-        let assets_response_clone = assets_response.clone();
+        if config.scan_interval_seconds != self.config.scan_interval_seconds {
+            info!("Scan interval changed to {}s", config.scan_interval_seconds);
+        }
+        let kept = keep_restart_only_keys(&self.config, &mut config);
+        if !kept.is_empty() {
+            warn!("{} take effect after restart, keeping the current values", kept.join(", "));
+        }
 
-        let filtered_instruments = match assets_response
-            .filter_instruments(&self.config.filter.class_code, &self.config.filter.instrument_type.as_str())
-            .await
-        {
-            Ok(instruments) => {
-                info!("Assets filtered successfully");
-                instruments
+        match notifiers::from_config(&self.client, &config.notifiers) {
+            Ok(notifiers) => self.notifiers = notifiers,
+            Err(e) => {
+                error!("Error creating notifiers, keeping the current ones: {}", e);
+                self.status.write().await.record_error(format!("Notifiers: {}", e));
             }
+        }
+
+        // Chats removed from the allowlist stop receiving signals and reports
+        if !self.dry_run {
+            self.notifier.subscribers().lock().await.retain_allowed(&config.telegram);
+        }
+
+        self.scan_interval = Duration::from_secs(config.scan_interval_seconds);
+        *self.shared_config.write().await = config.clone();
+        self.config = config;
+        info!("Config reloaded from {}", self.config_path);
+    }
+
+    /// Minimum price step of the instrument from the cache
+    async fn price_increment(&self, instrument_uid: &str) -> Option<f64> {
+        self.instruments
+            .read()
+            .await
+            .by_uid(instrument_uid)
+            .map(|instrument| instrument.min_price_increment)
+            .filter(|increment| *increment > 0.0)
+    }
+
+    /// Evaluates price and indicator alerts and notifies the chats whose alerts fired, only logs them in dry run
+    async fn check_alerts(&mut self) {
+        let interval = self.config.strategy.interval;
+        let triggered = match check_alerts(&self.client, &self.config.t_token, &self.alerts, interval, self.dry_run).await {
+            Ok(triggered) => triggered,
             Err(e) => {
-                error!("Error filtering assets: {}", e);
-                return Err(e);
+                error!("Error checking alerts: {}", e);
+                self.status.write().await.record_error(format!("Alerts: {}", e));
+                return;
+            }
+        };
+
+        for alert in &triggered {
+            if self.dry_run {
+                info!("Dry run, alert #{} for chat {} is not delivered", alert.alert.id, alert.alert.chat_id);
+                continue;
+            }
+            let increment = self.price_increment(&alert.alert.instrument_uid).await;
+            self.notifier.send_alert(alert, increment).await;
+        }
+    }
+
+    /// Sends the daily and weekly reports whose time has come since the last check
+    async fn send_due_reports(&mut self) {
+        let now = chrono::Utc::now();
+        let since = std::mem::replace(&mut self.status.write().await.last_report_check, now);
+
+        for kind in due_reports(&self.config.reports, since, now) {
+            let report = self.build_report(kind, now).await;
+            info!("Sending {}", report.title());
+
+            let notifiers = std::iter::once(&self.notifier as &dyn Notifier)
+                .chain(self.notifiers.iter().map(|notifier| notifier.as_ref()));
+            for notifier in notifiers {
+                if let Err(e) = notifier.send_report(&report).await {
+                    error!("Error sending report to {}: {}", notifier.name(), e);
+                    self.status.write().await.record_error(format!("{} notifier: {}", notifier.name(), e));
+                }
+            }
+        }
+    }
+
+    /// Report from the scanner state, with positions if trading is configured
+    async fn build_report(&self, kind: ReportKind, now: chrono::DateTime<chrono::Utc>) -> Report {
+        let portfolio = match &self.config.trading {
+            Some(trading) => Some(
+                GetPortfolioResponse::get_portfolio(
+                    &self.client,
+                    &self.config.t_token,
+                    GetPortfolioRequest::new(&trading.account_id),
+                    trading.sandbox,
+                )
+                .await
+                .map_err(|e| e.to_string()),
+            ),
+            None => None,
+        };
+
+        let status = self.status.read().await;
+        let mut report = Report::new(kind, now, &status, self.config.reports.closest);
+        match portfolio {
+            Some(Ok(portfolio)) => report.portfolio = Some(PortfolioReport::new(&portfolio, &*self.instruments.read().await)),
+            Some(Err(e)) => {
+                error!("Error getting portfolio for the report: {}", e);
+                report.portfolio_error = Some(e);
+            }
+            None => {}
+        }
+        report
+    }
+
+    /// Scans the market and returns trading signals for available instruments
+    async fn scan_market(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        info!("Starting market scanning cycle");
+
+        if self.instruments.read().await.is_stale(self.config.instruments.refresh_hours) {
+            // Commands keep reading the current cache while the lists download
+            match InstrumentCache::download(&self.client, &self.config.t_token, self.config.assets.instrument_status).await {
+                Ok(instruments) => self.instruments.write().await.update(instruments),
+                Err(e) => {
+                    if self.instruments.read().await.is_empty() {
+                        error!("Error loading instruments: {}", e);
+                        return Err(e);
+                    }
+                    error!("Error refreshing instruments, using the cached ones: {}", e);
+                    self.status.write().await.record_error(format!("Instrument cache: {}", e));
+                }
             }
+        }
+
+        let selected = {
+            let instruments = self.instruments.read().await;
+            self.universe
+                .select(&self.client, &self.config.t_token, &self.config.filter, &instruments)
+                .await
         };
+        let filtered_instruments: Vec<String> = selected.iter().map(|instrument| instrument.uid.clone()).collect();
+        let selected: HashMap<String, CachedInstrument> =
+            selected.into_iter().map(|instrument| (instrument.uid.clone(), instrument)).collect();
 
-        GetAssetsResponse::print_filtered_instruments(&filtered_instruments);
+        // Instruments no longer matching the filter lose their strategy state
+        self.strategies.retain(|uid, _| selected.contains_key(uid));
 
-        _check_instruments_availability(&self.client, &self.config.t_token, filtered_instruments.clone().into_uids())
+        _check_instruments_availability(&self.client, &self.config.t_token, filtered_instruments.clone())
             .await?;
 
         let trading_statuses = GetTradingStatusesResponse::get_trading_statuses(
             &self.client,
             &self.config.t_token,
-            filtered_instruments.into_uids(),
+            filtered_instruments,
         )
         .await?;
 
         let available_instruments = trading_statuses.get_available_instruments();
         info!("Found {} available instruments for trading", available_instruments.len());
 
+        let mut universe = BTreeMap::new();
+        let mut errors = Vec::new();
+        let mut signals = Vec::new();
+
         for available_instrument in available_instruments {
-            let ticker = assets_response_clone.get_instrument_ticker(&available_instrument)
-                .unwrap_or_else(|| available_instrument.clone());
-
-            let strategy = self.strategies.entry(available_instrument.clone()).or_insert_with(|| {
-                EmaCrossStrategy::new(
-                    available_instrument.clone(),
-                    ticker.clone(),
-                    self.config.strategy.short_ema_length,
-                    self.config.strategy.long_ema_length,
-                    self.config.strategy.interval,
-                    self.config.strategy.hysteresis_percentage,
-                    self.config.strategy.hysteresis_periods,
-                )
-            });
+            let Some(instrument) = selected.get(&available_instrument) else {
+                continue;
+            };
+            let ticker = instrument.ticker.clone();
+            let parameters = self.config.strategy_for(&ticker, &instrument.class_code, instrument.sector());
+
+            let strategy = EmaCrossStrategy::configure(&mut self.strategies, &available_instrument, &ticker, &parameters);
 
-            match strategy.get_trade_signal(&self.client, &self.config.t_token).await {
-                Ok(signal) => {
-                    info!("Received signal {:?} for instrument {} ({})", 
-                        signal, 
-                        strategy.get_ticker(), 
-                        available_instrument
-                    );
-                    let trade_signal = crate::bot::signal::TradeSignal::from(signal);
-                    self.notifier.notify_signal(
-                        &format!("{} ({})", strategy.get_ticker(), available_instrument),
-                        &trade_signal,
-                        strategy.get_last_short(),
-                        strategy.get_last_long(),
-                        strategy.get_last_price()
-                    ).await;
+            // Strategies are evaluated once per closed candle of their interval
+            if !strategy.is_due(chrono::Utc::now()) {
+                debug!("Skipping {} ({}): candle has not closed yet", strategy.get_ticker(), available_instrument);
+            } else {
+                match strategy.get_trade_signal(&self.client, &self.config.t_token).await {
+                    Ok(signal) => {
+                        info!("Received signal {:?} for instrument {} ({})", 
+                            signal, 
+                            strategy.get_ticker(), 
+                            available_instrument
+                        );
+                        let price_increment = Some(instrument.min_price_increment).filter(|i| *i > 0.0);
+                        let event = SignalEvent {
+                            ticker: strategy.get_ticker().to_string(),
+                            instrument_uid: available_instrument.clone(),
+                            strategy: EmaCrossStrategy::NAME.to_string(),
+                            signal: TradeSignal::from(signal),
+                            short_ema: strategy.get_last_short(),
+                            long_ema: strategy.get_last_long(),
+                            last_price: strategy.get_last_price(),
+                            price_increment,
+                        };
+                        if event.signal == TradeSignal::Hold {
+                            info!(
+                                "HOLD POSITION\n\
+                                Instrument: {} ({})\n\
+                                Last Price: {}\n\
+                                Short EMA: {:.6}\n\
+                                Long EMA: {:.6}\n\
+                                Difference: {:.6}%",
+                                event.ticker,
+                                event.instrument_uid,
+                                format_price(event.last_price, event.price_increment),
+                                event.short_ema,
+                                event.long_ema,
+                                event.ema_percentage()
+                            );
+                        } else {
+                            let kill_switch = {
+                                let mut status = self.status.write().await;
+                                status.record_signal(SignalRecord {
+                                    time: chrono::Utc::now(),
+                                    ticker: event.ticker.clone(),
+                                    strategy: event.strategy.clone(),
+                                    signal,
+                                    price: event.last_price,
+                                });
+                                status.kill_switch
+                            };
+                            if kill_switch {
+                                info!("Kill switch engaged, signal for {} is not delivered", strategy.get_ticker());
+                            } else {
+                                let chart = if self.config.telegram.charts {
+                                    signal_chart(&self.client, &self.config, strategy, &event).await
+                                } else {
+                                    None
+                                };
+                                signals.push(SignalNotification {
+                                    event,
+                                    chart: chart.map(Arc::new),
+                                    time: chrono::Utc::now(),
+                                });
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("Error getting signal for {} ({}): {}", 
+                            strategy.get_ticker(), 
+                            available_instrument, 
+                            e
+                        );
+                        errors.push(format!("{}: {}", strategy.get_ticker(), e));
+                    }
                 }
-                Err(e) => {
-                    error!("Error getting signal for {} ({}): {}", 
-                        strategy.get_ticker(), 
-                        available_instrument, 
-                        e
-                    );
+            }
+
+            universe.insert(strategy.get_ticker().to_uppercase(), InstrumentState {
+                uid: available_instrument.clone(),
+                ticker: strategy.get_ticker().to_string(),
+                class_code: instrument.class_code.clone(),
+                sector: instrument.sector().map(str::to_string),
+                last_price: strategy.get_last_price(),
+                short_ema: strategy.get_last_short(),
+                long_ema: strategy.get_last_long(),
+                last_signal: strategy.get_last_signal(),
+            });
+        }
+
+        // Signals of this scan are delivered together
+        if self.dry_run {
+            for signal in &signals {
+                info!("Dry run, signal is not delivered: {}", notifiers::plain_text(&signal.event));
+            }
+        } else if !signals.is_empty() {
+            let notifiers = std::iter::once(&self.notifier as &dyn Notifier)
+                .chain(self.notifiers.iter().map(|notifier| notifier.as_ref()));
+            for notifier in notifiers {
+                if let Err(e) = notifier.send_signals(&signals).await {
+                    error!("Error sending signals to {}: {}", notifier.name(), e);
+                    errors.push(format!("{} notifier: {}", notifier.name(), e));
                 }
             }
         }
 
+        let mut status = self.status.write().await;
+        status.last_scan = Some(chrono::Utc::now());
+        status.instruments_scanned = universe.len();
+        status.instruments = universe;
+        for error in errors {
+            status.record_error(error);
+        }
+
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+/// Modification time of a file, None if it can't be read
+fn file_modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Puts back the current values of keys that are only read on startup into a reloaded `config`,
+/// returns the keys that were changed in the file
+fn keep_restart_only_keys(current: &Config, config: &mut Config) -> Vec<&'static str> {
+    let mut kept = Vec::new();
+    if config.telegram_token != current.telegram_token {
+        config.telegram_token = current.telegram_token.clone();
+        kept.push("telegram_token");
+    }
+    if config.telegram.subscribers_file != current.telegram.subscribers_file {
+        config.telegram.subscribers_file = current.telegram.subscribers_file.clone();
+        kept.push("telegram.subscribers_file");
+    }
+    if config.telegram.alerts_file != current.telegram.alerts_file {
+        config.telegram.alerts_file = current.telegram.alerts_file.clone();
+        kept.push("telegram.alerts_file");
+    }
+    if config.instruments.cache_file != current.instruments.cache_file {
+        config.instruments.cache_file = current.instruments.cache_file.clone();
+        kept.push("instruments.cache_file");
+    }
+    kept
+}
+
+/// Renders the chart for a signal notification, the signal is sent without a chart if it fails
+async fn signal_chart(
+    client: &reqwest::Client,
+    config: &Config,
+    strategy: &EmaCrossStrategy,
+    event: &SignalEvent,
+) -> Option<Vec<u8>> {
+    let candles = load_candles(
+        client,
+        &config.t_token,
+        &event.instrument_uid,
+        strategy.get_interval(),
+        config.telegram.chart_candles,
+    )
+    .await
+    .map_err(|e| e.to_string());
+
+    let chart = candles.and_then(|candles| {
+        SignalChart::new(
+            event.ticker.clone(),
+            &candles,
+            strategy.ema_history(),
+            strategy.get_short_length(),
+            strategy.get_long_length(),
+            event.signal,
+            event.last_price,
+        )
+        .render()
+        .map_err(|e| e.to_string())
+    });
+
+    match chart {
+        Ok(chart) => Some(chart),
+        Err(e) => {
+            error!("Error rendering chart for {}: {}", event.ticker, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Config {
+        serde_yaml::from_str(include_str!("../../config.example.yaml")).unwrap()
+    }
+
+    #[test]
+    fn restart_only_keys_keep_their_values() {
+        let current = example();
+        let mut config = example();
+        config.telegram_token = serde_yaml::from_str("other-token").unwrap();
+        config.telegram.alerts_file = "other/alerts.json".to_string();
+        config.instruments.cache_file = "other/instruments.json".to_string();
+        config.scan_interval_seconds = 60;
+        config.strategy.hysteresis_percentage = 0.5;
+
+        let kept = keep_restart_only_keys(&current, &mut config);
+
+        assert_eq!(kept, ["telegram_token", "telegram.alerts_file", "instruments.cache_file"]);
+        assert_eq!(config.telegram_token, current.telegram_token);
+        assert_eq!(config.telegram.alerts_file, current.telegram.alerts_file);
+        assert_eq!(config.instruments.cache_file, current.instruments.cache_file);
+        // Everything else is reloaded
+        assert_eq!(config.scan_interval_seconds, 60);
+        assert_eq!(config.strategy.hysteresis_percentage, 0.5);
+    }
+
+    #[test]
+    fn unchanged_config_keeps_nothing() {
+        assert!(keep_restart_only_keys(&example(), &mut example()).is_empty());
+    }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{debug, info};
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
 pub enum Signal {
@@ -25,6 +25,35 @@ impl From<Signal> for TradeSignal {
     }
 }
 
+/// Trading signal for an instrument together with the data it was based on
+#[derive(Debug, Clone)]
+pub struct SignalEvent {
+    pub ticker: String,
+    pub instrument_uid: String,
+    pub strategy: String,
+    pub signal: TradeSignal,
+    pub short_ema: f64,
+    pub long_ema: f64,
+    pub last_price: f64,
+    /// Minimum price step of the instrument, None if unknown
+    pub price_increment: Option<f64>,
+}
+
+impl SignalEvent {
+    /// Difference between short and long EMA in percent of the long EMA
+    pub fn ema_percentage(&self) -> f64 {
+        if self.long_ema == 0.0 {
+            return 0.0;
+        }
+        (self.short_ema - self.long_ema) / self.long_ema * 100.0
+    }
+
+    /// Signal strength as the absolute EMA difference in percent
+    pub fn strength(&self) -> f64 {
+        self.ema_percentage().abs()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum State {
     Above,
@@ -58,6 +87,24 @@ impl CrossoverSignal {
         }
     }
 
+    /// Applies new thresholds on config reload, the crossover state is kept
+    pub fn set_hysteresis(&mut self, hysteresis_percentage: f64, hysteresis_periods: u32) {
+        self.hysteresis_percentage = hysteresis_percentage;
+        self.hysteresis_periods = hysteresis_periods;
+    }
+
+    /// Replays historical EMA values through `update` so the state matches
+    /// what it would have been had the bot been running continuously
+    pub fn warm_up(&mut self, history: &[(f64, f64)]) {
+        for &(short_ema, long_ema) in history {
+            self.update(short_ema, long_ema);
+        }
+    }
+
+    pub fn last_signal(&self) -> Option<Signal> {
+        self.last_signal
+    }
+
     pub fn update(&mut self, short_ema: f64, long_ema: f64) -> Signal {
         if long_ema == 0.0 {
             return Signal::Hold;
@@ -66,7 +113,7 @@ impl CrossoverSignal {
         let ema_diff = short_ema - long_ema;
         let ema_percentage = ema_diff / long_ema * 100.0;
         
-        info!(
+        debug!(
             "EMA analysis: short={}, long={}, difference={}%, threshold={}%",
             short_ema, long_ema, ema_percentage, self.hysteresis_percentage
         );
@@ -106,16 +153,18 @@ impl CrossoverSignal {
                 }
             }
             // Check for sell signal (short EMA below long EMA)
-            else if self.state == State::Below && ema_percentage < -self.hysteresis_percentage {
-                if self.time_in_state >= self.hysteresis_periods && self.last_signal != Some(Signal::Sell) {
-                    info!("Sell signal generated after crossing and exceeding hysteresis threshold");
-                    self.last_signal = Some(Signal::Sell);
-                    self.time_in_state = 0;
-                    self.has_crossed = false;
-                    self.last_short_ema = Some(short_ema);
-                    self.last_long_ema = Some(long_ema);
-                    return Signal::Sell;
-                }
+            else if self.state == State::Below
+                && ema_percentage < -self.hysteresis_percentage
+                && self.time_in_state >= self.hysteresis_periods
+                && self.last_signal != Some(Signal::Sell)
+            {
+                info!("Sell signal generated after crossing and exceeding hysteresis threshold");
+                self.last_signal = Some(Signal::Sell);
+                self.time_in_state = 0;
+                self.has_crossed = false;
+                self.last_short_ema = Some(short_ema);
+                self.last_long_ema = Some(long_ema);
+                return Signal::Sell;
             }
         }
 
@@ -125,4 +174,95 @@ impl CrossoverSignal {
 
         Signal::Hold
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(generator: &mut CrossoverSignal, values: &[(f64, f64)]) -> Vec<Signal> {
+        values.iter().map(|&(short, long)| generator.update(short, long)).collect()
+    }
+
+    #[test]
+    fn golden_cross_above_threshold_buys() {
+        let mut generator = CrossoverSignal::new(0.1, 1);
+        assert_eq!(feed(&mut generator, &[(100.0, 101.0), (101.0, 100.0)]), vec![Signal::Hold, Signal::Buy]);
+        assert_eq!(generator.last_signal(), Some(Signal::Buy));
+    }
+
+    #[test]
+    fn death_cross_above_threshold_sells() {
+        let mut generator = CrossoverSignal::new(0.1, 1);
+        assert_eq!(feed(&mut generator, &[(101.0, 100.0), (99.0, 100.0)]), vec![Signal::Hold, Signal::Sell]);
+    }
+
+    #[test]
+    fn crossover_waits_for_hysteresis_percentage() {
+        let mut generator = CrossoverSignal::new(1.0, 1);
+        let signals = feed(&mut generator, &[(100.0, 101.0), (100.5, 100.0), (102.0, 100.0)]);
+        assert_eq!(signals, vec![Signal::Hold, Signal::Hold, Signal::Buy]);
+    }
+
+    #[test]
+    fn crossover_waits_for_hysteresis_periods() {
+        let mut generator = CrossoverSignal::new(0.1, 3);
+        let signals = feed(&mut generator, &[(100.0, 101.0), (101.0, 100.0), (102.0, 100.0), (103.0, 100.0)]);
+        assert_eq!(signals, vec![Signal::Hold, Signal::Hold, Signal::Hold, Signal::Buy]);
+    }
+
+    #[test]
+    fn same_signal_is_not_repeated() {
+        let mut generator = CrossoverSignal::new(2.0, 1);
+        // Dips below without reaching the threshold, then crosses up again
+        let signals = feed(
+            &mut generator,
+            &[(100.0, 101.0), (103.0, 100.0), (99.5, 100.0), (101.0, 100.0), (104.0, 100.0)],
+        );
+        assert_eq!(signals, vec![Signal::Hold, Signal::Buy, Signal::Hold, Signal::Hold, Signal::Hold]);
+    }
+
+    #[test]
+    fn zero_long_ema_holds() {
+        let mut generator = CrossoverSignal::new(0.1, 1);
+        assert_eq!(feed(&mut generator, &[(100.0, 0.0), (101.0, 0.0)]), vec![Signal::Hold, Signal::Hold]);
+        assert_eq!(generator.last_signal(), None);
+    }
+
+    #[test]
+    fn warm_up_restores_last_signal() {
+        let mut generator = CrossoverSignal::new(0.1, 1);
+        generator.warm_up(&[(100.0, 101.0), (101.0, 100.0)]);
+        assert_eq!(generator.last_signal(), Some(Signal::Buy));
+        // The historical buy is not signalled again
+        assert_eq!(generator.update(102.0, 100.0), Signal::Hold);
+    }
+
+    #[test]
+    fn warm_up_keeps_hysteresis_counters() {
+        let mut generator = CrossoverSignal::new(0.1, 2);
+        generator.warm_up(&[(100.0, 101.0), (101.0, 100.0)]);
+        assert_eq!(generator.last_signal(), None);
+        // Second period after the historical crossover
+        assert_eq!(generator.update(102.0, 100.0), Signal::Buy);
+    }
+
+    #[test]
+    fn warm_up_matches_continuous_updates() {
+        let history = [(100.0, 101.0), (102.0, 100.0), (101.0, 100.5), (99.0, 100.0), (98.0, 100.0)];
+        let mut continuous = CrossoverSignal::new(0.5, 2);
+        feed(&mut continuous, &history);
+        let mut warmed = CrossoverSignal::new(0.5, 2);
+        warmed.warm_up(&history);
+
+        assert_eq!(warmed.last_signal(), continuous.last_signal());
+        assert_eq!(warmed.update(97.0, 100.0), continuous.update(97.0, 100.0));
+    }
+
+    #[test]
+    fn set_hysteresis_keeps_crossover_state() {
+        let mut generator = CrossoverSignal::new(5.0, 1);
+        assert_eq!(feed(&mut generator, &[(100.0, 101.0), (102.0, 100.0)]), vec![Signal::Hold, Signal::Hold]);
+        generator.set_hysteresis(1.0, 1);
+        assert_eq!(generator.update(102.5, 100.0), Signal::Buy);
+    }
+}
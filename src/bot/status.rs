@@ -0,0 +1,315 @@
+use crate::bot::signal::Signal;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info};
+
+/// Signals and errors are kept long enough for the weekly report
+const HISTORY_DAYS: i64 = 8;
+
+/// Last known state of an instrument in the scanned universe
+#[derive(Debug, Clone, Serialize)]
+pub struct InstrumentState {
+    pub uid: String,
+    pub ticker: String,
+    pub class_code: String,
+    /// Known when sector strategy overrides are configured
+    pub sector: Option<String>,
+    pub last_price: f64,
+    pub short_ema: f64,
+    pub long_ema: f64,
+    pub last_signal: Option<Signal>,
+}
+
+impl InstrumentState {
+    /// EMA difference in percent of the long EMA
+    pub fn spread(&self) -> f64 {
+        if self.long_ema != 0.0 {
+            (self.short_ema - self.long_ema) / self.long_ema * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Buy or Sell signal emitted by the scanner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalRecord {
+    pub time: DateTime<Utc>,
+    pub ticker: String,
+    pub strategy: String,
+    pub signal: Signal,
+    pub price: f64,
+}
+
+/// Long position opened at the price of a Buy signal and closed by the next Sell of the instrument
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperPosition {
+    pub ticker: String,
+    pub strategy: String,
+    pub opened_at: DateTime<Utc>,
+    pub entry_price: f64,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub exit_price: Option<f64>,
+}
+
+impl PaperPosition {
+    /// P&L in percent at the exit price, or at `price` while the position is open
+    pub fn pnl_percentage(&self, price: f64) -> f64 {
+        let price = self.exit_price.unwrap_or(price);
+        if self.entry_price > 0.0 {
+            (price / self.entry_price - 1.0) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Report history written to `reports.history_file`
+#[derive(Serialize, Deserialize, Default)]
+struct StoredHistory {
+    last_report_check: Option<DateTime<Utc>>,
+    signals: VecDeque<SignalRecord>,
+    error_times: VecDeque<DateTime<Utc>>,
+    paper_positions: Vec<PaperPosition>,
+}
+
+/// Scanner state shared with the Telegram command handlers
+#[derive(Debug)]
+pub struct ScannerStatus {
+    pub started_at: DateTime<Utc>,
+    pub last_scan: Option<DateTime<Utc>>,
+    pub instruments_scanned: usize,
+    pub errors: u64,
+    pub last_error: Option<String>,
+    pub paused: bool,
+    /// Emergency stop of scanning and signal delivery, cleared by /resume
+    pub kill_switch: bool,
+    /// Config file is reloaded before the next scan
+    pub reload_requested: bool,
+    /// Current universe by ticker
+    pub instruments: BTreeMap<String, InstrumentState>,
+    /// Signals of the last days for reports
+    pub signals: VecDeque<SignalRecord>,
+    /// Times of errors of the last days for reports
+    pub error_times: VecDeque<DateTime<Utc>>,
+    /// Open paper positions and those closed in the last days
+    pub paper_positions: Vec<PaperPosition>,
+    /// Reports scheduled up to this time have been sent
+    pub last_report_check: DateTime<Utc>,
+}
+
+pub type SharedStatus = Arc<RwLock<ScannerStatus>>;
+
+impl ScannerStatus {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            last_scan: None,
+            instruments_scanned: 0,
+            errors: 0,
+            last_error: None,
+            paused: false,
+            kill_switch: false,
+            reload_requested: false,
+            instruments: BTreeMap::new(),
+            signals: VecDeque::new(),
+            error_times: VecDeque::new(),
+            paper_positions: Vec::new(),
+            last_report_check: Utc::now(),
+        }
+    }
+
+    /// Restores the report history saved by `save_history`, nothing is restored if the file doesn't exist yet
+    pub fn load_history(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        let path = Path::new(path);
+        if !path.exists() {
+            return Ok(());
+        }
+        let stored: StoredHistory = serde_json::from_str(&fs::read_to_string(path)?)?;
+        info!(
+            "Loaded {} signals and {} paper positions from {}",
+            stored.signals.len(),
+            stored.paper_positions.len(),
+            path.display()
+        );
+
+        if let Some(last_report_check) = stored.last_report_check {
+            self.last_report_check = last_report_check;
+        }
+        self.signals = stored.signals;
+        self.error_times = stored.error_times;
+        self.paper_positions = stored.paper_positions;
+        self.prune_history();
+        Ok(())
+    }
+
+    /// Writes the report history atomically via a temporary file
+    pub fn save_history(&self, path: &str) {
+        let result = (|| -> Result<(), Box<dyn Error>> {
+            let path = Path::new(path);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let stored = StoredHistory {
+                last_report_check: Some(self.last_report_check),
+                signals: self.signals.clone(),
+                error_times: self.error_times.clone(),
+                paper_positions: self.paper_positions.clone(),
+            };
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_string(&stored)?)?;
+            fs::rename(tmp, path)?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            error!("Error saving report history to {}: {}", path, e);
+        }
+    }
+
+    pub fn record_error(&mut self, error: String) {
+        self.errors += 1;
+        self.last_error = Some(error);
+        self.error_times.push_back(Utc::now());
+        self.prune_history();
+    }
+
+    /// Records the signal and opens or closes the paper position of the instrument
+    pub fn record_signal(&mut self, signal: SignalRecord) {
+        let open = self
+            .paper_positions
+            .iter_mut()
+            .find(|position| position.closed_at.is_none() && position.ticker == signal.ticker);
+        match (signal.signal, open) {
+            (Signal::Buy, None) => self.paper_positions.push(PaperPosition {
+                ticker: signal.ticker.clone(),
+                strategy: signal.strategy.clone(),
+                opened_at: signal.time,
+                entry_price: signal.price,
+                closed_at: None,
+                exit_price: None,
+            }),
+            (Signal::Sell, Some(position)) => {
+                position.closed_at = Some(signal.time);
+                position.exit_price = Some(signal.price);
+            }
+            _ => {}
+        }
+
+        self.signals.push_back(signal);
+        self.prune_history();
+    }
+
+    fn prune_history(&mut self) {
+        let oldest = Utc::now() - Duration::days(HISTORY_DAYS);
+        while self.signals.front().is_some_and(|s| s.time < oldest) {
+            self.signals.pop_front();
+        }
+        while self.error_times.front().is_some_and(|time| *time < oldest) {
+            self.error_times.pop_front();
+        }
+        self.paper_positions.retain(|position| position.closed_at.is_none_or(|closed_at| closed_at >= oldest));
+    }
+
+    /// Finds an instrument of the current universe by ticker, case-insensitive
+    pub fn find_by_ticker(&self, ticker: &str) -> Option<&InstrumentState> {
+        self.instruments.get(&ticker.to_uppercase())
+    }
+}
+
+/// Formats a duration as "1d 2h 3m"
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let minutes = duration.num_minutes();
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+impl Default for ScannerStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(minutes_ago: i64, ticker: &str, signal: Signal, price: f64) -> SignalRecord {
+        SignalRecord {
+            time: Utc::now() - Duration::minutes(minutes_ago),
+            ticker: ticker.to_string(),
+            strategy: "ema 9/21".to_string(),
+            signal,
+            price,
+        }
+    }
+
+    #[test]
+    fn buy_opens_and_sell_closes_paper_positions() {
+        let mut status = ScannerStatus::new();
+        status.record_signal(signal(50, "SBER", Signal::Sell, 100.0));
+        status.record_signal(signal(40, "SBER", Signal::Buy, 100.0));
+        status.record_signal(signal(30, "SBER", Signal::Buy, 105.0));
+        status.record_signal(signal(20, "SBER", Signal::Sell, 110.0));
+        status.record_signal(signal(10, "SBER", Signal::Buy, 108.0));
+
+        // A sell without a position and a repeated buy don't change positions
+        let positions: Vec<(f64, Option<f64>)> =
+            status.paper_positions.iter().map(|p| (p.entry_price, p.exit_price)).collect();
+        assert_eq!(positions, vec![(100.0, Some(110.0)), (108.0, None)]);
+        assert!((status.paper_positions[0].pnl_percentage(0.0) - 10.0).abs() < 1e-9);
+        assert!((status.paper_positions[1].pnl_percentage(102.6) + 5.0).abs() < 1e-9);
+        assert_eq!(status.signals.len(), 5);
+    }
+
+    #[test]
+    fn old_closed_positions_are_pruned() {
+        let mut status = ScannerStatus::new();
+        let days_ago = |days| (HISTORY_DAYS + days) * 24 * 60;
+        status.record_signal(signal(days_ago(2), "SBER", Signal::Buy, 100.0));
+        status.record_signal(signal(days_ago(1), "SBER", Signal::Sell, 110.0));
+        status.record_signal(signal(days_ago(1), "GAZP", Signal::Buy, 150.0));
+        status.record_signal(signal(0, "LKOH", Signal::Buy, 7000.0));
+
+        // Open positions are kept however old they are
+        let tickers: Vec<&str> = status.paper_positions.iter().map(|p| p.ticker.as_str()).collect();
+        assert_eq!(tickers, vec!["GAZP", "LKOH"]);
+        assert_eq!(status.signals.len(), 1);
+    }
+
+    #[test]
+    fn history_survives_a_restart() {
+        let path = std::env::temp_dir().join(format!("bcp_report_history_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut status = ScannerStatus::new();
+        // Nothing to restore on the first start
+        status.load_history(path).unwrap();
+        status.record_signal(signal(10, "SBER", Signal::Buy, 100.0));
+        status.record_error("API error".to_string());
+        status.last_report_check = Utc::now() - Duration::hours(1);
+        status.save_history(path);
+
+        let mut restarted = ScannerStatus::new();
+        restarted.load_history(path).unwrap();
+        assert_eq!(restarted.last_report_check, status.last_report_check);
+        assert_eq!(restarted.signals.len(), 1);
+        assert_eq!(restarted.error_times, status.error_times);
+        assert_eq!(restarted.paper_positions.len(), 1);
+        assert_eq!(restarted.paper_positions[0].ticker, "SBER");
+        let _ = fs::remove_file(path);
+    }
+}
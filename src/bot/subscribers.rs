@@ -0,0 +1,364 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::bot::messages::Language;
+use crate::bot::signal::{SignalEvent, TradeSignal};
+use crate::config::TelegramConfig;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use teloxide::types::ChatId;
+use teloxide::{ApiError, RequestError};
+use tracing::{error, info};
+
+/// Which signals a chat wants to receive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preferences {
+    /// Tickers to receive signals for, every instrument if empty
+    #[serde(default)]
+    pub watchlist: BTreeSet<String>,
+    /// Strategies to receive signals from, every strategy if empty
+    #[serde(default)]
+    pub strategies: BTreeSet<String>,
+    #[serde(default = "default_true")]
+    pub buy: bool,
+    #[serde(default = "default_true")]
+    pub sell: bool,
+    /// Minimum absolute EMA difference in percent
+    #[serde(default)]
+    pub min_strength: f64,
+    #[serde(default)]
+    pub language: Language,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            watchlist: BTreeSet::new(),
+            strategies: BTreeSet::new(),
+            buy: true,
+            sell: true,
+            min_strength: 0.0,
+            language: Language::default(),
+        }
+    }
+}
+
+impl Preferences {
+    pub fn accepts(&self, event: &SignalEvent) -> bool {
+        let direction = match event.signal {
+            TradeSignal::Buy => self.buy,
+            TradeSignal::Sell => self.sell,
+            TradeSignal::Hold => false,
+        };
+
+        direction
+            && (self.watchlist.is_empty() || self.watchlist.contains(&event.ticker.to_uppercase()))
+            && (self.strategies.is_empty() || self.strategies.contains(&event.strategy))
+            && event.strength() >= self.min_strength
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscriber {
+    pub chat_id: i64,
+    pub subscribed_at: DateTime<Utc>,
+    #[serde(default)]
+    pub preferences: Preferences,
+    /// Approved by an admin, keeps access without being in `telegram.allowed_ids`
+    #[serde(default)]
+    pub approved: bool,
+}
+
+/// Telegram subscribers persisted to a JSON file on every change
+pub struct SubscriberStore {
+    path: PathBuf,
+    subscribers: HashMap<i64, Subscriber>,
+}
+
+impl SubscriberStore {
+    /// Loads subscribers from the file, starts empty if it doesn't exist yet
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let path = PathBuf::from(path);
+        let subscribers: Vec<Subscriber> = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+        info!("Loaded {} subscribers from {}", subscribers.len(), path.display());
+
+        Ok(Self {
+            path,
+            subscribers: subscribers.into_iter().map(|s| (s.chat_id, s)).collect(),
+        })
+    }
+
+    /// Writes the file atomically via a temporary file
+    fn save(&self) {
+        let result = (|| -> Result<(), Box<dyn Error>> {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut subscribers: Vec<&Subscriber> = self.subscribers.values().collect();
+            subscribers.sort_by_key(|s| s.chat_id);
+            let tmp = self.path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_string_pretty(&subscribers)?)?;
+            fs::rename(tmp, &self.path)?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            error!("Error saving subscribers to {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Adds a subscriber, `approved` if an admin let the chat in. Returns false if the chat was already subscribed,
+    /// its approval is recorded then
+    pub fn insert(&mut self, chat_id: ChatId, approved: bool) -> bool {
+        if let Some(subscriber) = self.subscribers.get_mut(&chat_id.0) {
+            if approved && !subscriber.approved {
+                subscriber.approved = true;
+                self.save();
+            }
+            return false;
+        }
+        self.subscribers.insert(chat_id.0, Subscriber {
+            chat_id: chat_id.0,
+            subscribed_at: Utc::now(),
+            preferences: Preferences::default(),
+            approved,
+        });
+        self.save();
+        true
+    }
+
+    pub fn contains(&self, chat_id: ChatId) -> bool {
+        self.subscribers.contains_key(&chat_id.0)
+    }
+
+    pub fn is_approved(&self, chat_id: ChatId) -> bool {
+        self.subscribers.get(&chat_id.0).is_some_and(|s| s.approved)
+    }
+
+    /// Removes chats that are neither allowed by `telegram` nor approved by an admin, returns their number
+    pub fn retain_allowed(&mut self, telegram: &TelegramConfig) -> usize {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|chat_id, subscriber| {
+            let keep = subscriber.approved || telegram.is_allowed(*chat_id);
+            if !keep {
+                info!("Removed subscriber {}: no longer allowed", chat_id);
+            }
+            keep
+        });
+        let removed = before - self.subscribers.len();
+        if removed > 0 {
+            self.save();
+        }
+        removed
+    }
+
+    pub fn remove(&mut self, chat_id: ChatId) -> bool {
+        let removed = self.subscribers.remove(&chat_id.0).is_some();
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    /// Chats whose preferences accept the signal with their language
+    pub fn recipients(&self, event: &SignalEvent) -> Vec<(ChatId, Language)> {
+        self.subscribers
+            .values()
+            .filter(|s| s.preferences.accepts(event))
+            .map(|s| (ChatId(s.chat_id), s.preferences.language))
+            .collect()
+    }
+
+    /// Every subscribed chat with its watchlist
+    pub fn watchlists(&self) -> Vec<(ChatId, BTreeSet<String>)> {
+        self.subscribers
+            .values()
+            .map(|s| (ChatId(s.chat_id), s.preferences.watchlist.clone()))
+            .collect()
+    }
+
+    pub fn preferences(&self, chat_id: ChatId) -> Option<&Preferences> {
+        self.subscribers.get(&chat_id.0).map(|s| &s.preferences)
+    }
+
+    /// Changes preferences of a subscriber and saves them, returns false if the chat isn't subscribed
+    pub fn update_preferences(&mut self, chat_id: ChatId, update: impl FnOnce(&mut Preferences)) -> bool {
+        let Some(subscriber) = self.subscribers.get_mut(&chat_id.0) else {
+            return false;
+        };
+        update(&mut subscriber.preferences);
+        self.save();
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// Removes the subscriber if the error means the chat can no longer receive messages
+    pub fn prune_on_error(&mut self, chat_id: ChatId, err: &RequestError) -> bool {
+        let unreachable = matches!(
+            err,
+            RequestError::Api(
+                ApiError::BotBlocked
+                    | ApiError::ChatNotFound
+                    | ApiError::GroupDeactivated
+                    | ApiError::BotKicked
+                    | ApiError::BotKickedFromSupergroup
+                    | ApiError::UserDeactivated
+                    | ApiError::CantInitiateConversation
+            )
+        );
+        if unreachable && self.remove(chat_id) {
+            info!("Removed subscriber {}: {}", chat_id, err);
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> SubscriberStore {
+        let path = std::env::temp_dir().join(format!("bcp_subscribers_{}_{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        SubscriberStore::load(path.to_str().unwrap()).unwrap()
+    }
+
+    fn telegram(allowed_ids: Vec<i64>, admin_ids: Vec<i64>) -> TelegramConfig {
+        TelegramConfig {
+            allowed_ids,
+            admin_ids,
+            ..TelegramConfig::default()
+        }
+    }
+
+    #[test]
+    fn retain_allowed_removes_chats_dropped_from_allowlist() {
+        let mut store = store("allowlist");
+        store.insert(ChatId(1), false);
+        store.insert(ChatId(2), false);
+        store.insert(ChatId(3), true);
+
+        assert_eq!(store.retain_allowed(&telegram(vec![1], vec![10])), 1);
+        assert!(store.contains(ChatId(1)));
+        assert!(!store.contains(ChatId(2)));
+        // Approved by an admin, kept without being listed
+        assert!(store.contains(ChatId(3)));
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn retain_allowed_keeps_everyone_without_restrictions() {
+        let mut store = store("open");
+        store.insert(ChatId(1), false);
+        assert_eq!(store.retain_allowed(&telegram(Vec::new(), Vec::new())), 0);
+        assert!(store.contains(ChatId(1)));
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn approval_is_recorded_for_existing_subscriber() {
+        let mut store = store("approval");
+        assert!(store.insert(ChatId(1), false));
+        assert!(!store.is_approved(ChatId(1)));
+        assert!(!store.insert(ChatId(1), true));
+        assert!(store.is_approved(ChatId(1)));
+
+        // Approval is persisted
+        let reloaded = SubscriberStore::load(store.path.to_str().unwrap()).unwrap();
+        assert!(reloaded.is_approved(ChatId(1)));
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn subscribers_without_approval_field_are_not_approved() {
+        let subscriber: Subscriber =
+            serde_json::from_str(r#"{"chat_id": 1, "subscribed_at": "2024-01-01T00:00:00Z"}"#).unwrap();
+        assert!(!subscriber.approved);
+    }
+
+    #[test]
+    fn unreachable_chats_are_pruned_on_send_errors() {
+        let mut store = store("prune");
+        store.insert(ChatId(1), false);
+        store.insert(ChatId(2), false);
+
+        let cases = [
+            (RequestError::Api(ApiError::MessageTextIsEmpty), 1, false),
+            (RequestError::RetryAfter(teloxide::types::Seconds::from_seconds(5)), 1, false),
+            (RequestError::Api(ApiError::BotBlocked), 1, true),
+            (RequestError::Api(ApiError::BotBlocked), 1, false),
+            (RequestError::Api(ApiError::ChatNotFound), 2, true),
+        ];
+        for (err, chat_id, removed) in cases {
+            assert_eq!(store.prune_on_error(ChatId(chat_id), &err), removed, "{} {}", chat_id, err);
+        }
+        assert!(!store.contains(ChatId(1)));
+        assert!(!store.contains(ChatId(2)));
+        let _ = fs::remove_file(&store.path);
+    }
+
+    fn event(ticker: &str, strategy: &str, signal: TradeSignal, short_ema: f64) -> SignalEvent {
+        SignalEvent {
+            ticker: ticker.to_string(),
+            instrument_uid: "uid".to_string(),
+            strategy: strategy.to_string(),
+            signal,
+            short_ema,
+            long_ema: 100.0,
+            last_price: 100.0,
+            price_increment: None,
+        }
+    }
+
+    #[test]
+    fn preferences_filter_signals() {
+        let sber_only = Preferences {
+            watchlist: ["SBER".to_string()].into(),
+            ..Preferences::default()
+        };
+        let ema_only = Preferences {
+            strategies: ["ema_cross".to_string()].into(),
+            ..Preferences::default()
+        };
+        let buy_only = Preferences {
+            sell: false,
+            ..Preferences::default()
+        };
+        let strong = Preferences {
+            min_strength: 0.5,
+            ..Preferences::default()
+        };
+
+        let cases = [
+            ("everything", Preferences::default(), event("GAZP", "ema_cross", TradeSignal::Sell, 99.0), true),
+            ("hold", Preferences::default(), event("GAZP", "ema_cross", TradeSignal::Hold, 101.0), false),
+            ("watched", sber_only.clone(), event("SBER", "ema_cross", TradeSignal::Buy, 101.0), true),
+            ("watched lowercase", sber_only.clone(), event("sber", "ema_cross", TradeSignal::Buy, 101.0), true),
+            ("not watched", sber_only, event("GAZP", "ema_cross", TradeSignal::Buy, 101.0), false),
+            ("strategy", ema_only.clone(), event("GAZP", "ema_cross", TradeSignal::Buy, 101.0), true),
+            ("other strategy", ema_only, event("GAZP", "rsi", TradeSignal::Buy, 101.0), false),
+            ("buy", buy_only.clone(), event("GAZP", "ema_cross", TradeSignal::Buy, 101.0), true),
+            ("sell", buy_only, event("GAZP", "ema_cross", TradeSignal::Sell, 99.0), false),
+            ("strong buy", strong.clone(), event("GAZP", "ema_cross", TradeSignal::Buy, 100.5), true),
+            ("strong sell", strong.clone(), event("GAZP", "ema_cross", TradeSignal::Sell, 99.0), true),
+            ("weak", strong, event("GAZP", "ema_cross", TradeSignal::Buy, 100.4), false),
+        ];
+
+        for (name, preferences, event, accepted) in cases {
+            assert_eq!(preferences.accepts(&event), accepted, "{}", name);
+        }
+    }
+}
@@ -1,5 +1,8 @@
-use crate::bot::signal::{CrossoverSignal, Signal, TradeSignal};
+use crate::bot::signal::{CrossoverSignal, Signal};
+use crate::config::StrategyConfig;
 use crate::market_data_service::get_tech_analysis::{GetTechAnalysisRequest, GetTechAnalysisResponse, IndicatorInterval, TypeOfPrice};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use crate::market_data_service::get_last_prices::{GetLastPricesRequest, GetLastPricesResponse, LastPriceType, InstrumentStatus};
 use reqwest::Client;
 use tracing::info;
@@ -14,9 +17,15 @@ pub struct EmaCrossStrategy {
     last_short_ema: f64,
     last_long_ema: f64,
     last_price: f64,
+    warmed_up: bool,
+    last_candle: Option<DateTime<Utc>>,
+    ema_history: Vec<(DateTime<Utc>, f64, f64)>,
 }
 
 impl EmaCrossStrategy {
+    /// Strategy name used in subscriber filters
+    pub const NAME: &'static str = "ema_cross";
+
     pub fn new(
         instrument_uid: String,
         instrument_ticker: String,
@@ -36,9 +45,52 @@ impl EmaCrossStrategy {
             last_short_ema: 0.0,
             last_long_ema: 0.0,
             last_price: 0.0,
+            warmed_up: false,
+            last_candle: None,
+            ema_history: Vec::new(),
         }
     }
 
+    /// EMA values and history stay valid while the lengths and interval are the same
+    pub fn is_compatible(&self, config: &StrategyConfig) -> bool {
+        self.short_ema_length == config.short_ema_length
+            && self.long_ema_length == config.long_ema_length
+            && self.interval == config.interval
+    }
+
+    /// Applies new hysteresis parameters keeping the EMA and crossover state
+    pub fn set_hysteresis(&mut self, hysteresis_percentage: f64, hysteresis_periods: u32) {
+        self.signal_generator.set_hysteresis(hysteresis_percentage, hysteresis_periods);
+    }
+
+    /// Strategy of `instrument_uid` for `config`. An existing strategy keeps its EMA and crossover state
+    /// and takes the new hysteresis, it is replaced if the EMA lengths or interval changed
+    pub fn configure<'a>(
+        strategies: &'a mut HashMap<String, EmaCrossStrategy>,
+        instrument_uid: &str,
+        instrument_ticker: &str,
+        config: &StrategyConfig,
+    ) -> &'a mut EmaCrossStrategy {
+        // EMA state is only valid for the lengths and interval it was built with
+        if strategies.get(instrument_uid).is_some_and(|s| !s.is_compatible(config)) {
+            info!("EMA lengths or interval of {} changed, resetting its strategy", instrument_ticker);
+            strategies.remove(instrument_uid);
+        }
+        let strategy = strategies.entry(instrument_uid.to_string()).or_insert_with(|| {
+            EmaCrossStrategy::new(
+                instrument_uid.to_string(),
+                instrument_ticker.to_string(),
+                config.short_ema_length,
+                config.long_ema_length,
+                config.interval,
+                config.hysteresis_percentage,
+                config.hysteresis_periods,
+            )
+        });
+        strategy.set_hysteresis(config.hysteresis_percentage, config.hysteresis_periods);
+        strategy
+    }
+
     /// Gets short EMA values
     pub async fn get_short_ema(
         &self,
@@ -47,7 +99,7 @@ impl EmaCrossStrategy {
     ) -> Result<GetTechAnalysisResponse, Box<dyn std::error::Error>> {
         let request = GetTechAnalysisRequest::new_ema_auto_period(
             &self.instrument_uid,
-            self.interval.clone(),
+            self.interval,
             TypeOfPrice::Close,
             self.short_ema_length,
         );
@@ -63,7 +115,7 @@ impl EmaCrossStrategy {
     ) -> Result<GetTechAnalysisResponse, Box<dyn std::error::Error>> {
         let request = GetTechAnalysisRequest::new_ema_auto_period(
             &self.instrument_uid,
-            self.interval.clone(),
+            self.interval,
             TypeOfPrice::Close,
             self.long_ema_length,
         );
@@ -71,52 +123,6 @@ impl EmaCrossStrategy {
         GetTechAnalysisResponse::get_tech_analysis(client, token, request).await
     }
 
-    /// Analyzes EMA crossover and returns trading signal
-    pub fn analyze_crossover(
-        &self,
-        short_ema: &GetTechAnalysisResponse,
-        long_ema: &GetTechAnalysisResponse,
-    ) -> TradeSignal {
-        // Get last two points for each EMA
-        let short_values: Vec<_> = short_ema.technical_indicators
-            .iter()
-            .filter_map(|i| i.middle_band.as_ref())
-            .rev()
-            .take(2)
-            .collect();
-
-        let long_values: Vec<_> = long_ema.technical_indicators
-            .iter()
-            .filter_map(|i| i.middle_band.as_ref())
-            .rev()
-            .take(2)
-            .collect();
-
-        if short_values.len() < 2 || long_values.len() < 2 {
-            return TradeSignal::Hold;
-        }
-
-        // Convert values to numbers for comparison
-        let short_current = short_values[0].units.parse::<f64>().unwrap_or(0.0) 
-            + (short_values[0].nano as f64 / 1_000_000_000.0);
-        let short_previous = short_values[1].units.parse::<f64>().unwrap_or(0.0)
-            + (short_values[1].nano as f64 / 1_000_000_000.0);
-        
-        let long_current = long_values[0].units.parse::<f64>().unwrap_or(0.0)
-            + (long_values[0].nano as f64 / 1_000_000_000.0);
-        let long_previous = long_values[1].units.parse::<f64>().unwrap_or(0.0)
-            + (long_values[1].nano as f64 / 1_000_000_000.0);
-
-        // Check for crossover
-        if short_current > long_current && short_previous <= long_previous {
-            TradeSignal::Buy
-        } else if short_current < long_current && short_previous >= long_previous {
-            TradeSignal::Sell
-        } else {
-            TradeSignal::Hold
-        }
-    }
-
     pub fn get_last_short(&self) -> f64 {
         self.last_short_ema
     }
@@ -129,6 +135,27 @@ impl EmaCrossStrategy {
         self.last_price
     }
 
+    /// Closed candle EMA values from the last evaluation as (open time, short, long)
+    pub fn ema_history(&self) -> &[(DateTime<Utc>, f64, f64)] {
+        &self.ema_history
+    }
+
+    pub fn get_short_length(&self) -> i32 {
+        self.short_ema_length
+    }
+
+    pub fn get_long_length(&self) -> i32 {
+        self.long_ema_length
+    }
+
+    pub fn get_interval(&self) -> IndicatorInterval {
+        self.interval
+    }
+
+    pub fn get_last_signal(&self) -> Option<Signal> {
+        self.signal_generator.last_signal()
+    }
+
     /// Gets and analyzes trading signal
     pub async fn get_trade_signal(
         &mut self,
@@ -149,8 +176,8 @@ impl EmaCrossStrategy {
                 + (last_price.price.nano as f64 / 1_000_000_000.0);
         }
 
-        let short_ema = self.get_ema_values(client, token, self.short_ema_length).await?;
-        let long_ema = self.get_ema_values(client, token, self.long_ema_length).await?;
+        let short_ema = self.get_short_ema(client, token).await?;
+        let long_ema = self.get_long_ema(client, token).await?;
 
         if short_ema.technical_indicators.is_empty() || long_ema.technical_indicators.is_empty() {
             info!("No data found for indicators");
@@ -168,77 +195,209 @@ impl EmaCrossStrategy {
             );
         }
 
-        self.last_short_ema = match short_ema.technical_indicators.last() {
-            Some(indicator) => {
-                match &indicator.signal {
-                    Some(value) => {
-                        let units = value.units.parse::<f64>().unwrap_or_else(|e| {
-                            info!("Error parsing units for short EMA: {}", e);
-                            0.0
-                        });
-                        let nanos = value.nano as f64 / 1_000_000_000.0;
-                        units + nanos
-                    }
-                    None => {
-                        info!("Missing signal value for short EMA");
-                        0.0
-                    }
-                }
-            }
-            None => {
-                info!("Failed to get last indicator for short EMA");
-                0.0
-            }
-        };
-
-        self.last_long_ema = match long_ema.technical_indicators.last() {
-            Some(indicator) => {
-                match &indicator.signal {
-                    Some(value) => {
-                        let units = value.units.parse::<f64>().unwrap_or_else(|e| {
-                            info!("Error parsing units for long EMA: {}", e);
-                            0.0
-                        });
-                        let nanos = value.nano as f64 / 1_000_000_000.0;
-                        units + nanos
-                    }
-                    None => {
-                        info!("Missing signal value for long EMA");
-                        0.0
-                    }
-                }
-            }
-            None => {
-                info!("Failed to get last indicator for long EMA");
-                0.0
+        // Only candles that have already closed take part in the evaluation
+        let now = Utc::now();
+        let closed: Vec<(DateTime<Utc>, f64, f64)> = Self::ema_series(&short_ema, &long_ema)
+            .into_iter()
+            .filter(|(open, _, _)| self.interval.candle_close(*open).is_some_and(|close| close <= now))
+            .collect();
+
+        self.ema_history = closed.clone();
+
+        // Replay history once so the crossover state doesn't start from scratch
+        if !self.warmed_up {
+            let Some((_, history)) = closed.split_last() else {
+                info!("No closed candles found for {}", self.instrument_ticker);
+                return Ok(Signal::Hold);
+            };
+            let pairs: Vec<(f64, f64)> = history.iter().map(|&(_, short, long)| (short, long)).collect();
+            self.signal_generator.warm_up(&pairs);
+            // The most recent closed candle is evaluated below like any new one
+            self.last_candle = history.last().map(|&(open, _, _)| open);
+            self.warmed_up = true;
+            info!(
+                "Warmed up {} with {} historical EMA points, last signal: {:?}",
+                self.instrument_ticker,
+                pairs.len(),
+                self.signal_generator.last_signal()
+            );
+        }
+
+        let new_candles: Vec<_> = closed
+            .iter()
+            .filter(|(open, _, _)| self.last_candle.is_none_or(|last| *open > last))
+            .collect();
+
+        if new_candles.is_empty() {
+            info!("No new closed candle for {} since {:?}", self.instrument_ticker, self.last_candle);
+            return Ok(Signal::Hold);
+        }
+
+        let mut signal = Signal::Hold;
+        for &&(open, short, long) in &new_candles {
+            let candle_signal = self.signal_generator.update(short, long);
+            if candle_signal != Signal::Hold {
+                signal = candle_signal;
             }
-        };
+            self.last_candle = Some(open);
+            self.last_short_ema = short;
+            self.last_long_ema = long;
+        }
 
         info!(
-            "Last EMA values - short: {:.6}, long: {:.6}",
-            self.last_short_ema, self.last_long_ema
+            "Evaluated {} new closed candle(s) for {}. Last EMA values - short: {:.6}, long: {:.6}",
+            new_candles.len(), self.instrument_ticker, self.last_short_ema, self.last_long_ema
         );
 
-        Ok(self.signal_generator.update(self.last_short_ema, self.last_long_ema))
+        Ok(signal)
     }
 
-    async fn get_ema_values(
-        &self,
-        client: &Client,
-        token: &str,
-        length: i32,
-    ) -> Result<GetTechAnalysisResponse, Box<dyn std::error::Error>> {
-        let request = GetTechAnalysisRequest::new_ema_auto_period(
-            &self.instrument_uid,
-            self.interval,
-            TypeOfPrice::Close,
-            length,
-        );
+    /// Returns true if a new candle may have closed since the last evaluation
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.last_candle
+            .and_then(|open| self.interval.candle_close(open))
+            .and_then(|close| self.interval.candle_close(close))
+            .is_none_or(|next_close| now >= next_close)
+    }
 
-        GetTechAnalysisResponse::get_tech_analysis(client, token, request).await
+    /// Pairs short and long EMA values by candle open time in chronological order
+    fn ema_series(
+        short_ema: &GetTechAnalysisResponse,
+        long_ema: &GetTechAnalysisResponse,
+    ) -> Vec<(DateTime<Utc>, f64, f64)> {
+        let long_values: HashMap<&str, f64> = long_ema.technical_indicators
+            .iter()
+            .filter_map(|i| i.signal.as_ref().map(|value| (i.timestamp.as_str(), value.to_f64())))
+            .collect();
+
+        short_ema.technical_indicators
+            .iter()
+            .filter_map(|i| {
+                let open = DateTime::parse_from_rfc3339(&i.timestamp).ok()?.with_timezone(&Utc);
+                let short = i.signal.as_ref()?.to_f64();
+                let long = *long_values.get(i.timestamp.as_str())?;
+                Some((open, short, long))
+            })
+            .collect()
     }
 
     pub fn get_ticker(&self) -> &str {
         &self.instrument_ticker
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, second).unwrap()
+    }
+
+    fn strategy(interval: IndicatorInterval, last_candle: Option<DateTime<Utc>>) -> EmaCrossStrategy {
+        let mut strategy = EmaCrossStrategy::new("uid".to_string(), "SBER".to_string(), 8, 21, interval, 0.1, 1);
+        strategy.last_candle = last_candle;
+        strategy
+    }
+
+    #[test]
+    fn candle_close_adds_interval_length() {
+        let open = at(2024, 3, 4, 10, 0, 0);
+        assert_eq!(IndicatorInterval::OneMinute.candle_close(open), Some(at(2024, 3, 4, 10, 1, 0)));
+        assert_eq!(IndicatorInterval::Hour.candle_close(open), Some(at(2024, 3, 4, 11, 0, 0)));
+        assert_eq!(IndicatorInterval::Day.candle_close(open), Some(at(2024, 3, 5, 10, 0, 0)));
+        assert_eq!(IndicatorInterval::Unspecified.candle_close(open), None);
+    }
+
+    #[test]
+    fn candle_close_of_month_uses_calendar_months() {
+        let open = at(2024, 1, 31, 0, 0, 0);
+        assert_eq!(IndicatorInterval::Month.candle_close(open), Some(at(2024, 2, 29, 0, 0, 0)));
+    }
+
+    fn config(short_ema_length: i32, long_ema_length: i32, interval: IndicatorInterval, hysteresis_percentage: f64) -> StrategyConfig {
+        StrategyConfig {
+            short_ema_length,
+            long_ema_length,
+            interval,
+            hysteresis_percentage,
+            hysteresis_periods: 1,
+        }
+    }
+
+    /// Strategy of `config` that has evaluated a candle with the short EMA just above the long one
+    fn evaluated(config: &StrategyConfig) -> HashMap<String, EmaCrossStrategy> {
+        let mut strategies = HashMap::new();
+        let strategy = EmaCrossStrategy::configure(&mut strategies, "uid", "SBER", config);
+        strategy.signal_generator.update(100.0, 101.0);
+        strategy.last_candle = Some(at(2024, 3, 4, 10, 0, 0));
+        strategy.warmed_up = true;
+        strategies
+    }
+
+    #[test]
+    fn same_lengths_and_interval_keep_the_state_and_take_the_new_hysteresis() {
+        let mut strategies = evaluated(&config(8, 21, IndicatorInterval::Hour, 5.0));
+        let strategy = EmaCrossStrategy::configure(&mut strategies, "uid", "SBER", &config(8, 21, IndicatorInterval::Hour, 0.0));
+
+        assert!(strategy.warmed_up);
+        assert_eq!(strategy.last_candle, Some(at(2024, 3, 4, 10, 0, 0)));
+        // A crossing by 0.2% is a signal without hysteresis, it was not one with 5%
+        assert_eq!(strategy.signal_generator.update(101.2, 101.0), Signal::Buy);
+        assert_eq!(strategies.len(), 1);
+    }
+
+    #[test]
+    fn changed_lengths_or_interval_reset_the_strategy() {
+        let changes = [
+            config(9, 21, IndicatorInterval::Hour, 5.0),
+            config(8, 34, IndicatorInterval::Hour, 5.0),
+            config(8, 21, IndicatorInterval::Day, 5.0),
+        ];
+        for changed in changes {
+            let mut strategies = evaluated(&config(8, 21, IndicatorInterval::Hour, 5.0));
+            let strategy = EmaCrossStrategy::configure(&mut strategies, "uid", "SBER", &changed);
+
+            assert!(!strategy.warmed_up);
+            assert_eq!(strategy.last_candle, None);
+            assert_eq!(
+                (strategy.get_short_length(), strategy.get_long_length(), strategy.get_interval()),
+                (changed.short_ema_length, changed.long_ema_length, changed.interval)
+            );
+        }
+    }
+
+    #[test]
+    fn new_strategy_is_due() {
+        assert!(strategy(IndicatorInterval::Hour, None).is_due(at(2024, 3, 4, 10, 30, 0)));
+    }
+
+    #[test]
+    fn one_minute_is_due_when_next_candle_closes() {
+        // Candle opened at 10:00 was evaluated, the next one closes at 10:02
+        let strategy = strategy(IndicatorInterval::OneMinute, Some(at(2024, 3, 4, 10, 0, 0)));
+        assert!(!strategy.is_due(at(2024, 3, 4, 10, 1, 59)));
+        assert!(strategy.is_due(at(2024, 3, 4, 10, 2, 0)));
+    }
+
+    #[test]
+    fn one_hour_is_due_when_next_candle_closes() {
+        let strategy = strategy(IndicatorInterval::Hour, Some(at(2024, 3, 4, 10, 0, 0)));
+        assert!(!strategy.is_due(at(2024, 3, 4, 11, 0, 0)));
+        assert!(!strategy.is_due(at(2024, 3, 4, 11, 59, 59)));
+        assert!(strategy.is_due(at(2024, 3, 4, 12, 0, 0)));
+    }
+
+    #[test]
+    fn one_day_is_due_when_next_candle_closes() {
+        let strategy = strategy(IndicatorInterval::Day, Some(at(2024, 3, 4, 0, 0, 0)));
+        assert!(!strategy.is_due(at(2024, 3, 5, 23, 59, 59)));
+        assert!(strategy.is_due(at(2024, 3, 6, 0, 0, 0)));
+    }
+
+    #[test]
+    fn unspecified_interval_is_always_due() {
+        let strategy = strategy(IndicatorInterval::Unspecified, Some(at(2024, 3, 4, 10, 0, 0)));
+        assert!(strategy.is_due(at(2024, 3, 4, 10, 0, 0)));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::chart::font;
+use std::error::Error;
+
+pub type Rgb = [u8; 3];
+
+/// RGB raster image with simple drawing primitives, coordinates outside the image are clipped
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: Rgb) -> Self {
+        Self {
+            width,
+            height,
+            pixels: background.repeat((width * height) as usize),
+        }
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width as i64
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height as i64
+    }
+
+    pub fn pixel(&mut self, x: i64, y: i64, color: Rgb) {
+        if x < 0 || y < 0 || x >= self.width() || y >= self.height() {
+            return;
+        }
+        let offset = ((y * self.width() + x) * 3) as usize;
+        self.pixels[offset..offset + 3].copy_from_slice(&color);
+    }
+
+    /// Fills the rectangle including both corners
+    pub fn fill_rect(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: Rgb) {
+        for y in y0.min(y1)..=y0.max(y1) {
+            for x in x0.min(x1)..=x0.max(x1) {
+                self.pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Bresenham line, thickness grows the line to the right and down
+    pub fn line(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, thickness: i64, color: Rgb) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+
+        loop {
+            self.fill_rect(x, y, x + thickness - 1, y + thickness - 1, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    pub fn dashed_hline(&mut self, x0: i64, x1: i64, y: i64, dash: i64, color: Rgb) {
+        for x in x0..=x1 {
+            if (x - x0) / dash % 2 == 0 {
+                self.pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Filled triangle pointing up or down with the tip at (x, y)
+    pub fn triangle(&mut self, x: i64, y: i64, size: i64, up: bool, color: Rgb) {
+        for row in 0..size {
+            let y = if up { y + row } else { y - row };
+            self.fill_rect(x - row, y, x + row, y, color);
+        }
+    }
+
+    /// Draws text with the bitmap font, (x, y) is the top left corner
+    pub fn text(&mut self, x: i64, y: i64, text: &str, scale: i64, color: Rgb) {
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i as i64 * (font::WIDTH + 1) * scale;
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..font::WIDTH {
+                    if bits & (1 << (font::WIDTH - 1 - column)) != 0 {
+                        let (px, py) = (left + column * scale, y + row as i64 * scale);
+                        self.fill_rect(px, py, px + scale - 1, py + scale - 1, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Encodes the image as PNG
+    pub fn to_png(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut png = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&self.pixels)?;
+        }
+        Ok(png)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod canvas;
+pub mod font;
+pub mod signal_chart;
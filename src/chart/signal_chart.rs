@@ -0,0 +1,306 @@
+use crate::backtest::candles::Candle;
+use crate::bot::signal::TradeSignal;
+use crate::chart::canvas::{Canvas, Rgb};
+use crate::chart::font;
+use crate::market_data_service::get_candles::{CandleInterval, GetCandlesResponse};
+use crate::market_data_service::get_tech_analysis::IndicatorInterval;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::error::Error;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 450;
+
+const BACKGROUND: Rgb = [255, 255, 255];
+const GRID: Rgb = [230, 230, 230];
+const TEXT: Rgb = [40, 40, 40];
+const UP: Rgb = [38, 166, 91];
+const DOWN: Rgb = [214, 69, 65];
+const SHORT_EMA: Rgb = [255, 140, 0];
+const LONG_EMA: Rgb = [30, 100, 220];
+const LAST_PRICE: Rgb = [90, 90, 110];
+
+/// Candles with both EMAs and the signal on the last candle
+pub struct SignalChart<'a> {
+    pub title: String,
+    pub candles: &'a [Candle],
+    /// Short EMA for each candle, None where unknown
+    pub short_ema: Vec<Option<f64>>,
+    /// Long EMA for each candle, None where unknown
+    pub long_ema: Vec<Option<f64>>,
+    pub short_length: i32,
+    pub long_length: i32,
+    pub signal: TradeSignal,
+    pub last_price: f64,
+}
+
+impl<'a> SignalChart<'a> {
+    /// Matches EMA points to candles by candle open time
+    pub fn new(
+        title: String,
+        candles: &'a [Candle],
+        ema_history: &[(DateTime<Utc>, f64, f64)],
+        short_length: i32,
+        long_length: i32,
+        signal: TradeSignal,
+        last_price: f64,
+    ) -> Self {
+        let ema: HashMap<DateTime<Utc>, (f64, f64)> = ema_history
+            .iter()
+            .map(|&(time, short, long)| (time, (short, long)))
+            .collect();
+
+        Self {
+            title,
+            candles,
+            short_ema: candles.iter().map(|c| ema.get(&c.time).map(|e| e.0)).collect(),
+            long_ema: candles.iter().map(|c| ema.get(&c.time).map(|e| e.1)).collect(),
+            short_length,
+            long_length,
+            signal,
+            last_price,
+        }
+    }
+
+    /// Renders the chart to PNG, the output depends only on the data
+    pub fn render(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        if self.candles.is_empty() {
+            return Err("No candles to draw".into());
+        }
+
+        let mut canvas = Canvas::new(WIDTH, HEIGHT, BACKGROUND);
+        let (left, right, top, bottom) = (10, canvas.width() - 80, 40, canvas.height() - 30);
+
+        // Price range covers candles, EMAs and the last price
+        let prices = self.candles
+            .iter()
+            .flat_map(|c| [c.low, c.high])
+            .chain(self.short_ema.iter().chain(&self.long_ema).flatten().copied())
+            .chain((self.last_price > 0.0).then_some(self.last_price));
+        let (mut min, mut max) = prices.fold((f64::MAX, f64::MIN), |(min, max), p| (min.min(p), max.max(p)));
+        if max <= min {
+            (min, max) = (min * 0.99, max * 1.01 + f64::EPSILON);
+        }
+        let padding = (max - min) * 0.05;
+        (min, max) = (min - padding, max + padding);
+
+        let y = |price: f64| top + ((max - price) / (max - min) * (bottom - top) as f64).round() as i64;
+        let slot = (right - left) as f64 / self.candles.len() as f64;
+        let x = |i: usize| left + (slot * (i as f64 + 0.5)).round() as i64;
+
+        // Grid with price labels
+        for i in 0..=4 {
+            let price = min + (max - min) * i as f64 / 4.0;
+            canvas.line(left, y(price), right, y(price), 1, GRID);
+            canvas.text(right + 6, y(price) - font::HEIGHT / 2, &format_price(price), 1, TEXT);
+        }
+
+        // Candles
+        let body = ((slot * 0.6) as i64).max(1);
+        for (i, candle) in self.candles.iter().enumerate() {
+            let color = if candle.close >= candle.open { UP } else { DOWN };
+            canvas.line(x(i), y(candle.high), x(i), y(candle.low), 1, color);
+            canvas.fill_rect(x(i) - body / 2, y(candle.open), x(i) - body / 2 + body - 1, y(candle.close), color);
+        }
+
+        // EMA lines
+        for (values, color) in [(&self.long_ema, LONG_EMA), (&self.short_ema, SHORT_EMA)] {
+            for i in 1..values.len() {
+                if let (Some(previous), Some(current)) = (values[i - 1], values[i]) {
+                    canvas.line(x(i - 1), y(previous), x(i), y(current), 2, color);
+                }
+            }
+        }
+
+        // Crossover marker on the signal candle
+        let last = self.candles.len() - 1;
+        match self.signal {
+            TradeSignal::Buy => canvas.triangle(x(last), y(self.candles[last].low) + 6, 8, true, UP),
+            TradeSignal::Sell => canvas.triangle(x(last), y(self.candles[last].high) - 6, 8, false, DOWN),
+            TradeSignal::Hold => {}
+        }
+
+        // Last price line with a label on the price axis
+        if self.last_price > 0.0 {
+            let price_y = y(self.last_price);
+            canvas.dashed_hline(left, right, price_y, 4, LAST_PRICE);
+            let label = format_price(self.last_price);
+            canvas.fill_rect(right + 2, price_y - 7, right + 8 + font::text_width(&label, 1), price_y + 7, LAST_PRICE);
+            canvas.text(right + 5, price_y - font::HEIGHT / 2, &label, 1, BACKGROUND);
+        }
+
+        // Title, legend and period
+        let (title_color, signal_text) = match self.signal {
+            TradeSignal::Buy => (UP, "BUY"),
+            TradeSignal::Sell => (DOWN, "SELL"),
+            TradeSignal::Hold => (TEXT, "HOLD"),
+        };
+        canvas.text(left, 10, &self.title, 2, TEXT);
+        canvas.text(left + font::text_width(&self.title, 2) + 16, 10, signal_text, 2, title_color);
+
+        let long_label = format!("EMA {}", self.long_length);
+        let short_label = format!("EMA {}", self.short_length);
+        let long_x = canvas.width() - 10 - font::text_width(&long_label, 2);
+        let short_x = long_x - 24 - font::text_width(&short_label, 2);
+        canvas.text(short_x, 10, &short_label, 2, SHORT_EMA);
+        canvas.text(long_x, 10, &long_label, 2, LONG_EMA);
+
+        let format_time = |time: DateTime<Utc>| time.format("%Y-%m-%d %H:%M").to_string();
+        let last_time = format_time(self.candles[last].time);
+        canvas.text(left, bottom + 12, &format_time(self.candles[0].time), 1, TEXT);
+        canvas.text(right - font::text_width(&last_time, 1), bottom + 12, &last_time, 1, TEXT);
+
+        canvas.to_png()
+    }
+}
+
+/// Price label with fewer decimals for larger prices
+fn format_price(price: f64) -> String {
+    if price >= 1000.0 {
+        format!("{:.1}", price)
+    } else if price >= 10.0 {
+        format!("{:.2}", price)
+    } else {
+        format!("{:.4}", price)
+    }
+}
+
+/// Loads the last `count` closed candles of the interval
+pub async fn load_candles(
+    client: &reqwest::Client,
+    token: &str,
+    instrument_uid: &str,
+    interval: IndicatorInterval,
+    count: usize,
+) -> Result<Vec<Candle>, Box<dyn Error>> {
+    let now = Utc::now();
+    let span = interval.candle_close(now).ok_or("Unspecified interval")? - now;
+    // Extra history covers nights, weekends and holidays
+    let period = (span * (count as i32 * 3)).max(chrono::Duration::days(4));
+
+    let candles = GetCandlesResponse::get_candles_range(
+        client,
+        token,
+        instrument_uid,
+        now - period,
+        now,
+        CandleInterval::from(interval),
+    )
+    .await?;
+
+    let mut candles: Vec<Candle> = candles
+        .iter()
+        .filter(|c| c.is_complete)
+        .filter_map(Candle::from_historic)
+        .collect();
+    candles.sort_by_key(|c| c.time);
+    if candles.len() > count {
+        candles.drain(..candles.len() - count);
+    }
+    Ok(candles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn candles(count: usize) -> Vec<Candle> {
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 7, 0, 0).unwrap();
+        (0..count)
+            .map(|i| {
+                let open = 100.0 + (i as f64 * 0.7).sin() * 5.0 + i as f64 * 0.2;
+                let close = open + if i % 3 == 0 { -1.5 } else { 1.0 };
+                Candle {
+                    time: start + Duration::hours(i as i64),
+                    open,
+                    high: open.max(close) + 0.8,
+                    low: open.min(close) - 0.8,
+                    close,
+                    volume: 1000,
+                }
+            })
+            .collect()
+    }
+
+    fn ema_history(candles: &[Candle]) -> Vec<(DateTime<Utc>, f64, f64)> {
+        let (mut short, mut long) = (candles[0].close, candles[0].close);
+        candles
+            .iter()
+            .map(|c| {
+                short += (c.close - short) * 2.0 / 10.0;
+                long += (c.close - long) * 2.0 / 22.0;
+                (c.time, short, long)
+            })
+            .collect()
+    }
+
+    /// Compares with the golden file under tests/fixtures, UPDATE_GOLDEN=1 rewrites it
+    fn assert_golden(name: &str, png: &[u8]) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, png).unwrap();
+        }
+        let golden = fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert!(golden == png, "{} differs from the rendered chart", path.display());
+    }
+
+    #[test]
+    fn renders_buy_chart() {
+        let candles = candles(40);
+        let history = ema_history(&candles);
+        let chart = SignalChart::new("SBER".into(), &candles, &history, 9, 21, TradeSignal::Buy, 107.25);
+        let png = chart.render().unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert_golden("signal_chart_buy.png", &png);
+    }
+
+    #[test]
+    fn renders_sell_chart() {
+        let candles = candles(40);
+        let history = ema_history(&candles);
+        let chart = SignalChart::new("GAZP".into(), &candles, &history, 9, 21, TradeSignal::Sell, 0.0);
+        assert_golden("signal_chart_sell.png", &chart.render().unwrap());
+    }
+
+    #[test]
+    fn render_is_deterministic() {
+        let candles = candles(20);
+        let history = ema_history(&candles);
+        let chart = SignalChart::new("SBER".into(), &candles, &history, 9, 21, TradeSignal::Buy, 107.25);
+        assert_eq!(chart.render().unwrap(), chart.render().unwrap());
+    }
+
+    #[test]
+    fn matches_ema_by_candle_time() {
+        let candles = candles(5);
+        let history = ema_history(&candles[2..]);
+        let chart = SignalChart::new("SBER".into(), &candles, &history, 9, 21, TradeSignal::Hold, 0.0);
+        assert_eq!(chart.short_ema.iter().filter(|e| e.is_none()).count(), 2);
+        assert_eq!(chart.long_ema[2], Some(history[0].2));
+    }
+
+    #[test]
+    fn renders_single_candle() {
+        let candles = candles(1);
+        let chart = SignalChart::new("SBER".into(), &candles, &[], 9, 21, TradeSignal::Sell, 0.0);
+        assert_golden("signal_chart_single.png", &chart.render().unwrap());
+    }
+
+    #[test]
+    fn renders_flat_candle() {
+        let mut candles = candles(1);
+        candles[0] = Candle { open: 50.0, high: 50.0, low: 50.0, close: 50.0, ..candles[0].clone() };
+        let chart = SignalChart::new("SBER".into(), &candles, &[], 9, 21, TradeSignal::Hold, 0.0);
+        assert!(chart.render().unwrap().starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn empty_candles_are_an_error() {
+        let chart = SignalChart::new("SBER".into(), &[], &[], 9, 21, TradeSignal::Buy, 100.0);
+        assert!(chart.render().is_err());
+    }
+}
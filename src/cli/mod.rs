@@ -0,0 +1,69 @@
+pub mod tools;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Trading signals for MOEX instruments via the T-Invest API
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path to the config file
+    #[arg(short, long, global = true, default_value = "config.yaml")]
+    pub config: String,
+    /// Log output format
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+    /// Evaluate strategies without sending signals, alerts and reports
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CliCommand {
+    /// Scan the market continuously and run the Telegram bot (default)
+    Run,
+    /// Scan the market once, print the signals and exit
+    ScanOnce {
+        /// Also send the signals to subscribers and notifiers
+        #[arg(long)]
+        deliver: bool,
+    },
+    /// Run the configured strategy over historical candles
+    Backtest,
+    /// Search strategy parameters over the filtered universe
+    Optimize,
+    /// Backtest the filtered universe with shared capital
+    Portfolio,
+    /// List instruments of the filtered universe
+    Instruments {
+        #[command(subcommand)]
+        command: Option<InstrumentsCommand>,
+    },
+    /// Print last prices of tickers within the class codes of `filter`
+    Price {
+        #[arg(required = true)]
+        tickers: Vec<String>,
+    },
+    /// Check the config file and exit
+    ValidateConfig,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum InstrumentsCommand {
+    /// Search all instruments by ticker, name, ISIN or FIGI
+    Search {
+        /// Ticker, name, ISIN or FIGI, several words are searched together
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Maximum number of matches to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
@@ -0,0 +1,105 @@
+use crate::bot::messages::format_price;
+use crate::config::Config;
+use crate::instruments::cache::InstrumentCache;
+use crate::instruments::lookup::InstrumentLookup;
+use crate::instruments::universe::Universe;
+use crate::market_data_service::get_last_prices::{
+    GetLastPricesRequest, GetLastPricesResponse, InstrumentStatus, LastPriceType,
+};
+use std::error::Error;
+
+fn client() -> Result<reqwest::Client, Box<dyn Error>> {
+    Ok(reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?)
+}
+
+/// Loads the config file and reports whether it is valid, listing all problems otherwise
+pub fn validate_config(path: &str) -> Result<(), Box<dyn Error>> {
+    Config::new(path)?;
+    println!("Config {} is valid", path);
+    Ok(())
+}
+
+/// Prints the instruments matching `filter`
+pub async fn instruments(config: &Config) -> Result<(), Box<dyn Error>> {
+    let client = client()?;
+    let cache = InstrumentCache::open(&client, config).await?;
+
+    let mut instruments = Universe::new()
+        .select(&client, &config.t_token, &config.filter, &cache)
+        .await;
+    instruments.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+
+    println!("{:<12} {:<8} {:<38} Name", "Ticker", "Class", "UID");
+    for instrument in &instruments {
+        println!("{:<12} {:<8} {:<38} {}", instrument.ticker, instrument.class_code, instrument.uid, instrument.name);
+    }
+    println!("{} instruments", instruments.len());
+    Ok(())
+}
+
+/// Searches all instruments by ticker, name, ISIN or FIGI
+pub async fn search(config: &Config, query: &str, limit: usize) -> Result<(), Box<dyn Error>> {
+    let client = client()?;
+    let results = InstrumentLookup::new(&client, &config.t_token).search(query, limit).await?;
+
+    println!("{:<12} {:<8} {:<38} {:>6} {:<26} {:<4} Name", "Ticker", "Class", "UID", "Lot", "Status", "API");
+    for instrument in &results.instruments {
+        println!(
+            "{:<12} {:<8} {:<38} {:>6} {:<26} {:<4} {}",
+            instrument.ticker,
+            instrument.class_code,
+            instrument.uid,
+            instrument.lot,
+            instrument.status_text(),
+            if instrument.api_trade_available { "yes" } else { "no" },
+            instrument.name
+        );
+    }
+    if results.total > results.instruments.len() {
+        println!("{} of {} matches, refine the query or raise --limit", results.instruments.len(), results.total);
+    } else {
+        println!("{} matches", results.total);
+    }
+    Ok(())
+}
+
+/// Prints last prices of the tickers within the class codes of `filter`
+pub async fn price(config: &Config, tickers: &[String]) -> Result<(), Box<dyn Error>> {
+    let client = client()?;
+    let cache = InstrumentCache::open(&client, config).await?;
+
+    let class_codes = config.filter.class_codes();
+    let mut found = Vec::new();
+    for ticker in tickers {
+        match cache.by_ticker(ticker, &class_codes) {
+            Some(instrument) => found.push(instrument),
+            None => eprintln!("{} not found in {}", ticker.to_uppercase(), class_codes.join(", ")),
+        }
+    }
+    if found.is_empty() {
+        return Err("No known tickers".into());
+    }
+
+    let request = GetLastPricesRequest::new(
+        found.iter().map(|instrument| instrument.uid.clone()).collect(),
+        LastPriceType::Unspecified,
+        InstrumentStatus::Base,
+    );
+    let prices = GetLastPricesResponse::get_last_prices(&client, &config.t_token, request).await?;
+
+    for instrument in &found {
+        let increment = Some(instrument.min_price_increment).filter(|i| *i > 0.0);
+        match prices.last_prices.iter().find(|p| p.instrument_uid == instrument.uid) {
+            Some(price) => println!(
+                "{:<12} {:>14} {}",
+                instrument.ticker,
+                format_price(price.price.to_f64(), increment),
+                price.time
+            ),
+            None => println!("{:<12} {:>14}", instrument.ticker, "no price"),
+        }
+    }
+    Ok(())
+}
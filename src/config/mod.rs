@@ -0,0 +1,915 @@
+pub mod validate;
+
+use crate::models::enums::InstrumentType;
+use crate::market_data_service::get_tech_analysis::IndicatorInterval;
+use crate::instruments::cache::InstrumentCache;
+use crate::instruments::get_instruments::InstrumentStatus;
+use crate::config::validate::{ConfigError, ConfigIssue};
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+
+/// Prefix of environment variables overriding config keys,
+/// nested keys are separated by a double underscore, e.g. BCP_TELEGRAM__ADMIN_IDS
+const ENV_PREFIX: &str = "BCP_";
+
+/// Keys that may be read from a file given by `<key>_file`, e.g. Docker secrets
+const SECRET_KEYS: &[&[&str]] = &[
+    &["t_token"],
+    &["telegram_token"],
+    &["notifiers", "email", "password"],
+];
+
+/// Keys besides `SECRET_KEYS` whose environment values are always strings, e.g. an all-digit account ID
+const STRING_KEYS: &[&[&str]] = &[
+    &["instruments", "cache_file"],
+    &["filter", "class_code"],
+    &["telegram", "subscribers_file"],
+    &["telegram", "alerts_file"],
+    &["trading", "account_id"],
+    &["notifiers", "email", "smtp_host"],
+    &["notifiers", "email", "username"],
+    &["notifiers", "email", "from"],
+    &["reports", "history_file"],
+    &["backtest", "candles_dir"],
+    &["backtest", "report_dir"],
+    &["optimization", "output_strategy_file"],
+];
+
+/// Token or password that is never printed in full
+#[derive(Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Secret {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"***\"")
+    }
+}
+
+// Configuration
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub t_token: Secret,
+    pub telegram_token: Secret,
+    pub scan_interval_seconds: u64,
+    pub strategy: StrategyConfig,
+    /// Strategy parameters of some instruments differing from `strategy`
+    #[serde(default)]
+    pub strategy_overrides: StrategyOverrides,
+    pub assets: AssetsConfig,
+    #[serde(default)]
+    pub instruments: InstrumentsConfig,
+    pub filter: FilterConfig,
+    #[serde(default)]
+    pub telegram: TelegramConfig,
+    #[serde(default)]
+    pub trading: Option<TradingConfig>,
+    #[serde(default)]
+    pub notifiers: NotifiersConfig,
+    #[serde(default)]
+    pub reports: ReportsConfig,
+    #[serde(default)]
+    pub backtest: Option<BacktestConfig>,
+    #[serde(default)]
+    pub optimization: Option<OptimizationConfig>,
+    #[serde(default)]
+    pub portfolio: Option<PortfolioConfig>,
+}
+
+/// Config shared with the Telegram command handlers, replaced on reload
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AssetsConfig {
+    pub instrument_status: InstrumentStatus,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct InstrumentsConfig {
+    /// File where shares, bonds, ETFs and futures are cached
+    #[serde(default = "default_instruments_cache_file")]
+    pub cache_file: String,
+    /// Age after which the cache is reloaded from the API
+    #[serde(default = "default_instruments_refresh_hours")]
+    pub refresh_hours: u64,
+}
+
+impl Default for InstrumentsConfig {
+    fn default() -> Self {
+        Self {
+            cache_file: default_instruments_cache_file(),
+            refresh_hours: default_instruments_refresh_hours(),
+        }
+    }
+}
+
+fn default_instruments_cache_file() -> String {
+    "data/instruments.json".to_string()
+}
+
+fn default_instruments_refresh_hours() -> u64 {
+    24
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyConfig {
+    pub short_ema_length: i32,
+    pub long_ema_length: i32,
+    pub interval: IndicatorInterval,
+    pub hysteresis_percentage: f64,
+    pub hysteresis_periods: u32,
+}
+
+/// Strategy parameters replacing those of `strategy`, missing keys are left as they are
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyOverride {
+    pub short_ema_length: Option<i32>,
+    pub long_ema_length: Option<i32>,
+    pub interval: Option<IndicatorInterval>,
+    pub hysteresis_percentage: Option<f64>,
+    pub hysteresis_periods: Option<u32>,
+}
+
+impl StrategyOverride {
+    pub fn apply(&self, strategy: &mut StrategyConfig) {
+        if let Some(short_ema_length) = self.short_ema_length {
+            strategy.short_ema_length = short_ema_length;
+        }
+        if let Some(long_ema_length) = self.long_ema_length {
+            strategy.long_ema_length = long_ema_length;
+        }
+        if let Some(interval) = self.interval {
+            strategy.interval = interval;
+        }
+        if let Some(hysteresis_percentage) = self.hysteresis_percentage {
+            strategy.hysteresis_percentage = hysteresis_percentage;
+        }
+        if let Some(hysteresis_periods) = self.hysteresis_periods {
+            strategy.hysteresis_periods = hysteresis_periods;
+        }
+    }
+}
+
+/// Overrides by class code, sector and ticker, applied in this order on top of `strategy`,
+/// so a ticker override wins over a sector one and a sector override over a class code one
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyOverrides {
+    /// By class code, e.g. TQTF
+    #[serde(default)]
+    pub class_codes: BTreeMap<String, StrategyOverride>,
+    /// By sector of the issuer as reported by the API, e.g. energy
+    #[serde(default)]
+    pub sectors: BTreeMap<String, StrategyOverride>,
+    /// By ticker, e.g. SBER
+    #[serde(default)]
+    pub tickers: BTreeMap<String, StrategyOverride>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FilterConfig {
+    /// Class code of the scanned board, e.g. TQBR
+    #[serde(default)]
+    pub class_code: Option<String>,
+    /// Several class codes, scanned together with `class_code`
+    #[serde(default)]
+    pub class_codes: Vec<String>,
+    pub instrument_type: InstrumentType,
+    /// Only these tickers are scanned if not empty
+    #[serde(default)]
+    pub include_tickers: Vec<String>,
+    #[serde(default)]
+    pub exclude_tickers: Vec<String>,
+    /// Trading currencies, e.g. rub
+    #[serde(default)]
+    pub currencies: Vec<String>,
+    /// Countries of risk, e.g. RU
+    #[serde(default)]
+    pub countries_of_risk: Vec<String>,
+    /// Issuer sectors, e.g. energy
+    #[serde(default)]
+    pub sectors: Vec<String>,
+    /// Require the instrument to be (true) or not to be (false) for qualified investors only
+    #[serde(default)]
+    pub for_qual_investor: Option<bool>,
+    /// Require short selling to be available (true) or unavailable (false)
+    #[serde(default)]
+    pub short_enabled: Option<bool>,
+    /// Minimum average daily turnover in instrument currency
+    #[serde(default)]
+    pub min_daily_turnover: Option<f64>,
+    /// Trading days the average turnover is calculated over
+    #[serde(default = "default_turnover_days")]
+    pub turnover_days: u32,
+}
+
+impl FilterConfig {
+    /// `class_code` followed by `class_codes`
+    pub fn class_codes(&self) -> Vec<&str> {
+        self.class_code.iter().chain(&self.class_codes).map(String::as_str).collect()
+    }
+}
+
+fn default_turnover_days() -> u32 {
+    20
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// File where subscribers and their preferences are stored
+    #[serde(default = "default_subscribers_file")]
+    pub subscribers_file: String,
+    /// File where price and indicator alerts are stored
+    #[serde(default = "default_alerts_file")]
+    pub alerts_file: String,
+    /// Chat and user IDs allowed to subscribe without approval
+    #[serde(default)]
+    pub allowed_ids: Vec<i64>,
+    /// User IDs allowed to use control commands and approve subscription requests
+    #[serde(default)]
+    pub admin_ids: Vec<i64>,
+    /// Attach a chart image to signal notifications
+    #[serde(default = "default_true")]
+    pub charts: bool,
+    /// Number of candles on the chart
+    #[serde(default = "default_chart_candles")]
+    pub chart_candles: usize,
+    /// Global limit of outgoing messages, Telegram allows about 30 per second
+    #[serde(default = "default_messages_per_second")]
+    pub messages_per_second: u32,
+    /// Minimum interval between messages to one private chat
+    #[serde(default = "default_chat_interval_ms")]
+    pub chat_interval_ms: u64,
+    /// Minimum interval between messages to one group, Telegram allows about 20 per minute
+    #[serde(default = "default_group_interval_ms")]
+    pub group_interval_ms: u64,
+    /// Attempts to resend a message after RetryAfter or network errors
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            subscribers_file: default_subscribers_file(),
+            alerts_file: default_alerts_file(),
+            allowed_ids: Vec::new(),
+            admin_ids: Vec::new(),
+            charts: true,
+            chart_candles: default_chart_candles(),
+            messages_per_second: default_messages_per_second(),
+            chat_interval_ms: default_chat_interval_ms(),
+            group_interval_ms: default_group_interval_ms(),
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+impl TelegramConfig {
+    /// Access is open to everyone when neither allowlist nor admins are configured
+    pub fn is_restricted(&self) -> bool {
+        !self.allowed_ids.is_empty() || !self.admin_ids.is_empty()
+    }
+
+    pub fn is_admin(&self, id: i64) -> bool {
+        self.admin_ids.contains(&id)
+    }
+
+    pub fn is_allowed(&self, id: i64) -> bool {
+        !self.is_restricted() || self.allowed_ids.contains(&id) || self.is_admin(id)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_chart_candles() -> usize {
+    80
+}
+
+fn default_messages_per_second() -> u32 {
+    25
+}
+
+fn default_chat_interval_ms() -> u64 {
+    1000
+}
+
+fn default_group_interval_ms() -> u64 {
+    3000
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_subscribers_file() -> String {
+    "data/subscribers.json".to_string()
+}
+
+fn default_alerts_file() -> String {
+    "data/alerts.json".to_string()
+}
+
+/// Semi-automatic trading from Telegram signal messages
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TradingConfig {
+    /// Account orders are placed to
+    pub account_id: String,
+    /// Place orders to the sandbox account
+    #[serde(default)]
+    pub sandbox: bool,
+    /// Amount in instrument currency bought or sold by "Execute sized"
+    pub position_amount: f64,
+    /// Trade buttons stop working after this time
+    #[serde(default = "default_confirmation_timeout_minutes")]
+    pub confirmation_timeout_minutes: i64,
+}
+
+fn default_confirmation_timeout_minutes() -> i64 {
+    30
+}
+
+/// Signal destinations besides Telegram
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NotifiersConfig {
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Slack incoming webhook URLs
+    #[serde(default)]
+    pub slack: Vec<String>,
+    /// Discord webhook URLs
+    #[serde(default)]
+    pub discord: Vec<String>,
+    #[serde(default)]
+    pub email: Option<EmailConfig>,
+}
+
+/// Generic webhook receiving each signal as JSON
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Extra HTTP headers, e.g. for authorization
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    None,
+    #[default]
+    StartTls,
+    Tls,
+}
+
+/// SMTP email with all signals of a scan in one message
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// Digest reports delivered through all notifiers, times are MOEX local time (UTC+3)
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ReportsConfig {
+    /// Daily report time on weekdays, e.g. "18:50"
+    #[serde(default)]
+    pub daily: Option<NaiveTime>,
+    #[serde(default)]
+    pub weekly: Option<WeeklyReportConfig>,
+    /// Number of instruments closest to an EMA crossover in a report
+    #[serde(default = "default_closest")]
+    pub closest: usize,
+    /// File keeping signals, errors and paper positions for reports across restarts
+    #[serde(default = "default_history_file")]
+    pub history_file: String,
+}
+
+impl Default for ReportsConfig {
+    fn default() -> Self {
+        Self {
+            daily: None,
+            weekly: None,
+            closest: default_closest(),
+            history_file: default_history_file(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WeeklyReportConfig {
+    /// Day of week, e.g. "fri"
+    pub day: Weekday,
+    pub time: NaiveTime,
+}
+
+fn default_closest() -> usize {
+    5
+}
+
+fn default_history_file() -> String {
+    "data/report_history.json".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BacktestConfig {
+    /// Tickers within the class codes of `filter`
+    pub instruments: Vec<String>,
+    pub from: NaiveDate,
+    /// Defaults to now
+    pub to: Option<NaiveDate>,
+    pub initial_capital: f64,
+    pub commission_percentage: f64,
+    pub slippage_percentage: f64,
+    #[serde(default = "default_candles_dir")]
+    pub candles_dir: String,
+    /// Directory for HTML, CSV and JSON reports, reports are not written if missing
+    pub report_dir: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMethod {
+    Grid,
+    Random,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    Sharpe,
+    Sortino,
+    Cagr,
+    TotalReturn,
+    ProfitFactor,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OptimizationConfig {
+    pub method: SearchMethod,
+    /// Number of parameter sets sampled by random search
+    #[serde(default = "default_random_samples")]
+    pub random_samples: usize,
+    pub seed: Option<u64>,
+    pub objective: Objective,
+    pub short_ema_lengths: Vec<i32>,
+    pub long_ema_lengths: Vec<i32>,
+    pub hysteresis_percentages: Vec<f64>,
+    pub hysteresis_periods: Vec<u32>,
+    pub intervals: Vec<IndicatorInterval>,
+    /// Number of best parameter sets in the report
+    #[serde(default = "default_top")]
+    pub top: usize,
+    pub walk_forward: Option<WalkForwardConfig>,
+    /// File to write the `strategy:` block of the best parameter set to
+    pub output_strategy_file: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WalkForwardConfig {
+    pub windows: usize,
+    /// Share of each window used for optimization, the rest is out-of-sample
+    pub train_ratio: f64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionSizing {
+    /// Equity divided by `max_positions`
+    EqualWeight,
+    /// `position_fraction` of equity
+    FixedFraction,
+    /// `position_amount` in currency
+    FixedAmount,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Rebalance {
+    #[default]
+    None,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PortfolioConfig {
+    pub max_positions: usize,
+    pub sizing: PositionSizing,
+    #[serde(default)]
+    pub position_fraction: f64,
+    #[serde(default)]
+    pub position_amount: f64,
+    /// Periodically resize open positions to the target size
+    #[serde(default)]
+    pub rebalance: Rebalance,
+}
+
+fn default_random_samples() -> usize {
+    100
+}
+
+fn default_top() -> usize {
+    10
+}
+
+fn default_candles_dir() -> String {
+    "candles".to_string()
+}
+
+impl Config {
+    /// Strategy parameters of an instrument with the overrides applied, matching is case-insensitive
+    pub fn strategy_for(&self, ticker: &str, class_code: &str, sector: Option<&str>) -> StrategyConfig {
+        let overrides = &self.strategy_overrides;
+        let layers = [
+            find_override(&overrides.class_codes, class_code),
+            sector.and_then(|sector| find_override(&overrides.sectors, sector)),
+            find_override(&overrides.tickers, ticker),
+        ];
+
+        let mut strategy = self.strategy.clone();
+        for layer in layers.into_iter().flatten() {
+            layer.apply(&mut strategy);
+        }
+        strategy
+    }
+
+    /// Reads the config file, applies `BCP_*` environment overrides and reads secret files.
+    /// Values are validated, all problems are reported at once in a `ConfigError`
+    pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
+        Self::with_vars(path, std::env::vars())
+    }
+
+    /// Same as `new` with the environment given by `vars`.
+    /// Class codes are checked against the instrument cache if its file has any instruments
+    fn with_vars(path: &str, vars: impl Iterator<Item = (String, String)>) -> Result<Self, Box<dyn Error>> {
+        // Reading configuration file
+        let config_data = fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path, e))?;
+        let invalid = |issues: Vec<ConfigIssue>| ConfigError {
+            path: path.to_string(),
+            issues,
+        };
+        let mut document: Value = serde_yaml::from_str(&config_data)
+            .map_err(|e| invalid(vec![ConfigIssue::new("yaml", e.to_string(), "fix the YAML syntax")]))?;
+
+        apply_env_overrides(&mut document, vars);
+        read_secret_files(&mut document)?;
+
+        let config: Config =
+            serde_path_to_error::deserialize(document).map_err(|e| invalid(vec![parse_issue(&e, &config_data)]))?;
+
+        let cache = Some(&config.instruments.cache_file)
+            .filter(|cache_file| Path::new(cache_file).exists())
+            .and_then(|cache_file| InstrumentCache::load(cache_file).ok())
+            .filter(|cache| !cache.is_empty());
+        let class_codes = cache.as_ref().map(InstrumentCache::class_codes);
+        let issues = validate::validate(&config, class_codes.as_ref());
+        if !issues.is_empty() {
+            return Err(invalid(issues).into());
+        }
+        Ok(config)
+    }
+}
+
+fn find_override<'a>(overrides: &'a BTreeMap<String, StrategyOverride>, key: &str) -> Option<&'a StrategyOverride> {
+    overrides
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, strategy)| strategy)
+}
+
+/// Describes a value that can't be deserialized with its key path and,
+/// if the problem is in the file itself rather than in an override, its line
+fn parse_issue(error: &serde_path_to_error::Error<serde_yaml::Error>, config_data: &str) -> ConfigIssue {
+    let key = match error.path().iter().next() {
+        Some(_) => error.path().to_string(),
+        None => "config".to_string(),
+    };
+    let mut problem = error.inner().to_string();
+    let location = serde_yaml::from_str::<Config>(config_data)
+        .err()
+        .filter(|e| e.to_string().contains(&problem))
+        .and_then(|e| e.location());
+    if let Some(location) = location {
+        problem = format!("{} (line {}, column {})", problem, location.line(), location.column());
+    }
+
+    let fix = if problem.starts_with("unknown field") {
+        "fix the key name or remove it"
+    } else if problem.starts_with("unknown variant") {
+        "use one of the expected values"
+    } else if problem.starts_with("missing field") {
+        "add the key, see config.example.yaml"
+    } else {
+        "check the value type, see config.example.yaml"
+    };
+    ConfigIssue::new(key, problem, fix)
+}
+
+/// Replaces config keys with `BCP_*` environment variables, empty variables are ignored.
+/// Values of `SECRET_KEYS`, `STRING_KEYS` and keys that are strings in the file are taken as is,
+/// other values are parsed as YAML, e.g. `[1, 2]`
+fn apply_env_overrides(document: &mut Value, vars: impl Iterator<Item = (String, String)>) {
+    for (name, value) in vars {
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        if value.is_empty() || key.is_empty() {
+            continue;
+        }
+
+        let path: Vec<String> = key.to_lowercase().split("__").map(str::to_string).collect();
+        let Some((last, parents)) = path.split_last() else {
+            continue;
+        };
+        let mut node = &mut *document;
+        for parent in parents {
+            node = mapping(node).entry(Value::from(parent.as_str())).or_insert(Value::Null);
+        }
+
+        let is_string_key = SECRET_KEYS.iter().chain(STRING_KEYS).any(|known| known.iter().eq(path.iter()));
+        let entry = mapping(node).entry(Value::from(last.as_str())).or_insert(Value::Null);
+        *entry = match entry {
+            Value::String(_) => Value::String(value),
+            _ if is_string_key => Value::String(value),
+            _ => serde_yaml::from_str(&value).unwrap_or(Value::String(value)),
+        };
+        info!("Config key {} is set from {}", path.join("."), name);
+    }
+}
+
+/// Replaces `<key>_file` of secret keys with the trimmed file content
+fn read_secret_files(document: &mut Value) -> Result<(), Box<dyn Error>> {
+    'keys: for path in SECRET_KEYS {
+        let Some((last, parents)) = path.split_last() else {
+            continue;
+        };
+        let mut node = &mut *document;
+        for parent in parents {
+            match node.get_mut(*parent) {
+                Some(child) => node = child,
+                None => continue 'keys,
+            }
+        }
+        let Value::Mapping(map) = node else {
+            continue;
+        };
+
+        let file_key = format!("{}_file", last);
+        let Some(file) = map.remove(file_key.as_str()) else {
+            continue;
+        };
+        let file = file.as_str().ok_or_else(|| format!("{} must be a file path", file_key))?;
+        let secret = fs::read_to_string(file).map_err(|e| format!("Error reading {} {}: {}", file_key, file, e))?;
+        map.insert(Value::from(*last), Value::String(secret.trim().to_string()));
+        info!("Config key {} is read from {}", path.join("."), file);
+    }
+    Ok(())
+}
+
+/// Mapping of the node, replacing other values with an empty mapping
+fn mapping(node: &mut Value) -> &mut Mapping {
+    if !node.is_mapping() {
+        *node = Value::Mapping(Mapping::new());
+    }
+    match node {
+        Value::Mapping(map) => map,
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Value {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/config.example.yaml");
+        serde_yaml::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Example config with the variables applied and secret files read
+    fn config(pairs: &[(&str, &str)]) -> Config {
+        let mut document = example();
+        apply_env_overrides(&mut document, vars(pairs));
+        read_secret_files(&mut document).unwrap();
+        serde_yaml::from_value(document).unwrap()
+    }
+
+    fn overridden(yaml: &str) -> Config {
+        let mut document = example();
+        mapping(&mut document).insert(Value::from("strategy_overrides"), serde_yaml::from_str(yaml).unwrap());
+        serde_yaml::from_value(document).unwrap()
+    }
+
+    #[test]
+    fn strategy_without_overrides() {
+        let config = config(&[]);
+        let strategy = config.strategy_for("SBER", "TQBR", Some("financial"));
+        assert_eq!(strategy.short_ema_length, config.strategy.short_ema_length);
+        assert_eq!(strategy.long_ema_length, config.strategy.long_ema_length);
+        assert_eq!(strategy.interval, config.strategy.interval);
+    }
+
+    #[test]
+    fn ticker_wins_over_sector_and_sector_over_class_code() {
+        let config = overridden(
+            "class_codes:\n  TQBR: {short_ema_length: 3, long_ema_length: 30, hysteresis_periods: 4}\n\
+             sectors:\n  energy: {short_ema_length: 5, long_ema_length: 34}\n\
+             tickers:\n  GAZP: {short_ema_length: 7}",
+        );
+
+        let class_code = config.strategy_for("SBER", "TQBR", Some("financial"));
+        assert_eq!((class_code.short_ema_length, class_code.long_ema_length), (3, 30));
+
+        let sector = config.strategy_for("LKOH", "TQBR", Some("energy"));
+        assert_eq!((sector.short_ema_length, sector.long_ema_length), (5, 34));
+
+        // Keys missing in the ticker override come from the sector and class code ones
+        let ticker = config.strategy_for("GAZP", "TQBR", Some("energy"));
+        assert_eq!((ticker.short_ema_length, ticker.long_ema_length, ticker.hysteresis_periods), (7, 34, 4));
+        assert_eq!(ticker.interval, config.strategy.interval);
+
+        // Other class codes only get the ticker override
+        let other = config.strategy_for("GAZP", "SPBXM", None);
+        assert_eq!((other.short_ema_length, other.long_ema_length), (7, config.strategy.long_ema_length));
+    }
+
+    #[test]
+    fn override_names_are_case_insensitive() {
+        let config = overridden(
+            "class_codes:\n  tqtf: {hysteresis_periods: 3}\n\
+             sectors:\n  Energy: {long_ema_length: 34}\n\
+             tickers:\n  sber: {short_ema_length: 5}",
+        );
+        assert_eq!(config.strategy_for("SBER", "TQBR", None).short_ema_length, 5);
+        assert_eq!(config.strategy_for("Sber", "TQBR", None).short_ema_length, 5);
+        assert_eq!(config.strategy_for("LKOH", "TQBR", Some("ENERGY")).long_ema_length, 34);
+        assert_eq!(config.strategy_for("FXGD", "TQTF", None).hysteresis_periods, 3);
+    }
+
+    #[test]
+    fn string_keys_are_kept_verbatim() {
+        let config = config(&[
+            ("BCP_T_TOKEN", "123456"),
+            ("BCP_TRADING__ACCOUNT_ID", "2000123456"),
+            ("BCP_NOTIFIERS__EMAIL__USERNAME", "yes"),
+        ]);
+        assert_eq!(config.t_token.expose(), "123456");
+        assert_eq!(config.trading.unwrap().account_id, "2000123456");
+        assert_eq!(config.notifiers.email.unwrap().username.as_deref(), Some("yes"));
+    }
+
+    #[test]
+    fn string_keys_missing_from_the_file_are_kept_verbatim() {
+        let mut document = example();
+        let root = mapping(&mut document);
+        root.remove("t_token");
+        root.remove("telegram_token");
+        mapping(&mut root["notifiers"]["email"]).remove("password");
+        mapping(&mut root["trading"]).remove("account_id");
+        apply_env_overrides(
+            &mut document,
+            vars(&[
+                ("BCP_T_TOKEN", "123456"),
+                ("BCP_TELEGRAM_TOKEN", "1e5"),
+                ("BCP_NOTIFIERS__EMAIL__PASSWORD", "true"),
+                ("BCP_TRADING__ACCOUNT_ID", "2000123456"),
+            ]),
+        );
+
+        assert_eq!(document["t_token"], Value::from("123456"));
+        assert_eq!(document["telegram_token"], Value::from("1e5"));
+        assert_eq!(document["notifiers"]["email"]["password"], Value::from("true"));
+        let config: Config = serde_yaml::from_value(document).unwrap();
+        assert_eq!(config.t_token.expose(), "123456");
+        assert_eq!(config.telegram_token.expose(), "1e5");
+        assert_eq!(config.trading.unwrap().account_id, "2000123456");
+    }
+
+    #[test]
+    fn other_values_are_parsed_as_yaml() {
+        let config = config(&[
+            ("BCP_TELEGRAM__ADMIN_IDS", "[111, 222]"),
+            ("BCP_SCAN_INTERVAL_SECONDS", "60"),
+            ("BCP_STRATEGY__SHORT_EMA_LENGTH", "9"),
+            ("BCP_TELEGRAM__CHARTS", "false"),
+        ]);
+        assert_eq!(config.telegram.admin_ids, [111, 222]);
+        assert_eq!(config.scan_interval_seconds, 60);
+        assert_eq!(config.strategy.short_ema_length, 9);
+        assert!(!config.telegram.charts);
+    }
+
+    #[test]
+    fn missing_keys_are_created() {
+        let config = config(&[("BCP_STRATEGY_OVERRIDES__TICKERS__SBER__SHORT_EMA_LENGTH", "5")]);
+        assert_eq!(config.strategy_for("SBER", "TQBR", None).short_ema_length, 5);
+    }
+
+    #[test]
+    fn empty_and_foreign_variables_are_ignored() {
+        let mut document = example();
+        apply_env_overrides(
+            &mut document,
+            vars(&[("BCP_T_TOKEN", ""), ("BCP_", "value"), ("T_TOKEN", "other"), ("HOME", "/root")]),
+        );
+        assert_eq!(document, example());
+    }
+
+    #[test]
+    fn secret_files_override_values() {
+        let path = std::env::temp_dir().join(format!("bcp_secret_{}", std::process::id()));
+        fs::write(&path, "  token-from-file\n").unwrap();
+
+        // The file wins over the value from the config and from BCP_T_TOKEN
+        let config = config(&[("BCP_T_TOKEN", "token-from-env"), ("BCP_T_TOKEN_FILE", path.to_str().unwrap())]);
+        assert_eq!(config.t_token.expose(), "token-from-file");
+
+        let mut document = example();
+        mapping(&mut document).insert(Value::from("telegram_token_file"), Value::from("/nonexistent/bcp_secret"));
+        let error = read_secret_files(&mut document).unwrap_err().to_string();
+        assert!(error.contains("telegram_token_file"), "{}", error);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn debug_output_hides_secrets() {
+        let path = std::env::temp_dir().join(format!("bcp_password_{}", std::process::id()));
+        fs::write(&path, "smtp-secret-password").unwrap();
+
+        let config = config(&[
+            ("BCP_T_TOKEN", "t.secret-api-token"),
+            ("BCP_TELEGRAM_TOKEN", "123:secret-bot-token"),
+            ("BCP_NOTIFIERS__EMAIL__PASSWORD_FILE", path.to_str().unwrap()),
+        ]);
+        let debug = format!("{:?}", config);
+        for secret in ["t.secret-api-token", "123:secret-bot-token", "smtp-secret-password", "your-webhook-token"] {
+            assert!(!debug.contains(secret), "{} is printed", secret);
+        }
+        assert!(debug.contains("\"***\""));
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,542 @@
+use crate::config::{Config, PositionSizing, SearchMethod, StrategyConfig};
+use crate::instruments::get_instruments::{InstrumentKind, InstrumentStatus};
+use crate::market_data_service::get_tech_analysis::IndicatorInterval;
+use crate::models::enums::InstrumentType;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Class codes of MOEX boards available through the T-Invest API, used when there is no instrument cache yet
+pub const KNOWN_CLASS_CODES: &[&str] = &[
+    "TQBR", "TQTF", "TQTD", "TQTE", "TQPI", "TQIF", "TQFD", "TQFE", "TQCB", "TQOB", "TQIR", "TQRD", "TQOD", "TQOE",
+    "TQOY", "TQBD", "TQDE", "SMAL", "SPBFUT", "SPBOPT", "CETS", "SPBRU", "SPBXM", "SPEQ", "FQBR",
+];
+
+/// Problem with one config key and the way to fix it
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    /// Key path, e.g. `strategy.short_ema_length`
+    pub key: String,
+    pub problem: String,
+    pub fix: String,
+}
+
+impl ConfigIssue {
+    pub fn new(key: impl Into<String>, problem: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            problem: problem.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}. Fix: {}", self.key, self.problem, self.fix)
+    }
+}
+
+/// All problems of a config file
+pub struct ConfigError {
+    pub path: String,
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Config {} is invalid, {} problem(s):", self.path, self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Same as Display, so the error returned from main is printed one problem per line
+impl fmt::Debug for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// False for NaN
+fn is_positive(value: f64) -> bool {
+    value > 0.0
+}
+
+/// Share above 0 and up to 1
+fn is_fraction(value: f64) -> bool {
+    value > 0.0 && value <= 1.0
+}
+
+/// Checks values that parse but can't work, e.g. a short EMA longer than the long one.
+/// Class codes are checked against `class_codes` of the instrument cache or `KNOWN_CLASS_CODES` without one
+pub fn validate(config: &Config, class_codes: Option<&BTreeSet<&str>>) -> Vec<ConfigIssue> {
+    let known_class_codes: Vec<&str> = match class_codes {
+        Some(class_codes) => class_codes.iter().copied().collect(),
+        None => KNOWN_CLASS_CODES.to_vec(),
+    };
+    let mut issues = Vec::new();
+    let mut issue = |key: &str, problem: String, fix: &str| issues.push(ConfigIssue::new(key, problem, fix));
+
+    if config.t_token.trim().is_empty() {
+        issue("t_token", "is empty".to_string(), "set t_token, t_token_file or BCP_T_TOKEN");
+    }
+    if config.telegram_token.trim().is_empty() {
+        issue(
+            "telegram_token",
+            "is empty".to_string(),
+            "set telegram_token, telegram_token_file or BCP_TELEGRAM_TOKEN",
+        );
+    }
+    if config.scan_interval_seconds == 0 {
+        issue(
+            "scan_interval_seconds",
+            "is 0".to_string(),
+            "set the interval between scans in seconds, e.g. 300",
+        );
+    }
+
+    strategy_issues("strategy", &config.strategy, &mut issue);
+    let overrides = [
+        ("class_codes", &config.strategy_overrides.class_codes),
+        ("sectors", &config.strategy_overrides.sectors),
+        ("tickers", &config.strategy_overrides.tickers),
+    ];
+    for (group, overrides) in overrides {
+        for (name, strategy_override) in overrides {
+            let mut strategy = config.strategy.clone();
+            strategy_override.apply(&mut strategy);
+            strategy_issues(&format!("strategy_overrides.{}.{}", group, name), &strategy, &mut issue);
+        }
+    }
+
+    let filter = &config.filter;
+    if filter.class_codes().is_empty() {
+        issue(
+            "filter.class_code",
+            "is missing".to_string(),
+            "set the class code of the scanned board, e.g. TQBR, or a list in filter.class_codes",
+        );
+    }
+    let filter_class_codes = filter
+        .class_code
+        .iter()
+        .map(|code| ("filter.class_code".to_string(), code))
+        .chain(filter.class_codes.iter().enumerate().map(|(i, code)| (format!("filter.class_codes[{}]", i), code)));
+    for (key, class_code) in filter_class_codes {
+        if !known_class_codes.contains(&class_code.as_str()) {
+            let fix = if known_class_codes.contains(&class_code.to_uppercase().as_str()) {
+                format!("class codes are upper case, use {}", class_code.to_uppercase())
+            } else {
+                format!("use one of {}", known_class_codes.join(", "))
+            };
+            issue(&key, format!("{:?} is not a known class code", class_code), &fix);
+        }
+    }
+    if let Some(ticker) = filter.include_tickers.iter().find(|t| filter.exclude_tickers.iter().any(|e| e.eq_ignore_ascii_case(t))) {
+        issue(
+            "filter.exclude_tickers",
+            format!("{} is also in filter.include_tickers", ticker),
+            "remove the ticker from one of the lists",
+        );
+    }
+    if let Some(turnover) = filter.min_daily_turnover.filter(|turnover| turnover.is_nan() || *turnover < 0.0) {
+        issue(
+            "filter.min_daily_turnover",
+            format!("{} is negative", turnover),
+            "set the minimum average daily turnover in instrument currency, e.g. 10000000",
+        );
+    }
+    if filter.min_daily_turnover.is_some() && filter.turnover_days == 0 {
+        issue(
+            "filter.turnover_days",
+            "is 0".to_string(),
+            "remove the key to average over the default of 20 days",
+        );
+    }
+    if matches!(config.filter.instrument_type, InstrumentType::Unspecified) {
+        issue(
+            "filter.instrument_type",
+            "is INSTRUMENT_TYPE_UNSPECIFIED".to_string(),
+            "set the scanned type, e.g. INSTRUMENT_TYPE_SHARE",
+        );
+    } else if !InstrumentKind::ALL
+        .iter()
+        .any(|kind| kind.instrument_type() == config.filter.instrument_type.as_str())
+    {
+        issue(
+            "filter.instrument_type",
+            format!("{} is not in the instrument cache", config.filter.instrument_type),
+            "use INSTRUMENT_TYPE_SHARE, INSTRUMENT_TYPE_BOND, INSTRUMENT_TYPE_ETF or INSTRUMENT_TYPE_FUTURES",
+        );
+    }
+    if matches!(config.assets.instrument_status, InstrumentStatus::Unspecified) {
+        issue(
+            "assets.instrument_status",
+            "is INSTRUMENT_STATUS_UNSPECIFIED".to_string(),
+            "use INSTRUMENT_STATUS_BASE or INSTRUMENT_STATUS_ALL",
+        );
+    }
+    if config.instruments.refresh_hours == 0 {
+        issue(
+            "instruments.refresh_hours",
+            "is 0, the cache would be reloaded on every scan".to_string(),
+            "set a positive number of hours, e.g. 24",
+        );
+    }
+
+    let telegram = &config.telegram;
+    if telegram.messages_per_second == 0 {
+        issue(
+            "telegram.messages_per_second",
+            "is 0, no message would be sent".to_string(),
+            "remove the key to use the default of 25",
+        );
+    }
+    if telegram.charts && telegram.chart_candles < 2 {
+        issue(
+            "telegram.chart_candles",
+            format!("{} is too few for a chart", telegram.chart_candles),
+            "set at least 2 candles or disable telegram.charts",
+        );
+    }
+
+    if let Some(trading) = &config.trading {
+        if trading.account_id.trim().is_empty() {
+            issue(
+                "trading.account_id",
+                "is empty".to_string(),
+                "set the account ID or remove the trading section",
+            );
+        }
+        if !is_positive(trading.position_amount) {
+            issue(
+                "trading.position_amount",
+                format!("{} is not positive", trading.position_amount),
+                "set the amount in instrument currency, e.g. 10000",
+            );
+        }
+        if trading.confirmation_timeout_minutes <= 0 {
+            issue(
+                "trading.confirmation_timeout_minutes",
+                format!("{} is not positive", trading.confirmation_timeout_minutes),
+                "remove the key to use the default of 30",
+            );
+        }
+    }
+
+    let notifiers = &config.notifiers;
+    let urls = notifiers
+        .webhooks
+        .iter()
+        .enumerate()
+        .map(|(i, webhook)| (format!("notifiers.webhooks[{}].url", i), &webhook.url))
+        .chain(notifiers.slack.iter().enumerate().map(|(i, url)| (format!("notifiers.slack[{}]", i), url)))
+        .chain(notifiers.discord.iter().enumerate().map(|(i, url)| (format!("notifiers.discord[{}]", i), url)));
+    for (key, url) in urls {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            issue(&key, format!("{:?} is not an HTTP URL", url), "use the full URL starting with https://");
+        }
+    }
+    if let Some(email) = &notifiers.email {
+        if email.to.is_empty() {
+            issue(
+                "notifiers.email.to",
+                "has no recipients".to_string(),
+                "add at least one address or remove the email section",
+            );
+        }
+        if email.username.is_some() != email.password.is_some() {
+            issue(
+                "notifiers.email",
+                "has only one of username and password".to_string(),
+                "set both for SMTP authentication or neither",
+            );
+        }
+    }
+
+    if let Some(backtest) = &config.backtest {
+        if backtest.instruments.is_empty() {
+            issue(
+                "backtest.instruments",
+                "is empty".to_string(),
+                "list tickers within the class codes of filter, e.g. [SBER]",
+            );
+        }
+        if let Some(to) = backtest.to.filter(|to| *to <= backtest.from) {
+            issue(
+                "backtest.to",
+                format!("{} is not after backtest.from {}", to, backtest.from),
+                "set a later end date or remove the key to test until now",
+            );
+        }
+        if !is_positive(backtest.initial_capital) {
+            issue(
+                "backtest.initial_capital",
+                format!("{} is not positive", backtest.initial_capital),
+                "set the starting capital, e.g. 100000",
+            );
+        }
+        for (key, value) in [
+            ("backtest.commission_percentage", backtest.commission_percentage),
+            ("backtest.slippage_percentage", backtest.slippage_percentage),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                issue(key, format!("{} is negative", value), "set 0 or a positive percentage");
+            }
+        }
+    }
+
+    if let Some(optimization) = &config.optimization {
+        for (key, empty) in [
+            ("optimization.short_ema_lengths", optimization.short_ema_lengths.is_empty()),
+            ("optimization.long_ema_lengths", optimization.long_ema_lengths.is_empty()),
+            ("optimization.hysteresis_percentages", optimization.hysteresis_percentages.is_empty()),
+            ("optimization.hysteresis_periods", optimization.hysteresis_periods.is_empty()),
+            ("optimization.intervals", optimization.intervals.is_empty()),
+        ] {
+            if empty {
+                issue(key, "is empty".to_string(), "list at least one value to search");
+            }
+        }
+        let shortest = optimization.short_ema_lengths.iter().min();
+        let longest = optimization.long_ema_lengths.iter().max();
+        if let (Some(short), Some(long)) = (shortest, longest) {
+            if short >= long {
+                issue(
+                    "optimization.short_ema_lengths",
+                    format!("no length is less than the longest of optimization.long_ema_lengths {}", long),
+                    "add shorter lengths or longer long EMA lengths",
+                );
+            }
+        }
+        if optimization.intervals.contains(&IndicatorInterval::Unspecified) {
+            issue(
+                "optimization.intervals",
+                "contains INDICATOR_INTERVAL_UNSPECIFIED".to_string(),
+                "list candle intervals, e.g. INDICATOR_INTERVAL_ONE_HOUR",
+            );
+        }
+        if optimization.method == SearchMethod::Random && optimization.random_samples == 0 {
+            issue(
+                "optimization.random_samples",
+                "is 0".to_string(),
+                "remove the key to use the default of 100",
+            );
+        }
+        if let Some(walk_forward) = &optimization.walk_forward {
+            if walk_forward.windows == 0 {
+                issue(
+                    "optimization.walk_forward.windows",
+                    "is 0".to_string(),
+                    "set the number of windows, e.g. 4",
+                );
+            }
+            if !is_fraction(walk_forward.train_ratio) {
+                issue(
+                    "optimization.walk_forward.train_ratio",
+                    format!("{} is not between 0 and 1", walk_forward.train_ratio),
+                    "set the in-sample share of each window, e.g. 0.7",
+                );
+            }
+        }
+    }
+
+    if let Some(portfolio) = &config.portfolio {
+        if portfolio.max_positions == 0 {
+            issue(
+                "portfolio.max_positions",
+                "is 0".to_string(),
+                "set the number of simultaneous positions, e.g. 5",
+            );
+        }
+        match portfolio.sizing {
+            PositionSizing::FixedFraction if !is_fraction(portfolio.position_fraction) => {
+                issue(
+                    "portfolio.position_fraction",
+                    format!("{} is not between 0 and 1 with fixed_fraction sizing", portfolio.position_fraction),
+                    "set the share of equity per position, e.g. 0.2",
+                )
+            }
+            PositionSizing::FixedAmount if !is_positive(portfolio.position_amount) => issue(
+                "portfolio.position_amount",
+                format!("{} is not positive with fixed_amount sizing", portfolio.position_amount),
+                "set the amount per position, e.g. 20000",
+            ),
+            _ => {}
+        }
+    }
+
+    issues
+}
+
+/// Checks EMA lengths, hysteresis and interval of `strategy` or a strategy with overrides applied
+fn strategy_issues(prefix: &str, strategy: &StrategyConfig, issue: &mut impl FnMut(&str, String, &str)) {
+    if strategy.short_ema_length <= 0 {
+        issue(
+            &format!("{}.short_ema_length", prefix),
+            format!("{} is not positive", strategy.short_ema_length),
+            "set a length of at least 1 candle, e.g. 9",
+        );
+    }
+    if strategy.long_ema_length <= 0 {
+        issue(
+            &format!("{}.long_ema_length", prefix),
+            format!("{} is not positive", strategy.long_ema_length),
+            "set a length of at least 2 candles, e.g. 21",
+        );
+    }
+    if strategy.short_ema_length >= strategy.long_ema_length {
+        issue(
+            &format!("{}.short_ema_length", prefix),
+            format!(
+                "{} is not less than long_ema_length {}",
+                strategy.short_ema_length, strategy.long_ema_length
+            ),
+            "make the short EMA shorter than the long EMA or swap the values",
+        );
+    }
+    if strategy.hysteresis_percentage.is_nan() || strategy.hysteresis_percentage < 0.0 {
+        issue(
+            &format!("{}.hysteresis_percentage", prefix),
+            format!("{} is negative", strategy.hysteresis_percentage),
+            "set 0 to disable hysteresis or a positive percentage, e.g. 0.1",
+        );
+    }
+    if strategy.interval == IndicatorInterval::Unspecified {
+        issue(
+            &format!("{}.interval", prefix),
+            "is INDICATOR_INTERVAL_UNSPECIFIED".to_string(),
+            "set a candle interval, e.g. INDICATOR_INTERVAL_ONE_HOUR",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const EXAMPLE: &str = include_str!("../../config.example.yaml");
+
+    /// Expected key, part of the fix and the change to the example config
+    type Case = (&'static str, &'static str, fn(&mut Config));
+
+    fn example() -> Config {
+        serde_yaml::from_str(EXAMPLE).unwrap()
+    }
+
+    /// Asserts that exactly these issues are reported, each by key and a part of its fix
+    fn assert_issues(config: &Config, expected: &[(&str, &str)]) {
+        assert_issues_with(config, None, expected);
+    }
+
+    fn assert_issues_with(config: &Config, class_codes: Option<&BTreeSet<&str>>, expected: &[(&str, &str)]) {
+        let issues = validate(config, class_codes);
+        for (key, fix) in expected {
+            assert!(
+                issues.iter().any(|issue| issue.key == *key && issue.fix.contains(fix)),
+                "no issue for {} with fix {:?} in {:#?}",
+                key,
+                fix,
+                issues
+            );
+        }
+        assert_eq!(issues.len(), expected.len(), "{:#?}", issues);
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        assert_issues(&example(), &[]);
+    }
+
+    #[test]
+    fn reports_every_issue_at_once() {
+        let mut config = example();
+        config.strategy.short_ema_length = 21;
+        config.strategy.long_ema_length = 9;
+        config.strategy.hysteresis_percentage = -0.1;
+        config.strategy.interval = IndicatorInterval::Unspecified;
+        config.scan_interval_seconds = 0;
+        config.filter.class_code = Some("tqbr".to_string());
+
+        assert_issues(&config, &[
+            ("strategy.short_ema_length", "make the short EMA shorter than the long EMA"),
+            ("strategy.hysteresis_percentage", "set 0 to disable hysteresis"),
+            ("strategy.interval", "set a candle interval"),
+            ("scan_interval_seconds", "set the interval between scans"),
+            ("filter.class_code", "class codes are upper case, use TQBR"),
+        ]);
+    }
+
+    #[test]
+    fn table_of_single_issues() {
+        let cases: Vec<Case> = vec![
+            ("t_token", "BCP_T_TOKEN", |c| c.t_token = Default::default()),
+            ("strategy.short_ema_length", "at least 1 candle", |c| c.strategy.short_ema_length = 0),
+            ("strategy.short_ema_length", "shorter than the long EMA", |c| c.strategy.short_ema_length = 21),
+            ("strategy.hysteresis_percentage", "positive percentage", |c| c.strategy.hysteresis_percentage = f64::NAN),
+            ("filter.class_codes[1]", "use one of TQBR", |c| c.filter.class_codes = vec!["TQTF".into(), "XXXX".into()]),
+            ("instruments.refresh_hours", "positive number of hours", |c| c.instruments.refresh_hours = 0),
+            ("telegram.messages_per_second", "default of 25", |c| c.telegram.messages_per_second = 0),
+            ("notifiers.slack[0]", "starting with https://", |c| c.notifiers.slack = vec!["hooks.slack.com".into()]),
+            ("notifiers.email", "set both", |c| c.notifiers.email.as_mut().unwrap().password = None),
+            ("backtest.initial_capital", "starting capital", |c| c.backtest.as_mut().unwrap().initial_capital = 0.0),
+            ("optimization.intervals", "list candle intervals", |c| {
+                c.optimization.as_mut().unwrap().intervals.push(IndicatorInterval::Unspecified)
+            }),
+            ("portfolio.max_positions", "simultaneous positions", |c| c.portfolio.as_mut().unwrap().max_positions = 0),
+            ("filter.instrument_type", "INSTRUMENT_TYPE_SHARE", |c| c.filter.instrument_type = InstrumentType::Unspecified),
+            ("filter.instrument_type", "INSTRUMENT_TYPE_FUTURES", |c| c.filter.instrument_type = InstrumentType::Currency),
+            ("filter.instrument_type", "INSTRUMENT_TYPE_FUTURES", |c| c.filter.instrument_type = InstrumentType::Option),
+            ("filter.instrument_type", "INSTRUMENT_TYPE_FUTURES", |c| c.filter.instrument_type = InstrumentType::Index),
+        ];
+
+        for (key, fix, change) in cases {
+            let mut config = example();
+            change(&mut config);
+            assert_issues(&config, &[(key, fix)]);
+        }
+    }
+
+    #[test]
+    fn class_codes_are_checked_against_the_instrument_cache() {
+        let cached = BTreeSet::from(["TQBR", "SPBXM", "XNEW"]);
+        let mut config = example();
+        config.filter.class_codes = vec!["XNEW".into()];
+        assert_issues_with(&config, Some(&cached), &[]);
+        assert_issues(&config, &[("filter.class_codes[0]", "use one of TQBR")]);
+
+        config.filter.class_codes = vec!["TQTF".into()];
+        assert_issues_with(&config, Some(&cached), &[("filter.class_codes[0]", "use one of SPBXM, TQBR, XNEW")]);
+    }
+
+    #[test]
+    fn overrides_are_checked_with_their_key_path() {
+        let config: Config = serde_yaml::from_str(&format!(
+            "{}\nstrategy_overrides:\n  tickers:\n    SBER:\n      short_ema_length: 30\n",
+            EXAMPLE
+        ))
+        .unwrap();
+        assert_issues(&config, &[("strategy_overrides.tickers.SBER.short_ema_length", "shorter than the long EMA")]);
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_with_their_path() {
+        let path = std::env::temp_dir().join(format!("bcp_config_{}.yaml", std::process::id()));
+        fs::write(&path, EXAMPLE.replace("  short_ema_length: 8", "  short_ema: 8")).unwrap();
+
+        let error = Config::with_vars(path.to_str().unwrap(), std::iter::empty()).unwrap_err().to_string();
+        assert!(error.contains("strategy.short_ema: unknown field `short_ema`"), "{}", error);
+        assert!(error.contains("Fix: fix the key name or remove it"), "{}", error);
+
+        fs::write(&path, EXAMPLE.replace("scan_interval_seconds: 300", "scan_interval_seconds: 0")).unwrap();
+        let error = Config::with_vars(path.to_str().unwrap(), std::iter::empty()).unwrap_err().to_string();
+        assert!(error.contains("1 problem(s)"), "{}", error);
+        assert!(error.contains("  - scan_interval_seconds: is 0. Fix: set the interval between scans"), "{}", error);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::config::Config;
+use crate::instruments::get_instruments::{
+    InstrumentInfo, InstrumentKind, InstrumentStatus, InstrumentsRequest, InstrumentsResponse,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info};
+
+/// Cache shared between the scanner and command handlers
+pub type SharedInstruments = Arc<RwLock<InstrumentCache>>;
+
+/// Instrument metadata kept in the cache
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CachedInstrument {
+    pub uid: String,
+    pub figi: String,
+    pub ticker: String,
+    pub class_code: String,
+    pub isin: String,
+    pub name: String,
+    /// share, bond, etf or futures
+    pub instrument_type: String,
+    pub lot: i32,
+    pub currency: String,
+    pub min_price_increment: f64,
+    pub country_of_risk: String,
+    /// Lowercase, e.g. energy
+    pub sector: String,
+    pub asset_uid: String,
+    pub short_enabled: bool,
+    pub for_qual_investor: bool,
+    pub api_trade_available: bool,
+}
+
+impl CachedInstrument {
+    fn new(kind: InstrumentKind, info: InstrumentInfo) -> Option<Self> {
+        Some(Self {
+            uid: info.uid.filter(|uid| !uid.is_empty())?,
+            figi: info.figi.unwrap_or_default(),
+            ticker: info.ticker.unwrap_or_default(),
+            class_code: info.class_code.unwrap_or_default(),
+            isin: info.isin.unwrap_or_default(),
+            name: info.name.unwrap_or_default(),
+            instrument_type: kind.instrument_type().to_string(),
+            lot: info.lot.unwrap_or(1),
+            currency: info.currency.unwrap_or_default(),
+            min_price_increment: info.min_price_increment.map(|q| q.to_f64()).unwrap_or_default(),
+            country_of_risk: info.country_of_risk.unwrap_or_default(),
+            sector: info.sector.unwrap_or_default().to_lowercase(),
+            asset_uid: info.asset_uid.unwrap_or_default(),
+            short_enabled: info.short_enabled_flag.unwrap_or(false),
+            for_qual_investor: info.for_qual_investor_flag.unwrap_or(false),
+            api_trade_available: info.api_trade_available_flag.unwrap_or(false),
+        })
+    }
+
+    /// Sector if the API reports one
+    pub fn sector(&self) -> Option<&str> {
+        Some(self.sector.as_str()).filter(|sector| !sector.is_empty())
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct StoredInstruments {
+    updated_at: Option<DateTime<Utc>>,
+    instruments: Vec<CachedInstrument>,
+}
+
+/// Shares, bonds, ETFs and futures persisted to a JSON file and refreshed from the API when stale
+pub struct InstrumentCache {
+    path: PathBuf,
+    updated_at: Option<DateTime<Utc>>,
+    instruments: Vec<CachedInstrument>,
+    /// Index in `instruments` by UID
+    by_uid: HashMap<String, usize>,
+    /// Index in `instruments` by FIGI
+    by_figi: HashMap<String, usize>,
+}
+
+impl InstrumentCache {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let path = PathBuf::from(path);
+        let stored: StoredInstruments = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            StoredInstruments::default()
+        };
+        info!("Loaded {} instruments from {}", stored.instruments.len(), path.display());
+
+        let mut cache = Self {
+            path,
+            updated_at: stored.updated_at,
+            instruments: Vec::new(),
+            by_uid: HashMap::new(),
+            by_figi: HashMap::new(),
+        };
+        cache.replace(stored.instruments);
+        Ok(cache)
+    }
+
+    /// Loads the cache file and refreshes it if it is older than `instruments.refresh_hours`
+    pub async fn open(client: &reqwest::Client, config: &Config) -> Result<Self, Box<dyn Error>> {
+        let mut cache = Self::load(&config.instruments.cache_file)?;
+        if cache.is_stale(config.instruments.refresh_hours) {
+            cache.refresh(client, &config.t_token, config.assets.instrument_status).await?;
+        }
+        Ok(cache)
+    }
+
+    /// Writes the file atomically via a temporary file
+    fn save(&self) {
+        let result = (|| -> Result<(), Box<dyn Error>> {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let stored = StoredInstruments {
+                updated_at: self.updated_at,
+                instruments: self.instruments.clone(),
+            };
+            let tmp = self.path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_string(&stored)?)?;
+            fs::rename(tmp, &self.path)?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            error!("Error saving instruments to {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn replace(&mut self, instruments: Vec<CachedInstrument>) {
+        self.by_uid = instruments.iter().enumerate().map(|(i, instrument)| (instrument.uid.clone(), i)).collect();
+        self.by_figi = instruments
+            .iter()
+            .enumerate()
+            .filter(|(_, instrument)| !instrument.figi.is_empty())
+            .map(|(i, instrument)| (instrument.figi.clone(), i))
+            .collect();
+        self.instruments = instruments;
+    }
+
+    /// Empty or loaded more than `refresh_hours` ago
+    pub fn is_stale(&self, refresh_hours: u64) -> bool {
+        self.instruments.is_empty()
+            || self
+                .updated_at
+                .is_none_or(|updated_at| Utc::now() - updated_at >= Duration::hours(refresh_hours as i64))
+    }
+
+    /// Downloads all instrument lists, the cache is left as it was if any of them fails
+    pub async fn refresh(
+        &mut self,
+        client: &reqwest::Client,
+        token: &str,
+        instrument_status: InstrumentStatus,
+    ) -> Result<(), Box<dyn Error>> {
+        let instruments = Self::download(client, token, instrument_status).await?;
+        self.update(instruments);
+        Ok(())
+    }
+
+    /// Downloads all instrument lists without touching a cache
+    pub async fn download(
+        client: &reqwest::Client,
+        token: &str,
+        instrument_status: InstrumentStatus,
+    ) -> Result<Vec<CachedInstrument>, Box<dyn Error>> {
+        let mut instruments = Vec::new();
+        for kind in InstrumentKind::ALL {
+            let request = InstrumentsRequest { instrument_status };
+            let response = InstrumentsResponse::get_instruments(client, token, kind, request).await?;
+            instruments.extend(
+                response
+                    .instruments
+                    .into_iter()
+                    .filter_map(|info| CachedInstrument::new(kind, info)),
+            );
+        }
+        Ok(instruments)
+    }
+
+    /// Replaces the instruments with downloaded ones and saves the file
+    pub fn update(&mut self, instruments: Vec<CachedInstrument>) {
+        info!("Instrument cache refreshed with {} instruments", instruments.len());
+        self.updated_at = Some(Utc::now());
+        self.replace(instruments);
+        self.save();
+    }
+
+    pub fn by_uid(&self, uid: &str) -> Option<&CachedInstrument> {
+        self.by_uid.get(uid).map(|i| &self.instruments[*i])
+    }
+
+    pub fn by_figi(&self, figi: &str) -> Option<&CachedInstrument> {
+        self.by_figi.get(figi).map(|i| &self.instruments[*i])
+    }
+
+    /// Finds an instrument by ticker, case-insensitive, within the first of the class codes that lists it
+    pub fn by_ticker(&self, ticker: &str, class_codes: &[&str]) -> Option<&CachedInstrument> {
+        class_codes.iter().find_map(|class_code| {
+            self.instruments
+                .iter()
+                .find(|i| i.class_code == *class_code && i.ticker.eq_ignore_ascii_case(ticker))
+        })
+    }
+
+    /// Class codes of all cached instruments
+    pub fn class_codes(&self) -> BTreeSet<&str> {
+        self.instruments.iter().map(|i| i.class_code.as_str()).filter(|code| !code.is_empty()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CachedInstrument> {
+        self.instruments.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instruments.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instrument(uid: &str, figi: &str, ticker: &str, class_code: &str) -> CachedInstrument {
+        CachedInstrument {
+            uid: uid.to_string(),
+            figi: figi.to_string(),
+            ticker: ticker.to_string(),
+            class_code: class_code.to_string(),
+            instrument_type: "share".to_string(),
+            lot: 10,
+            ..Default::default()
+        }
+    }
+
+    fn cache(name: &str, instruments: Vec<CachedInstrument>) -> InstrumentCache {
+        let path = std::env::temp_dir().join(format!("bcp_instruments_{}_{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut cache = InstrumentCache::load(path.to_str().unwrap()).unwrap();
+        cache.replace(instruments);
+        cache.updated_at = Some(Utc::now());
+        cache
+    }
+
+    #[test]
+    fn instruments_survive_a_save_and_load() {
+        let mut cache = cache("round_trip", Vec::new());
+        let sber = CachedInstrument {
+            sector: "financial".to_string(),
+            short_enabled: true,
+            min_price_increment: 0.01,
+            ..instrument("uid-sber", "BBG004730N88", "SBER", "TQBR")
+        };
+        cache.update(vec![sber, instrument("uid-gazp", "", "GAZP", "TQBR")]);
+
+        let loaded = InstrumentCache::load(cache.path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.updated_at, cache.updated_at);
+        assert_eq!(loaded.iter().map(|i| i.ticker.as_str()).collect::<Vec<_>>(), ["SBER", "GAZP"]);
+        let sber = loaded.by_uid("uid-sber").unwrap();
+        assert_eq!(sber.sector(), Some("financial"));
+        assert!(sber.short_enabled);
+        assert_eq!(sber.min_price_increment, 0.01);
+        assert_eq!(loaded.by_figi("BBG004730N88").unwrap().uid, "uid-sber");
+        assert!(!cache.path.with_extension("tmp").exists());
+        let _ = fs::remove_file(&cache.path);
+    }
+
+    #[test]
+    fn staleness_follows_refresh_hours() {
+        let mut cache = cache("stale", vec![instrument("uid-sber", "", "SBER", "TQBR")]);
+        assert!(!cache.is_stale(24));
+
+        cache.updated_at = Some(Utc::now() - Duration::hours(23));
+        assert!(!cache.is_stale(24));
+        assert!(cache.is_stale(23));
+        cache.updated_at = Some(Utc::now() - Duration::hours(25));
+        assert!(cache.is_stale(24));
+        cache.updated_at = None;
+        assert!(cache.is_stale(24));
+
+        let empty = self::cache("stale_empty", Vec::new());
+        assert!(empty.is_stale(24));
+    }
+
+    #[test]
+    fn ticker_is_found_on_the_first_configured_board_listing_it() {
+        let cache = cache(
+            "by_ticker",
+            vec![
+                instrument("uid-sber-smal", "", "SBER", "SMAL"),
+                instrument("uid-sber-tqbr", "", "SBER", "TQBR"),
+                instrument("uid-tmos", "", "TMOS", "TQTF"),
+            ],
+        );
+
+        assert_eq!(cache.by_ticker("sber", &["TQBR", "SMAL"]).unwrap().uid, "uid-sber-tqbr");
+        assert_eq!(cache.by_ticker("SBER", &["SMAL", "TQBR"]).unwrap().uid, "uid-sber-smal");
+        assert_eq!(cache.by_ticker("SBER", &["TQTF", "TQBR"]).unwrap().uid, "uid-sber-tqbr");
+        assert!(cache.by_ticker("SBER", &["TQTF"]).is_none());
+        assert!(cache.by_ticker("TMOS", &[]).is_none());
+    }
+
+    #[test]
+    fn instruments_are_found_by_uid_and_figi() {
+        let cache = cache(
+            "by_uid",
+            vec![instrument("uid-sber", "BBG004730N88", "SBER", "TQBR"), instrument("uid-gazp", "", "GAZP", "TQBR")],
+        );
+
+        assert_eq!(cache.by_uid("uid-gazp").unwrap().ticker, "GAZP");
+        assert_eq!(cache.by_figi("BBG004730N88").unwrap().ticker, "SBER");
+        assert!(cache.by_uid("BBG004730N88").is_none());
+        assert!(cache.by_figi("").is_none());
+        assert!(cache.by_uid("unknown").is_none());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::models::enums::InstrumentType;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FindInstrumentRequest {
+    /// Ticker, name, ISIN or FIGI, matched in full or in part
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instrument_kind: Option<InstrumentType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_trade_available_flag: Option<bool>,
+}
+
+impl FindInstrumentRequest {
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            instrument_kind: None,
+            api_trade_available_flag: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InstrumentShort {
+    pub isin: Option<String>,
+    pub figi: Option<String>,
+    pub ticker: Option<String>,
+    pub class_code: Option<String>,
+    pub instrument_type: Option<String>,
+    pub name: Option<String>,
+    pub uid: Option<String>,
+    pub position_uid: Option<String>,
+    pub instrument_kind: Option<InstrumentType>,
+    pub api_trade_available_flag: Option<bool>,
+    pub for_iis_flag: Option<bool>,
+    pub for_qual_investor_flag: Option<bool>,
+    pub weekend_flag: Option<bool>,
+    pub blocked_tca_flag: Option<bool>,
+    pub lot: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FindInstrumentResponse {
+    #[serde(default)]
+    pub instruments: Vec<InstrumentShort>,
+}
+
+impl FindInstrumentResponse {
+    pub async fn find_instrument(
+        client: &reqwest::Client,
+        api_url: &str,
+        api_token: &str,
+        request: FindInstrumentRequest,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let find_url = format!("{}/tinkoff.public.invest.api.contract.v1.InstrumentsService/FindInstrument", api_url);
+
+        info!("send request: {:?}", request);
+
+        let response = client
+            .post(&find_url)
+            .bearer_auth(api_token)
+            .json(&request)
+            .send()
+            .await?;
+
+        // Check response status
+        if !response.status().is_success() {
+            error!("request error: {}", response.status());
+            let error_text = response.text().await?;
+            error!("text of error: {}", error_text);
+            return Err("api request error".into());
+        }
+
+        let find_response: FindInstrumentResponse = response.json().await?;
+
+        Ok(find_response)
+    }
+}
@@ -18,7 +18,7 @@ pub enum InstrumentIdType {
     PositionUid,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
 pub enum TradingStatus {
     #[serde(rename = "SECURITY_TRADING_STATUS_UNSPECIFIED")]
     #[default]
@@ -57,6 +57,31 @@ pub enum TradingStatus {
     DealerNotAvailableForTrading,
 }
 
+impl TradingStatus {
+    /// Short description for messages
+    pub fn description(&self) -> &'static str {
+        match self {
+            TradingStatus::Unspecified => "unknown",
+            TradingStatus::NotAvailableForTrading => "not available for trading",
+            TradingStatus::OpeningPeriod => "opening period",
+            TradingStatus::ClosingPeriod => "closing period",
+            TradingStatus::BreakInTrading => "break in trading",
+            TradingStatus::NormalTrading => "normal trading",
+            TradingStatus::ClosingAuction => "closing auction",
+            TradingStatus::DarkPoolAuction => "dark pool auction",
+            TradingStatus::DiscreteAuction => "discrete auction",
+            TradingStatus::OpeningAuctionPeriod => "opening auction",
+            TradingStatus::TradingAtClosingAuctionPrice => "trading at closing auction price",
+            TradingStatus::SessionAssigned => "session assigned",
+            TradingStatus::SessionClose => "session closed",
+            TradingStatus::SessionOpen => "session open",
+            TradingStatus::DealerNormalTrading => "dealer trading",
+            TradingStatus::DealerBreakInTrading => "dealer break in trading",
+            TradingStatus::DealerNotAvailableForTrading => "not available for dealer trading",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub enum RealExchange {
     #[serde(rename = "REAL_EXCHANGE_UNSPECIFIED")]
@@ -140,17 +165,18 @@ pub struct InstrumentResponse {
 }
 
 impl InstrumentResponse {
-    pub async fn _get_instrument_by(
+    pub async fn get_instrument_by(
         client: &reqwest::Client,
+        api_url: &str,
         api_token: &str,
         request: GetInstrumentByRequest,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let instrument_url = "https://invest-public-api.tinkoff.ru/rest/tinkoff.public.invest.api.contract.v1.InstrumentsService/GetInstrumentBy";
+        let instrument_url = format!("{}/tinkoff.public.invest.api.contract.v1.InstrumentsService/GetInstrumentBy", api_url);
 
         info!("send request: {:?}", request);
 
         let response = client
-            .post(instrument_url)
+            .post(&instrument_url)
             .bearer_auth(api_token)
             .json(&request)
             .send()
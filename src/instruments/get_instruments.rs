@@ -0,0 +1,118 @@
+use crate::models::structs::Quotation;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+/// Instruments available for trading through the API or all of them
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum InstrumentStatus {
+    #[serde(rename = "INSTRUMENT_STATUS_UNSPECIFIED")]
+    Unspecified,
+    #[serde(rename = "INSTRUMENT_STATUS_BASE")]
+    Base,
+    #[serde(rename = "INSTRUMENT_STATUS_ALL")]
+    All,
+}
+
+/// Instrument lists of InstrumentsService
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstrumentKind {
+    Shares,
+    Bonds,
+    Etfs,
+    Futures,
+}
+
+impl InstrumentKind {
+    pub const ALL: [InstrumentKind; 4] = [
+        InstrumentKind::Shares,
+        InstrumentKind::Bonds,
+        InstrumentKind::Etfs,
+        InstrumentKind::Futures,
+    ];
+
+    fn method(&self) -> &str {
+        match self {
+            InstrumentKind::Shares => "Shares",
+            InstrumentKind::Bonds => "Bonds",
+            InstrumentKind::Etfs => "Etfs",
+            InstrumentKind::Futures => "Futures",
+        }
+    }
+
+    /// Same as `InstrumentType::as_str`
+    pub fn instrument_type(&self) -> &str {
+        match self {
+            InstrumentKind::Shares => "share",
+            InstrumentKind::Bonds => "bond",
+            InstrumentKind::Etfs => "etf",
+            InstrumentKind::Futures => "futures",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InstrumentsRequest {
+    #[serde(rename = "instrumentStatus")]
+    pub instrument_status: InstrumentStatus,
+}
+
+/// Fields shared by Share, Bond, Etf and Future
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InstrumentInfo {
+    pub uid: Option<String>,
+    pub figi: Option<String>,
+    pub ticker: Option<String>,
+    pub class_code: Option<String>,
+    pub isin: Option<String>,
+    pub name: Option<String>,
+    pub lot: Option<i32>,
+    pub currency: Option<String>,
+    pub min_price_increment: Option<Quotation>,
+    pub country_of_risk: Option<String>,
+    pub sector: Option<String>,
+    pub asset_uid: Option<String>,
+    pub short_enabled_flag: Option<bool>,
+    pub for_qual_investor_flag: Option<bool>,
+    pub api_trade_available_flag: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InstrumentsResponse {
+    pub instruments: Vec<InstrumentInfo>,
+}
+
+impl InstrumentsResponse {
+    pub async fn get_instruments(
+        client: &reqwest::Client,
+        api_token: &str,
+        kind: InstrumentKind,
+        request: InstrumentsRequest,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let instruments_url = format!(
+            "https://invest-public-api.tinkoff.ru/rest/tinkoff.public.invest.api.contract.v1.InstrumentsService/{}",
+            kind.method()
+        );
+
+        info!("send {} request: {:?}", kind.method(), request);
+
+        let response = client
+            .post(instruments_url)
+            .bearer_auth(api_token)
+            .json(&request)
+            .send()
+            .await?;
+
+        // Check response status
+        if !response.status().is_success() {
+            error!("request error: {}", response.status());
+            let error_text = response.text().await?;
+            error!("text of error: {}", error_text);
+            return Err("api request error".into());
+        }
+
+        let instruments_response: InstrumentsResponse = response.json().await?;
+
+        Ok(instruments_response)
+    }
+}
@@ -0,0 +1,225 @@
+use crate::instruments::find_instrument::{FindInstrumentRequest, FindInstrumentResponse, InstrumentShort};
+use crate::instruments::get_instrument_by::{GetInstrumentByRequest, Instrument, InstrumentIdType, InstrumentResponse, TradingStatus};
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::error;
+
+/// GetInstrumentBy calls in flight during a search
+const DETAIL_REQUESTS: usize = 5;
+
+/// REST endpoint of the T-Invest API
+const API_URL: &str = "https://invest-public-api.tinkoff.ru/rest";
+
+/// Instrument description shown by search and lookup
+#[derive(Debug, Clone)]
+pub struct InstrumentSummary {
+    pub uid: String,
+    pub ticker: String,
+    pub name: String,
+    pub class_code: String,
+    pub lot: i32,
+    /// Unknown if the details couldn't be loaded
+    pub trading_status: Option<TradingStatus>,
+    pub api_trade_available: bool,
+}
+
+impl From<Instrument> for InstrumentSummary {
+    fn from(instrument: Instrument) -> Self {
+        Self {
+            uid: instrument.uid.unwrap_or_default(),
+            ticker: instrument.ticker.unwrap_or_default(),
+            name: instrument.name.unwrap_or_default(),
+            class_code: instrument.class_code.unwrap_or_default(),
+            lot: instrument.lot.unwrap_or(1),
+            trading_status: instrument.trading_status,
+            api_trade_available: instrument.api_trade_available_flag.unwrap_or(false),
+        }
+    }
+}
+
+impl From<InstrumentShort> for InstrumentSummary {
+    fn from(instrument: InstrumentShort) -> Self {
+        Self {
+            uid: instrument.uid.unwrap_or_default(),
+            ticker: instrument.ticker.unwrap_or_default(),
+            name: instrument.name.unwrap_or_default(),
+            class_code: instrument.class_code.unwrap_or_default(),
+            lot: instrument.lot.unwrap_or(1),
+            trading_status: None,
+            api_trade_available: instrument.api_trade_available_flag.unwrap_or(false),
+        }
+    }
+}
+
+impl InstrumentSummary {
+    /// Trading status for display
+    pub fn status_text(&self) -> &str {
+        self.trading_status.map_or("unknown", |status| status.description())
+    }
+}
+
+/// Search results with the number of matches before the limit
+#[derive(Debug)]
+pub struct SearchResults {
+    pub instruments: Vec<InstrumentSummary>,
+    pub total: usize,
+}
+
+/// Looks instruments up with GetInstrumentBy and searches them with FindInstrument
+pub struct InstrumentLookup<'a> {
+    client: &'a reqwest::Client,
+    token: &'a str,
+    api_url: &'a str,
+}
+
+impl<'a> InstrumentLookup<'a> {
+    pub fn new(client: &'a reqwest::Client, token: &'a str) -> Self {
+        Self {
+            client,
+            token,
+            api_url: API_URL,
+        }
+    }
+
+    /// Sends requests to another endpoint, e.g. a local server in tests
+    pub fn with_api_url(mut self, api_url: &'a str) -> Self {
+        self.api_url = api_url;
+        self
+    }
+
+    pub async fn by_uid(&self, uid: &str) -> Result<InstrumentSummary, Box<dyn Error>> {
+        let request = GetInstrumentByRequest {
+            id_type: InstrumentIdType::Uid,
+            class_code: None,
+            id: uid.to_string(),
+        };
+        let response = InstrumentResponse::get_instrument_by(self.client, self.api_url, self.token, request).await?;
+        Ok(response.instrument.into())
+    }
+
+    /// Full-text search by ticker, name, ISIN or FIGI. Trading status of the first `limit` matches
+    /// is loaded with concurrent GetInstrumentBy calls, matches whose details fail are returned without it
+    pub async fn search(&self, query: &str, limit: usize) -> Result<SearchResults, Box<dyn Error>> {
+        let response =
+            FindInstrumentResponse::find_instrument(self.client, self.api_url, self.token, FindInstrumentRequest::new(query))
+                .await?;
+        let total = response.instruments.len();
+
+        let permits = Arc::new(Semaphore::new(DETAIL_REQUESTS));
+        let mut tasks = JoinSet::new();
+        for (index, found) in response.instruments.into_iter().take(limit).enumerate() {
+            let client = self.client.clone();
+            let token = self.token.to_string();
+            let api_url = self.api_url.to_string();
+            let permits = permits.clone();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let uid = found.uid.clone().unwrap_or_default();
+                let details = InstrumentLookup::new(&client, &token)
+                    .with_api_url(&api_url)
+                    .by_uid(&uid)
+                    .await
+                    .map_err(|e| e.to_string());
+                let summary = details.unwrap_or_else(|e| {
+                    error!("Error getting details of {}: {}", uid, e);
+                    InstrumentSummary::from(found)
+                });
+                (index, summary)
+            });
+        }
+
+        // Tasks finish in any order, keep the order of the search results
+        let mut instruments = tasks.join_all().await;
+        instruments.sort_by_key(|(index, _)| *index);
+        let instruments = instruments.into_iter().map(|(_, summary)| summary).collect();
+
+        Ok(SearchResults { instruments, total })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifiers::mock::{client, http_responder, HttpReply};
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn found(index: usize) -> serde_json::Value {
+        json!({"uid": format!("uid-{}", index), "ticker": format!("T{}", index), "classCode": "TQBR", "lot": 1})
+    }
+
+    #[tokio::test]
+    async fn search_keeps_the_result_order_within_the_limit() {
+        let (url, requests) = http_responder(|request| {
+            if request.path.ends_with("/FindInstrument") {
+                return HttpReply::json(json!({"instruments": (0..4).map(found).collect::<Vec<_>>()}));
+            }
+            let uid = request.json()["id"].as_str().unwrap().to_string();
+            let index: u64 = uid.trim_start_matches("uid-").parse().unwrap();
+            let instrument = json!({
+                "uid": uid,
+                "ticker": format!("T{}", index),
+                "name": format!("Instrument {}", index),
+                "classCode": "TQBR",
+                "lot": 10,
+                "tradingStatus": "SECURITY_TRADING_STATUS_NORMAL_TRADING",
+                "apiTradeAvailableFlag": true,
+            });
+            // Earlier results are answered later
+            HttpReply::json(json!({"instrument": instrument})).after(Duration::from_millis(30 * (3 - index)))
+        })
+        .await;
+
+        let client = client();
+        let results = InstrumentLookup::new(&client, "token").with_api_url(&url).search("T", 3).await.unwrap();
+
+        assert_eq!(results.total, 4);
+        let tickers: Vec<&str> = results.instruments.iter().map(|i| i.ticker.as_str()).collect();
+        assert_eq!(tickers, ["T0", "T1", "T2"]);
+        assert!(results.instruments.iter().all(|i| i.lot == 10 && i.api_trade_available));
+        assert_eq!(results.instruments[0].status_text(), "normal trading");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].json()["query"], "T");
+        assert!(requests[1..].iter().all(|r| r.path.ends_with("/GetInstrumentBy")));
+    }
+
+    #[tokio::test]
+    async fn failed_details_fall_back_to_the_search_result() {
+        let (url, _) = http_responder(|request| {
+            if request.path.ends_with("/FindInstrument") {
+                HttpReply::json(json!({"instruments": [found(0)]}))
+            } else {
+                HttpReply::status(500)
+            }
+        })
+        .await;
+
+        let client = client();
+        let results = InstrumentLookup::new(&client, "token").with_api_url(&url).search("T0", 5).await.unwrap();
+
+        assert_eq!(results.instruments.len(), 1);
+        assert_eq!(results.instruments[0].uid, "uid-0");
+        assert_eq!(results.instruments[0].trading_status, None);
+        assert_eq!(results.instruments[0].status_text(), "unknown");
+    }
+
+    #[tokio::test]
+    async fn by_uid_requests_the_instrument_by_uid() {
+        let (url, requests) = http_responder(|_| {
+            HttpReply::json(json!({"instrument": {"uid": "uid-sber", "ticker": "SBER", "lot": 10, "classCode": "TQBR"}}))
+        })
+        .await;
+
+        let client = client();
+        let summary = InstrumentLookup::new(&client, "token").with_api_url(&url).by_uid("uid-sber").await.unwrap();
+
+        assert_eq!((summary.ticker.as_str(), summary.lot), ("SBER", 10));
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].json(), json!({"idType": "INSTRUMENT_ID_TYPE_UID", "classCode": null, "id": "uid-sber"}));
+        assert_eq!(requests[0].header("authorization"), Some("Bearer token"));
+    }
+}
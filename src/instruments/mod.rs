@@ -1,2 +1,6 @@
-pub mod get_assets;
+pub mod cache;
+pub mod find_instrument;
 pub mod get_instrument_by;
+pub mod get_instruments;
+pub mod lookup;
+pub mod universe;
@@ -0,0 +1,227 @@
+use crate::config::FilterConfig;
+use crate::instruments::cache::{CachedInstrument, InstrumentCache};
+use crate::market_data_service::get_candles::{CandleInterval, GetCandlesResponse, HistoricCandle};
+use chrono::{Duration, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{error, info};
+
+/// Daily candle requests in flight while calculating turnovers
+const CANDLE_REQUESTS: usize = 5;
+
+/// Selects instruments matching `FilterConfig` from the instrument cache, turnover is cached between scans
+#[derive(Default)]
+pub struct Universe {
+    /// Average daily turnover by instrument UID and the day it was calculated
+    turnovers: HashMap<String, (NaiveDate, f64)>,
+}
+
+impl Universe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached instruments matching the filter. Instruments whose turnover can't be loaded are skipped
+    pub async fn select(
+        &mut self,
+        client: &reqwest::Client,
+        token: &str,
+        filter: &FilterConfig,
+        instruments: &InstrumentCache,
+    ) -> Vec<CachedInstrument> {
+        let candidates: Vec<CachedInstrument> =
+            instruments.iter().filter(|instrument| matches(instrument, filter)).cloned().collect();
+
+        let selected: Vec<CachedInstrument> = match filter.min_daily_turnover {
+            Some(minimum) => {
+                self.update_turnovers(client, token, &candidates, filter.turnover_days).await;
+                self.with_turnover(candidates, minimum)
+            }
+            None => candidates,
+        };
+
+        info!("{} instruments match the filter", selected.len());
+        selected
+    }
+
+    /// Candidates with an average daily turnover of at least `minimum`, the ones never loaded are skipped
+    fn with_turnover(&self, candidates: Vec<CachedInstrument>, minimum: f64) -> Vec<CachedInstrument> {
+        candidates
+            .into_iter()
+            .filter(|candidate| self.turnovers.get(&candidate.uid).is_some_and(|(_, turnover)| *turnover >= minimum))
+            .collect()
+    }
+
+    /// Calculates the average daily turnover over the last `days` trading days once a day.
+    /// The previous value is kept if the candles can't be loaded
+    async fn update_turnovers(&mut self, client: &reqwest::Client, token: &str, candidates: &[CachedInstrument], days: u32) {
+        let today = Utc::now().date_naive();
+        let permits = Arc::new(Semaphore::new(CANDLE_REQUESTS));
+        let mut tasks = JoinSet::new();
+        for candidate in candidates {
+            if self.turnovers.get(&candidate.uid).is_some_and(|(date, _)| *date == today) {
+                continue;
+            }
+            let client = client.clone();
+            let token = token.to_string();
+            let uid = candidate.uid.clone();
+            let lot = candidate.lot;
+            let permits = permits.clone();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let now = Utc::now();
+                // Calendar days covering weekends and holidays
+                let from = now - Duration::days(days as i64 * 7 / 5 + 7);
+                let candles = GetCandlesResponse::get_candles_range(&client, &token, &uid, from, now, CandleInterval::Day)
+                    .await
+                    .map_err(|e| e.to_string());
+                let turnover = candles.map(|candles| average_turnover(&candles, lot, days));
+                (uid, turnover)
+            });
+        }
+
+        for (uid, turnover) in tasks.join_all().await {
+            match turnover {
+                Ok(turnover) => {
+                    self.turnovers.insert(uid, (today, turnover));
+                }
+                Err(e) => error!("Error getting daily candles of {}: {}", uid, e),
+            }
+        }
+    }
+}
+
+/// Checks every filter key except the turnover threshold, lists are case-insensitive and empty ones allow all
+pub fn matches(instrument: &CachedInstrument, filter: &FilterConfig) -> bool {
+    let listed = |values: &[String], value: &str| values.iter().any(|v| v.eq_ignore_ascii_case(value));
+    let allowed = |values: &[String], value: &str| values.is_empty() || listed(values, value);
+    let flag = |required: Option<bool>, value: bool| required.is_none_or(|required| value == required);
+
+    filter.class_codes().contains(&instrument.class_code.as_str())
+        && instrument.instrument_type == filter.instrument_type.as_str()
+        && allowed(&filter.include_tickers, &instrument.ticker)
+        && !listed(&filter.exclude_tickers, &instrument.ticker)
+        && allowed(&filter.currencies, &instrument.currency)
+        && allowed(&filter.countries_of_risk, &instrument.country_of_risk)
+        && allowed(&filter.sectors, &instrument.sector)
+        && flag(filter.for_qual_investor, instrument.for_qual_investor)
+        && flag(filter.short_enabled, instrument.short_enabled)
+}
+
+/// Average close price times volume of the last `days` complete candles, 0 without any
+fn average_turnover(candles: &[HistoricCandle], lot: i32, days: u32) -> f64 {
+    let turnovers: Vec<f64> = candles
+        .iter()
+        .filter(|candle| candle.is_complete)
+        .rev()
+        .take(days as usize)
+        .map(|candle| candle.close.to_f64() * candle.volume.parse::<f64>().unwrap_or(0.0) * lot as f64)
+        .collect();
+    if turnovers.is_empty() {
+        0.0
+    } else {
+        turnovers.iter().sum::<f64>() / turnovers.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::structs::Quotation;
+
+    /// Case name, whether the instrument matches and the change to the default filter and instrument
+    type Case = (&'static str, bool, fn(&mut FilterConfig, &mut CachedInstrument));
+
+    fn instrument() -> CachedInstrument {
+        CachedInstrument {
+            uid: "uid".to_string(),
+            ticker: "SBER".to_string(),
+            class_code: "TQBR".to_string(),
+            instrument_type: "share".to_string(),
+            lot: 10,
+            currency: "rub".to_string(),
+            country_of_risk: "RU".to_string(),
+            sector: "financial".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn filter() -> FilterConfig {
+        serde_yaml::from_str("class_codes: [TQBR, TQTF]\ninstrument_type: INSTRUMENT_TYPE_SHARE").unwrap()
+    }
+
+    fn candle(close: i64, volume: u64, is_complete: bool) -> HistoricCandle {
+        let price = Quotation { units: close.to_string(), nano: 0 };
+        HistoricCandle {
+            open: price.clone(),
+            high: price.clone(),
+            low: price.clone(),
+            close: price,
+            volume: volume.to_string(),
+            time: String::new(),
+            is_complete,
+        }
+    }
+
+    #[test]
+    fn table_of_filter_keys() {
+        let cases: Vec<Case> = vec![
+            ("all defaults", true, |_, _| {}),
+            ("other class code", false, |_, i| i.class_code = "SPBXM".to_string()),
+            ("second class code", true, |_, i| i.class_code = "TQTF".to_string()),
+            ("other type", false, |_, i| i.instrument_type = "bond".to_string()),
+            ("included ticker", true, |f, _| f.include_tickers = vec!["sber".to_string()]),
+            ("not included ticker", false, |f, _| f.include_tickers = vec!["GAZP".to_string()]),
+            ("excluded ticker", false, |f, _| f.exclude_tickers = vec!["Sber".to_string()]),
+            ("other excluded ticker", true, |f, _| f.exclude_tickers = vec!["GAZP".to_string()]),
+            ("currency", true, |f, _| f.currencies = vec!["RUB".to_string(), "usd".to_string()]),
+            ("other currency", false, |f, _| f.currencies = vec!["usd".to_string()]),
+            ("country", true, |f, _| f.countries_of_risk = vec!["ru".to_string()]),
+            ("other country", false, |f, _| f.countries_of_risk = vec!["KZ".to_string()]),
+            ("sector", true, |f, _| f.sectors = vec!["Financial".to_string()]),
+            ("other sector", false, |f, _| f.sectors = vec!["energy".to_string()]),
+            ("qualified investors only", false, |f, _| f.for_qual_investor = Some(true)),
+            ("not for qualified investors", true, |f, _| f.for_qual_investor = Some(false)),
+            ("shortable", true, |f, i| {
+                f.short_enabled = Some(true);
+                i.short_enabled = true;
+            }),
+            ("not shortable", false, |f, _| f.short_enabled = Some(true)),
+            ("turnover is checked separately", true, |f, _| f.min_daily_turnover = Some(f64::MAX)),
+        ];
+
+        for (name, expected, change) in cases {
+            let (mut filter, mut instrument) = (filter(), instrument());
+            change(&mut filter, &mut instrument);
+            assert_eq!(matches(&instrument, &filter), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn turnover_averages_the_last_complete_candles() {
+        let candles = [candle(1000, 1, true), candle(100, 2, true), candle(100, 4, true), candle(100, 99, false)];
+        // (200 + 400) / 2 * lot 10
+        assert_eq!(average_turnover(&candles, 10, 2), 3000.0);
+        assert_eq!(average_turnover(&candles, 1, 20), 1600.0 / 3.0);
+        assert_eq!(average_turnover(&candles[3..], 1, 20), 0.0);
+        assert_eq!(average_turnover(&[], 1, 20), 0.0);
+    }
+
+    #[test]
+    fn turnover_threshold_keeps_the_order_and_skips_unknown_turnovers() {
+        let today = Utc::now().date_naive();
+        let candidate = |uid: &str| CachedInstrument { uid: uid.to_string(), ..instrument() };
+        let mut universe = Universe::new();
+        universe.turnovers.insert("high".to_string(), (today, 3000.0));
+        universe.turnovers.insert("exact".to_string(), (today, 2000.0));
+        universe.turnovers.insert("low".to_string(), (today, 1999.0));
+        // A value of an earlier day is kept when the candles can't be reloaded
+        universe.turnovers.insert("stale".to_string(), (today - Duration::days(3), 5000.0));
+
+        let candidates = ["exact", "low", "unknown", "stale", "high"].map(candidate).to_vec();
+        let selected: Vec<String> = universe.with_turnover(candidates, 2000.0).into_iter().map(|i| i.uid).collect();
+        assert_eq!(selected, ["exact", "stale", "high"]);
+    }
+}
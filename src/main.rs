@@ -1,33 +1,78 @@
+use crate::cli::{Cli, CliCommand, InstrumentsCommand, LogFormat};
 use crate::config::Config;
 use crate::bot::MarketScanner;
+use clap::Parser;
 use tracing::{info, error};
 use tracing_subscriber::EnvFilter;
 use tokio::signal;
 
+mod backtest;
 mod bot;
+mod chart;
+mod cli;
 mod config;
 mod instruments;
 mod models;
+mod notifiers;
+mod operations_service;
 mod market_data_service;
+mod orders_service;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
     // Initialize logging with extended configuration
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| EnvFilter::new("info,better_call_put=debug")),
-        )
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info,better_call_put=debug"));
+    let logger = tracing_subscriber::fmt()
+        .with_env_filter(filter)
         .with_file(true)
         .with_line_number(true)
         .with_thread_ids(true)
-        .with_thread_names(true)
-        .init();
+        .with_thread_names(true);
+    match cli.log_format {
+        LogFormat::Text => logger.init(),
+        LogFormat::Json => logger.json().init(),
+    }
 
     info!("Starting application");
 
-    let config = Config::new("config.yaml")?;
-    let mut scanner = MarketScanner::new(config)?;
+    let command = cli.command.clone().unwrap_or(CliCommand::Run);
+    if let CliCommand::ValidateConfig = command {
+        return cli::tools::validate_config(&cli.config);
+    }
+
+    if matches!(command, CliCommand::ScanOnce { deliver: true }) && cli.dry_run {
+        return Err("--deliver can't be combined with --dry-run".into());
+    }
+
+    let config = Config::new(&cli.config)?;
+    if cli.dry_run {
+        info!("Dry run: signals, alerts and reports are not delivered");
+    }
+
+    match command {
+        CliCommand::Run => run(config, &cli.config, cli.dry_run).await,
+        CliCommand::ScanOnce { deliver } => {
+            // Signals are only printed unless delivery is requested
+            MarketScanner::new(config, &cli.config, !deliver)?.scan_once().await
+        }
+        CliCommand::Backtest => backtest::runner::run(&config).await,
+        CliCommand::Optimize => backtest::runner::optimize(&config).await,
+        CliCommand::Portfolio => backtest::runner::portfolio(&config).await,
+        CliCommand::Instruments { command: None } => cli::tools::instruments(&config).await,
+        CliCommand::Instruments {
+            command: Some(InstrumentsCommand::Search { query, limit }),
+        } => cli::tools::search(&config, &query.join(" "), limit).await,
+        CliCommand::Price { tickers } => cli::tools::price(&config, &tickers).await,
+        CliCommand::ValidateConfig => Ok(()),
+    }
+}
+
+/// Scans the market until Ctrl+C
+async fn run(config: Config, config_path: &str, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut scanner = MarketScanner::new(config, config_path, dry_run)?;
     
     // Create channel for sending termination signal
     let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
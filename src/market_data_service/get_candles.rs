@@ -0,0 +1,217 @@
+use crate::market_data_service::get_tech_analysis::IndicatorInterval;
+use crate::models::structs::Quotation;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info};
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum CandleInterval {
+    #[serde(rename = "CANDLE_INTERVAL_UNSPECIFIED")]
+    Unspecified,
+    #[serde(rename = "CANDLE_INTERVAL_1_MIN")]
+    OneMinute,
+    #[serde(rename = "CANDLE_INTERVAL_2_MIN")]
+    TwoMinutes,
+    #[serde(rename = "CANDLE_INTERVAL_3_MIN")]
+    ThreeMinutes,
+    #[serde(rename = "CANDLE_INTERVAL_5_MIN")]
+    FiveMinutes,
+    #[serde(rename = "CANDLE_INTERVAL_10_MIN")]
+    TenMinutes,
+    #[serde(rename = "CANDLE_INTERVAL_15_MIN")]
+    FifteenMinutes,
+    #[serde(rename = "CANDLE_INTERVAL_30_MIN")]
+    ThirtyMin,
+    #[serde(rename = "CANDLE_INTERVAL_HOUR")]
+    Hour,
+    #[serde(rename = "CANDLE_INTERVAL_2_HOUR")]
+    TwoHours,
+    #[serde(rename = "CANDLE_INTERVAL_4_HOUR")]
+    FourHour,
+    #[serde(rename = "CANDLE_INTERVAL_DAY")]
+    Day,
+    #[serde(rename = "CANDLE_INTERVAL_WEEK")]
+    Week,
+    #[serde(rename = "CANDLE_INTERVAL_MONTH")]
+    Month,
+}
+
+impl From<IndicatorInterval> for CandleInterval {
+    fn from(interval: IndicatorInterval) -> Self {
+        match interval {
+            IndicatorInterval::Unspecified => CandleInterval::Unspecified,
+            IndicatorInterval::OneMinute => CandleInterval::OneMinute,
+            IndicatorInterval::TwoMinutes => CandleInterval::TwoMinutes,
+            IndicatorInterval::ThreeMinutes => CandleInterval::ThreeMinutes,
+            IndicatorInterval::FiveMinutes => CandleInterval::FiveMinutes,
+            IndicatorInterval::TenMinutes => CandleInterval::TenMinutes,
+            IndicatorInterval::FifteenMinutes => CandleInterval::FifteenMinutes,
+            IndicatorInterval::ThirtyMin => CandleInterval::ThirtyMin,
+            IndicatorInterval::Hour => CandleInterval::Hour,
+            IndicatorInterval::TwoHours => CandleInterval::TwoHours,
+            IndicatorInterval::FourHour => CandleInterval::FourHour,
+            IndicatorInterval::Day => CandleInterval::Day,
+            IndicatorInterval::Week => CandleInterval::Week,
+            IndicatorInterval::Month => CandleInterval::Month,
+        }
+    }
+}
+
+impl CandleInterval {
+    /// Maximum period that can be requested in a single GetCandles call
+    pub fn max_request_period(&self) -> Duration {
+        match self {
+            CandleInterval::OneMinute
+            | CandleInterval::TwoMinutes
+            | CandleInterval::ThreeMinutes
+            | CandleInterval::FiveMinutes
+            | CandleInterval::TenMinutes
+            | CandleInterval::FifteenMinutes => Duration::days(1),
+            CandleInterval::ThirtyMin => Duration::days(2),
+            CandleInterval::Hour => Duration::weeks(1),
+            CandleInterval::TwoHours | CandleInterval::FourHour => Duration::days(30),
+            CandleInterval::Day | CandleInterval::Unspecified => Duration::days(365),
+            CandleInterval::Week => Duration::days(2 * 365),
+            CandleInterval::Month => Duration::days(10 * 365),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            CandleInterval::Unspecified => "unspecified",
+            CandleInterval::OneMinute => "1min",
+            CandleInterval::TwoMinutes => "2min",
+            CandleInterval::ThreeMinutes => "3min",
+            CandleInterval::FiveMinutes => "5min",
+            CandleInterval::TenMinutes => "10min",
+            CandleInterval::FifteenMinutes => "15min",
+            CandleInterval::ThirtyMin => "30min",
+            CandleInterval::Hour => "1hour",
+            CandleInterval::TwoHours => "2hour",
+            CandleInterval::FourHour => "4hour",
+            CandleInterval::Day => "1day",
+            CandleInterval::Week => "1week",
+            CandleInterval::Month => "1month",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetCandlesRequest {
+    #[serde(rename = "instrumentId")]
+    pub instrument_id: String,
+    pub from: String,
+    pub to: String,
+    pub interval: CandleInterval,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoricCandle {
+    pub open: Quotation,
+    pub high: Quotation,
+    pub low: Quotation,
+    pub close: Quotation,
+    pub volume: String,
+    pub time: String,
+    #[serde(rename = "isComplete")]
+    pub is_complete: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetCandlesResponse {
+    pub candles: Vec<HistoricCandle>,
+}
+
+impl GetCandlesRequest {
+    pub fn new(
+        instrument_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        interval: CandleInterval,
+    ) -> Self {
+        Self {
+            instrument_id: instrument_id.to_string(),
+            from: from.to_rfc3339(),
+            to: to.to_rfc3339(),
+            interval,
+        }
+    }
+}
+
+impl GetCandlesResponse {
+    pub async fn get_candles(
+        client: &reqwest::Client,
+        token: &str,
+        request: GetCandlesRequest,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let url = "https://invest-public-api.tinkoff.ru/rest/tinkoff.public.invest.api.contract.v1.MarketDataService/GetCandles";
+
+        info!("Sending GetCandles request: {:?}", request);
+        debug!("Request URL: {}", url);
+
+        let response = match client
+            .post(url)
+            .bearer_auth(token)
+            .json(&request)
+            .send()
+            .await
+        {
+            Ok(resp) => {
+                info!("Received response from server, status: {}", resp.status());
+                resp
+            }
+            Err(e) => {
+                error!("Error sending request: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_else(|e| {
+                error!("Unable to read error body: {}", e);
+                "Unknown error".to_string()
+            });
+
+            error!("Request error: status {}, text: {}", status, error_text);
+            return Err(format!("API error: {} - {}", status, error_text).into());
+        }
+
+        match response.json::<Self>().await {
+            Ok(candles_response) => {
+                info!("Successfully received {} candles", candles_response.candles.len());
+                Ok(candles_response)
+            }
+            Err(e) => {
+                error!("Error deserializing response: {}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Gets candles for an arbitrary period by splitting it into
+    /// chunks allowed by the API for the given interval
+    pub async fn get_candles_range(
+        client: &reqwest::Client,
+        token: &str,
+        instrument_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        interval: CandleInterval,
+    ) -> Result<Vec<HistoricCandle>, Box<dyn std::error::Error>> {
+        let step = interval.max_request_period();
+        let mut candles = Vec::new();
+        let mut chunk_from = from;
+
+        while chunk_from < to {
+            let chunk_to = (chunk_from + step).min(to);
+            let request = GetCandlesRequest::new(instrument_id, chunk_from, chunk_to, interval);
+            let response = Self::get_candles(client, token, request).await?;
+            candles.extend(response.candles);
+            chunk_from = chunk_to;
+        }
+
+        Ok(candles)
+    }
+}
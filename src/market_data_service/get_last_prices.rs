@@ -95,8 +95,7 @@ impl GetLastPricesResponse {
         if !response.status().is_success() {
             let error_text = response.text().await?;
             tracing::error!("Server returned error: {}", error_text);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            return Err(Box::new(std::io::Error::other(
                 format!("Server error: {}", error_text)
             )));
         }
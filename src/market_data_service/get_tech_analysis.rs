@@ -1,9 +1,10 @@
 use crate::models::structs::Quotation;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info};
-use chrono;
+use chrono::{DateTime, Months, Utc};
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum IndicatorType {
     #[serde(rename = "INDICATOR_TYPE_UNSPECIFIED")]
     #[default]
@@ -20,7 +21,7 @@ pub enum IndicatorType {
     SMA,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub enum IndicatorInterval {
     #[serde(rename = "INDICATOR_INTERVAL_UNSPECIFIED")]
     Unspecified,
@@ -52,6 +53,32 @@ pub enum IndicatorInterval {
     Month,
 }
 
+impl IndicatorInterval {
+    /// Returns the close time of a candle opened at `open`,
+    /// or None if the interval is unspecified
+    pub fn candle_close(&self, open: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let minutes = match self {
+            IndicatorInterval::Unspecified => return None,
+            IndicatorInterval::OneMinute => 1,
+            IndicatorInterval::TwoMinutes => 2,
+            IndicatorInterval::ThreeMinutes => 3,
+            IndicatorInterval::FiveMinutes => 5,
+            IndicatorInterval::TenMinutes => 10,
+            IndicatorInterval::FifteenMinutes => 15,
+            IndicatorInterval::ThirtyMin => 30,
+            IndicatorInterval::Hour => 60,
+            IndicatorInterval::TwoHours => 2 * 60,
+            IndicatorInterval::FourHour => 4 * 60,
+            IndicatorInterval::Day => 24 * 60,
+            IndicatorInterval::Week => 7 * 24 * 60,
+            // Months have different lengths
+            IndicatorInterval::Month => return open.checked_add_months(Months::new(1)),
+        };
+
+        Some(open + chrono::Duration::minutes(minutes))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub enum TypeOfPrice {
     #[serde(rename = "TYPE_OF_PRICE_UNSPECIFIED")]
@@ -121,6 +148,7 @@ pub struct GetTechAnalysisResponse {
 }
 
 impl GetTechAnalysisRequest {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         indicator_type: IndicatorType,
         instrument_uid: String,
@@ -152,9 +180,21 @@ impl GetTechAnalysisRequest {
         type_of_price: TypeOfPrice,
         length: i32,
     ) -> Self {
-        let now = chrono::Utc::now();
+        Self::new_auto_period(IndicatorType::EMA, instrument_uid, interval, type_of_price, length)
+    }
+
+    /// Creates a request for a single-value indicator such as EMA, SMA or RSI
+    /// with automatic time range calculation
+    pub fn new_auto_period(
+        indicator_type: IndicatorType,
+        instrument_uid: &str,
+        interval: IndicatorInterval,
+        type_of_price: TypeOfPrice,
+        length: i32,
+    ) -> Self {
+        let now = Utc::now();
         
-        // For EMA we need at least 2.5 * length points to form the indicator
+        // At least 2.5 * length points are needed to form the indicator
         // Add an additional 50% for displaying trends
         let required_points = (length as f64 * 3.75).ceil() as i32;
         
@@ -211,11 +251,11 @@ impl GetTechAnalysisRequest {
             .date_naive()
             .and_hms_opt(0, 0, 0)
             .unwrap()
-            .and_local_timezone(chrono::Utc)
+            .and_local_timezone(Utc)
             .unwrap();
         
         Self::new(
-            IndicatorType::EMA,
+            indicator_type,
             instrument_uid.to_string(),
             from.to_rfc3339(),
             now.to_rfc3339(),
@@ -1,3 +1,4 @@
 pub mod get_tech_analysis;
 pub mod get_trading_statuses;
 pub mod get_last_prices;
+pub mod get_candles;
@@ -5,3 +5,24 @@ pub struct Quotation {
     pub units: String,
     pub nano: i32,
 }
+
+impl Quotation {
+    /// Converts quotation to a floating point number
+    pub fn to_f64(&self) -> f64 {
+        self.units.parse::<f64>().unwrap_or(0.0) + (self.nano as f64 / 1_000_000_000.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoneyValue {
+    pub currency: String,
+    pub units: String,
+    pub nano: i32,
+}
+
+impl MoneyValue {
+    /// Converts money value to a floating point number
+    pub fn to_f64(&self) -> f64 {
+        self.units.parse::<f64>().unwrap_or(0.0) + (self.nano as f64 / 1_000_000_000.0)
+    }
+}
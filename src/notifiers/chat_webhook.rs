@@ -0,0 +1,188 @@
+use crate::bot::messages::format_price;
+use crate::bot::report::Report;
+use crate::bot::signal::TradeSignal;
+use crate::notifiers::{signal_name, Notifier, NotifyFuture, NotifyResult, SignalNotification};
+use serde_json::json;
+use tracing::info;
+
+/// Discord rejects messages longer than 2000 characters
+const DISCORD_LENGTH: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatFormat {
+    Slack,
+    Discord,
+}
+
+/// Sends the signals of a scan and reports as messages to a Slack or Discord incoming webhook
+pub struct ChatWebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+    format: ChatFormat,
+}
+
+impl ChatWebhookNotifier {
+    pub fn new(client: reqwest::Client, url: String, format: ChatFormat) -> Self {
+        Self { client, url, format }
+    }
+
+    fn line(&self, signal: &SignalNotification) -> String {
+        let event = &signal.event;
+        let icon = match event.signal {
+            TradeSignal::Buy => "🟢",
+            TradeSignal::Sell => "🔴",
+            TradeSignal::Hold => "⚪",
+        };
+        let ticker = match self.format {
+            ChatFormat::Slack => format!("*{}*", event.ticker),
+            ChatFormat::Discord => format!("**{}**", event.ticker),
+        };
+        format!(
+            "{} {} {} ({}) at {}, EMA difference {:.4}%",
+            icon,
+            signal_name(event.signal).to_uppercase(),
+            ticker,
+            event.strategy,
+            format_price(event.last_price, event.price_increment),
+            event.ema_percentage()
+        )
+    }
+
+    /// Lines grouped into messages within the Discord length limit
+    fn messages(&self, lines: impl Iterator<Item = String>) -> Vec<String> {
+        if self.format == ChatFormat::Slack {
+            return vec![lines.collect::<Vec<_>>().join("\n")];
+        }
+
+        let mut messages = Vec::new();
+        let mut message = String::new();
+        for line in lines {
+            if !message.is_empty() && message.chars().count() + line.chars().count() + 1 > DISCORD_LENGTH {
+                messages.push(std::mem::take(&mut message));
+            }
+            if !message.is_empty() {
+                message.push('\n');
+            }
+            message.push_str(&line);
+        }
+        messages.push(message);
+        messages
+    }
+
+    async fn post(&self, messages: Vec<String>) -> NotifyResult {
+        for message in messages {
+            let body = match self.format {
+                ChatFormat::Slack => json!({ "text": message }),
+                ChatFormat::Discord => json!({ "content": message }),
+            };
+            let response = self.client.post(&self.url).json(&body).send().await?;
+            if !response.status().is_success() {
+                return Err(format!("{} webhook returned {}", self.name(), response.status()).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Notifier for ChatWebhookNotifier {
+    fn name(&self) -> &str {
+        match self.format {
+            ChatFormat::Slack => "slack",
+            ChatFormat::Discord => "discord",
+        }
+    }
+
+    fn send_signals<'a>(&'a self, signals: &'a [SignalNotification]) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            if signals.is_empty() {
+                return Ok(());
+            }
+
+            self.post(self.messages(signals.iter().map(|signal| self.line(signal)))).await?;
+            info!("Sent {} signals to {} webhook", signals.len(), self.name());
+            Ok(())
+        })
+    }
+
+    fn send_report<'a>(&'a self, report: &'a Report) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let text = report.text(None);
+            self.post(self.messages(text.lines().map(str::to_string))).await?;
+            info!("Sent {} to {} webhook", report.title(), self.name());
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifiers::mock::{client, http_server, notification};
+
+    fn signals(count: usize) -> Vec<SignalNotification> {
+        (0..count)
+            .map(|i| notification(&format!("TICKER{:02}", i), if i % 2 == 0 { TradeSignal::Buy } else { TradeSignal::Sell }, None))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn slack_posts_one_text_message() {
+        let (url, requests) = http_server(200).await;
+        let notifier = ChatWebhookNotifier::new(client(), url, ChatFormat::Slack);
+
+        notifier.send_signals(&signals(2)).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({
+                "text": "🟢 BUY *TICKER00* (ema 9/21) at 102.50, EMA difference 1.0000%\n\
+                         🔴 SELL *TICKER01* (ema 9/21) at 102.50, EMA difference 1.0000%"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn discord_splits_at_2000_characters() {
+        let (url, requests) = http_server(204).await;
+        let notifier = ChatWebhookNotifier::new(client(), url, ChatFormat::Discord);
+        let signals = signals(60);
+
+        notifier.send_signals(&signals).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert!(requests.len() > 1);
+        let contents: Vec<String> = requests
+            .iter()
+            .map(|request| {
+                let body = request.json();
+                assert_eq!(body.as_object().unwrap().len(), 1);
+                body["content"].as_str().unwrap().to_string()
+            })
+            .collect();
+        for content in &contents {
+            assert!(content.chars().count() <= DISCORD_LENGTH);
+        }
+        // Messages are split between lines and keep every signal in order
+        let lines: Vec<String> = signals.iter().map(|signal| notifier.line(signal)).collect();
+        assert!(lines[0].starts_with("🟢 BUY **TICKER00**"));
+        assert_eq!(contents.join("\n"), lines.join("\n"));
+    }
+
+    #[tokio::test]
+    async fn no_signals_posts_nothing() {
+        let (url, requests) = http_server(200).await;
+        let notifier = ChatWebhookNotifier::new(client(), url, ChatFormat::Discord);
+
+        notifier.send_signals(&[]).await.unwrap();
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn error_status_fails() {
+        let (url, _) = http_server(400).await;
+        let notifier = ChatWebhookNotifier::new(client(), url, ChatFormat::Slack);
+        assert!(notifier.send_signals(&signals(1)).await.is_err());
+    }
+}
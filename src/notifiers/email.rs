@@ -0,0 +1,149 @@
+use crate::bot::report::Report;
+use crate::config::{EmailConfig, SmtpSecurity};
+use crate::notifiers::{plain_text, signal_name, Notifier, NotifyFuture, NotifyResult, SignalNotification};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::error::Error;
+use tracing::info;
+
+/// Sends the signals of a scan as one email with charts attached, and reports
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn new(config: EmailConfig) -> Result<Self, Box<dyn Error>> {
+        let builder = match config.security {
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)?,
+        };
+        let builder = builder.port(config.smtp_port);
+        let builder = match (config.username, config.password) {
+            (Some(username), Some(password)) => builder.credentials(Credentials::new(username, password.expose().to_string())),
+            _ => builder,
+        };
+
+        Ok(Self {
+            transport: builder.build(),
+            from: config.from.parse()?,
+            to: config.to.iter().map(|to| to.parse()).collect::<Result<_, _>>()?,
+        })
+    }
+
+    async fn send(&self, subject: String, content: MultiPart) -> NotifyResult {
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        self.transport.send(message.multipart(content)?).await?;
+        Ok(())
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn send_signals<'a>(&'a self, signals: &'a [SignalNotification]) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            if signals.is_empty() || self.to.is_empty() {
+                return Ok(());
+            }
+
+            let subject = match signals {
+                [signal] => format!("{} {}", signal_name(signal.event.signal).to_uppercase(), signal.event.ticker),
+                _ => format!("{} trading signals", signals.len()),
+            };
+            let body = signals
+                .iter()
+                .map(|signal| format!("{} {}", signal.time.format("%Y-%m-%d %H:%M UTC"), plain_text(&signal.event)))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let mut content = MultiPart::mixed().singlepart(SinglePart::plain(body));
+            for signal in signals {
+                if let Some(chart) = &signal.chart {
+                    content = content.singlepart(
+                        Attachment::new(format!("{}.png", signal.event.ticker))
+                            .body(chart.to_vec(), ContentType::parse("image/png")?),
+                    );
+                }
+            }
+
+            self.send(subject, content).await?;
+            info!("Emailed {} signals to {} recipients", signals.len(), self.to.len());
+            Ok(())
+        })
+    }
+
+    fn send_report<'a>(&'a self, report: &'a Report) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            if self.to.is_empty() {
+                return Ok(());
+            }
+
+            let content = MultiPart::mixed().singlepart(SinglePart::plain(report.text(None)));
+            self.send(report.title(), content).await?;
+            info!("Emailed {} to {} recipients", report.title(), self.to.len());
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::signal::TradeSignal;
+    use crate::notifiers::mock::{notification, smtp_server};
+
+    fn notifier(port: u16) -> EmailNotifier {
+        let config: EmailConfig = serde_yaml::from_str(&format!(
+            "smtp_host: 127.0.0.1\nsmtp_port: {}\nsecurity: none\nfrom: bot@example.com\nto: [a@example.com, b@example.com]",
+            port
+        ))
+        .unwrap();
+        EmailNotifier::new(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn sends_one_multipart_email_per_scan() {
+        let (port, messages) = smtp_server().await;
+        let signals = [
+            notification("SBER", TradeSignal::Buy, Some(b"\x89PNG chart".to_vec())),
+            notification("GAZP", TradeSignal::Sell, None),
+        ];
+
+        notifier(port).send_signals(&signals).await.unwrap();
+
+        let messages = messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        let message = &messages[0];
+        assert!(message.contains("Subject: 2 trading signals"));
+        assert!(message.contains("To: a@example.com, b@example.com"));
+        assert!(message.contains("Content-Type: multipart/mixed"));
+        assert!(message.contains("BUY SBER (ema 9/21) at 102.50"));
+        assert!(message.contains("SELL GAZP (ema 9/21) at 102.50"));
+        assert!(message.contains("filename=\"SBER.png\""));
+        // Base64 of the chart bytes
+        assert!(message.contains("iVBORyBjaGFydA=="));
+        assert!(!message.contains("GAZP.png"));
+    }
+
+    #[tokio::test]
+    async fn single_signal_subject() {
+        let (port, messages) = smtp_server().await;
+
+        notifier(port).send_signals(&[notification("SBER", TradeSignal::Buy, None)]).await.unwrap();
+        notifier(port).send_signals(&[]).await.unwrap();
+
+        let messages = messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("Subject: BUY SBER"));
+    }
+}
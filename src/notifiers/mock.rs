@@ -0,0 +1,198 @@
+//! Local HTTP and SMTP servers standing in for webhooks, the T-Invest API and mail relays in tests
+
+use crate::bot::signal::{SignalEvent, TradeSignal};
+use crate::notifiers::SignalNotification;
+use chrono::{TimeZone, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// Request received by the HTTP server
+#[derive(Debug)]
+pub struct HttpRequest {
+    /// Path of the request line, e.g. `/hook`
+    pub path: String,
+    /// Header names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+}
+
+/// Response of `http_responder`
+pub struct HttpReply {
+    pub status: u16,
+    pub body: String,
+    /// Wait before the response is written
+    pub delay: Duration,
+}
+
+impl HttpReply {
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            body: String::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn json(body: serde_json::Value) -> Self {
+        Self {
+            body: body.to_string(),
+            ..Self::status(200)
+        }
+    }
+
+    pub fn after(self, delay: Duration) -> Self {
+        Self { delay, ..self }
+    }
+}
+
+/// Answers every request with `status`, returns the URL and the requests received
+pub async fn http_server(status: u16) -> (String, Arc<Mutex<Vec<HttpRequest>>>) {
+    http_responder(move |_| HttpReply::status(status)).await
+}
+
+/// Answers each request with the reply of `respond`, returns the URL and the requests received
+pub async fn http_responder(
+    respond: impl Fn(&HttpRequest) -> HttpReply + Send + Sync + 'static,
+) -> (String, Arc<Mutex<Vec<HttpRequest>>>) {
+    let respond = Arc::new(respond);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let received = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let received = received.clone();
+            let respond = respond.clone();
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                // Keep-alive connections carry several requests
+                loop {
+                    let mut line = String::new();
+                    if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    let path = line.split(' ').nth(1).unwrap_or_default().to_string();
+
+                    let mut headers = Vec::new();
+                    loop {
+                        line.clear();
+                        stream.read_line(&mut line).await.unwrap();
+                        let Some((name, value)) = line.trim_end().split_once(':') else {
+                            break;
+                        };
+                        headers.push((name.to_lowercase(), value.trim().to_string()));
+                    }
+
+                    let length = headers
+                        .iter()
+                        .find(|(name, _)| name == "content-length")
+                        .map_or(0, |(_, value)| value.parse().unwrap());
+                    let mut body = vec![0; length];
+                    stream.read_exact(&mut body).await.unwrap();
+                    let request = HttpRequest { path, headers, body };
+                    let reply = respond(&request);
+                    received.lock().unwrap().push(request);
+
+                    tokio::time::sleep(reply.delay).await;
+                    let response = format!(
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        reply.status,
+                        reply.body.len(),
+                        reply.body
+                    );
+                    stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+                }
+            });
+        }
+    });
+
+    (url, requests)
+}
+
+/// Plain SMTP server without authentication, returns the port and the DATA of each message
+pub async fn smtp_server() -> (u16, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let messages = Arc::new(Mutex::new(Vec::new()));
+
+    let received = messages.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let received = received.clone();
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                stream.get_mut().write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+
+                    let command = line.get(..4).unwrap_or_default().to_uppercase();
+                    let reply: &[u8] = match command.as_str() {
+                        "EHLO" | "HELO" => b"250-localhost\r\n250 8BITMIME\r\n",
+                        "DATA" => {
+                            stream.get_mut().write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await.unwrap();
+                            let mut data = String::new();
+                            loop {
+                                line.clear();
+                                stream.read_line(&mut line).await.unwrap();
+                                if line == ".\r\n" {
+                                    break;
+                                }
+                                data.push_str(&line);
+                            }
+                            received.lock().unwrap().push(data);
+                            b"250 Queued\r\n"
+                        }
+                        "QUIT" => {
+                            stream.get_mut().write_all(b"221 Bye\r\n").await.unwrap();
+                            return;
+                        }
+                        _ => b"250 OK\r\n",
+                    };
+                    stream.get_mut().write_all(reply).await.unwrap();
+                }
+            });
+        }
+    });
+
+    (port, messages)
+}
+
+/// Client ignoring proxy settings of the environment
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder().no_proxy().build().unwrap()
+}
+
+pub fn notification(ticker: &str, signal: TradeSignal, chart: Option<Vec<u8>>) -> SignalNotification {
+    SignalNotification {
+        event: SignalEvent {
+            ticker: ticker.to_string(),
+            instrument_uid: format!("{}-uid", ticker.to_lowercase()),
+            strategy: "ema 9/21".to_string(),
+            signal,
+            short_ema: 101.0,
+            long_ema: 100.0,
+            last_price: 102.5,
+            price_increment: Some(0.01),
+        },
+        chart: chart.map(Arc::new),
+        time: Utc.with_ymd_and_hms(2024, 3, 1, 7, 0, 0).unwrap(),
+    }
+}
@@ -0,0 +1,144 @@
+pub mod chat_webhook;
+pub mod email;
+#[cfg(test)]
+pub mod mock;
+pub mod webhook;
+
+use crate::bot::messages::format_price;
+use crate::bot::report::Report;
+use crate::bot::signal::{SignalEvent, TradeSignal};
+use crate::config::NotifiersConfig;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub type NotifyResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+pub type NotifyFuture<'a> = Pin<Box<dyn Future<Output = NotifyResult> + Send + 'a>>;
+
+/// Buy or Sell signal delivered to notifiers at the end of a scan
+#[derive(Debug, Clone)]
+pub struct SignalNotification {
+    pub event: SignalEvent,
+    pub chart: Option<Arc<Vec<u8>>>,
+    pub time: DateTime<Utc>,
+}
+
+/// Destination of trading signals
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Delivers all signals of one scan
+    fn send_signals<'a>(&'a self, signals: &'a [SignalNotification]) -> NotifyFuture<'a>;
+
+    /// Delivers a scheduled digest report
+    fn send_report<'a>(&'a self, report: &'a Report) -> NotifyFuture<'a>;
+}
+
+/// JSON representation of a signal for webhooks
+#[derive(Debug, Serialize)]
+pub struct SignalPayload<'a> {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub ticker: &'a str,
+    pub instrument_uid: &'a str,
+    pub strategy: &'a str,
+    pub signal: &'static str,
+    pub last_price: f64,
+    pub short_ema: f64,
+    pub long_ema: f64,
+    pub difference_percentage: f64,
+    pub time: DateTime<Utc>,
+}
+
+impl<'a> SignalPayload<'a> {
+    pub fn new(notification: &'a SignalNotification) -> Self {
+        let event = &notification.event;
+        Self {
+            kind: "signal",
+            ticker: &event.ticker,
+            instrument_uid: &event.instrument_uid,
+            strategy: &event.strategy,
+            signal: signal_name(event.signal),
+            last_price: event.last_price,
+            short_ema: event.short_ema,
+            long_ema: event.long_ema,
+            difference_percentage: event.ema_percentage(),
+            time: notification.time,
+        }
+    }
+}
+
+/// JSON representation of a report for webhooks
+#[derive(Debug, Serialize)]
+pub struct ReportPayload<'a> {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub title: String,
+    #[serde(flatten)]
+    pub report: &'a Report,
+}
+
+impl<'a> ReportPayload<'a> {
+    pub fn new(report: &'a Report) -> Self {
+        Self {
+            kind: "report",
+            title: report.title(),
+            report,
+        }
+    }
+}
+
+pub fn signal_name(signal: TradeSignal) -> &'static str {
+    match signal {
+        TradeSignal::Buy => "buy",
+        TradeSignal::Sell => "sell",
+        TradeSignal::Hold => "hold",
+    }
+}
+
+/// One line plain text description of a signal
+pub fn plain_text(event: &SignalEvent) -> String {
+    format!(
+        "{} {} ({}) at {}, short EMA {:.4}, long EMA {:.4}, difference {:.4}%",
+        signal_name(event.signal).to_uppercase(),
+        event.ticker,
+        event.strategy,
+        format_price(event.last_price, event.price_increment),
+        event.short_ema,
+        event.long_ema,
+        event.ema_percentage()
+    )
+}
+
+/// Builds the notifiers enabled in config besides Telegram
+pub fn from_config(
+    client: &reqwest::Client,
+    config: &NotifiersConfig,
+) -> Result<Vec<Box<dyn Notifier>>, Box<dyn std::error::Error>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+
+    for webhook in &config.webhooks {
+        notifiers.push(Box::new(webhook::WebhookNotifier::new(client.clone(), webhook.clone())));
+    }
+    for url in &config.slack {
+        notifiers.push(Box::new(chat_webhook::ChatWebhookNotifier::new(
+            client.clone(),
+            url.clone(),
+            chat_webhook::ChatFormat::Slack,
+        )));
+    }
+    for url in &config.discord {
+        notifiers.push(Box::new(chat_webhook::ChatWebhookNotifier::new(
+            client.clone(),
+            url.clone(),
+            chat_webhook::ChatFormat::Discord,
+        )));
+    }
+    if let Some(email) = &config.email {
+        notifiers.push(Box::new(email::EmailNotifier::new(email.clone())?));
+    }
+
+    Ok(notifiers)
+}
@@ -0,0 +1,117 @@
+use crate::bot::report::Report;
+use crate::config::WebhookConfig;
+use crate::notifiers::{Notifier, NotifyFuture, NotifyResult, ReportPayload, SignalNotification, SignalPayload};
+use serde::Serialize;
+use tracing::info;
+
+/// Posts every signal and report as JSON to an HTTP endpoint
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+impl WebhookNotifier {
+    pub fn new(client: reqwest::Client, config: WebhookConfig) -> Self {
+        Self { client, config }
+    }
+
+    async fn post(&self, payload: &impl Serialize) -> NotifyResult {
+        let mut request = self.client.post(&self.config.url).json(payload);
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value.expose());
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(format!("Webhook {} returned {}", self.config.url, response.status()).into());
+        }
+        Ok(())
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn send_signals<'a>(&'a self, signals: &'a [SignalNotification]) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            // A failed post doesn't keep the remaining signals from being sent
+            let mut errors = Vec::new();
+            for signal in signals {
+                if let Err(e) = self.post(&SignalPayload::new(signal)).await {
+                    errors.push(format!("{}: {}", signal.event.ticker, e));
+                }
+            }
+            info!("Sent {} of {} signals to webhook {}", signals.len() - errors.len(), signals.len(), self.config.url);
+
+            if !errors.is_empty() {
+                return Err(format!("{} of {} signals failed: {}", errors.len(), signals.len(), errors.join("; ")).into());
+            }
+            Ok(())
+        })
+    }
+
+    fn send_report<'a>(&'a self, report: &'a Report) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            self.post(&ReportPayload::new(report)).await?;
+            info!("Sent {} to webhook {}", report.title(), self.config.url);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::signal::TradeSignal;
+    use crate::notifiers::mock::{client, http_server, notification};
+
+    fn notifier(url: String) -> WebhookNotifier {
+        let config: WebhookConfig =
+            serde_yaml::from_str(&format!("url: {}\nheaders:\n  Authorization: Bearer secret\n  X-Source: bcp", url)).unwrap();
+        WebhookNotifier::new(client(), config)
+    }
+
+    #[tokio::test]
+    async fn posts_each_signal_as_json_with_headers() {
+        let (url, requests) = http_server(200).await;
+        let signals = [notification("SBER", TradeSignal::Buy, None), notification("GAZP", TradeSignal::Sell, None)];
+
+        notifier(url).send_signals(&signals).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("authorization"), Some("Bearer secret"));
+        assert_eq!(requests[0].header("x-source"), Some("bcp"));
+        assert_eq!(requests[0].header("content-type"), Some("application/json"));
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({
+                "type": "signal",
+                "ticker": "SBER",
+                "instrument_uid": "sber-uid",
+                "strategy": "ema 9/21",
+                "signal": "buy",
+                "last_price": 102.5,
+                "short_ema": 101.0,
+                "long_ema": 100.0,
+                "difference_percentage": 1.0,
+                "time": "2024-03-01T07:00:00Z",
+            })
+        );
+        assert_eq!(requests[1].json()["signal"], "sell");
+        assert_eq!(requests[1].header("authorization"), Some("Bearer secret"));
+    }
+
+    #[tokio::test]
+    async fn error_status_fails_after_posting_every_signal() {
+        let (url, requests) = http_server(500).await;
+        let signals = [notification("SBER", TradeSignal::Buy, None), notification("GAZP", TradeSignal::Sell, None)];
+
+        let err = notifier(url).send_signals(&signals).await.unwrap_err().to_string();
+        assert!(err.starts_with("2 of 2 signals failed: SBER: "), "{}", err);
+        assert!(err.contains("; GAZP: "), "{}", err);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+}
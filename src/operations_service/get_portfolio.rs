@@ -0,0 +1,79 @@
+use crate::models::structs::{MoneyValue, Quotation};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPortfolioRequest {
+    pub account_id: String,
+    /// Currency of the portfolio totals
+    pub currency: String,
+}
+
+impl GetPortfolioRequest {
+    pub fn new(account_id: &str) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            currency: "RUB".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortfolioPosition {
+    pub figi: String,
+    #[serde(default)]
+    pub instrument_uid: String,
+    pub instrument_type: String,
+    pub quantity: Quotation,
+    pub average_position_price: Option<MoneyValue>,
+    /// Current P&L of the position in its currency
+    pub expected_yield: Option<Quotation>,
+    pub current_price: Option<MoneyValue>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPortfolioResponse {
+    pub total_amount_portfolio: Option<MoneyValue>,
+    /// Relative P&L of the portfolio in percent
+    pub expected_yield: Option<Quotation>,
+    #[serde(default)]
+    pub positions: Vec<PortfolioPosition>,
+}
+
+impl GetPortfolioResponse {
+    pub async fn get_portfolio(
+        client: &reqwest::Client,
+        token: &str,
+        request: GetPortfolioRequest,
+        sandbox: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let url = if sandbox {
+            "https://sandbox-invest-public-api.tinkoff.ru/rest/tinkoff.public.invest.api.contract.v1.SandboxService/GetSandboxPortfolio"
+        } else {
+            "https://invest-public-api.tinkoff.ru/rest/tinkoff.public.invest.api.contract.v1.OperationsService/GetPortfolio"
+        };
+
+        let response = client
+            .post(url)
+            .bearer_auth(token)
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Server returned error: {}", error_text);
+            return Err(Box::new(std::io::Error::other(
+                format!("Server error: {}", error_text)
+            )));
+        }
+
+        let response: GetPortfolioResponse = response.json().await?;
+        info!("Received portfolio with {} positions", response.positions.len());
+
+        Ok(response)
+    }
+}
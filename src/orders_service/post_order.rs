@@ -0,0 +1,144 @@
+use crate::models::structs::MoneyValue;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::{error, info};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum OrderDirection {
+    #[serde(rename = "ORDER_DIRECTION_UNSPECIFIED")]
+    Unspecified,
+    #[serde(rename = "ORDER_DIRECTION_BUY")]
+    Buy,
+    #[serde(rename = "ORDER_DIRECTION_SELL")]
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum OrderType {
+    #[serde(rename = "ORDER_TYPE_UNSPECIFIED")]
+    Unspecified,
+    #[serde(rename = "ORDER_TYPE_LIMIT")]
+    Limit,
+    #[serde(rename = "ORDER_TYPE_MARKET")]
+    Market,
+    #[serde(rename = "ORDER_TYPE_BESTPRICE")]
+    BestPrice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum ExecutionReportStatus {
+    #[serde(rename = "EXECUTION_REPORT_STATUS_UNSPECIFIED")]
+    Unspecified,
+    #[serde(rename = "EXECUTION_REPORT_STATUS_FILL")]
+    Fill,
+    #[serde(rename = "EXECUTION_REPORT_STATUS_REJECTED")]
+    Rejected,
+    #[serde(rename = "EXECUTION_REPORT_STATUS_CANCELLED")]
+    Cancelled,
+    #[serde(rename = "EXECUTION_REPORT_STATUS_NEW")]
+    New,
+    #[serde(rename = "EXECUTION_REPORT_STATUS_PARTIALLYFILL")]
+    PartiallyFill,
+}
+
+impl fmt::Display for ExecutionReportStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionReportStatus::Unspecified => write!(f, "Unspecified"),
+            ExecutionReportStatus::Fill => write!(f, "Filled"),
+            ExecutionReportStatus::Rejected => write!(f, "Rejected"),
+            ExecutionReportStatus::Cancelled => write!(f, "Cancelled"),
+            ExecutionReportStatus::New => write!(f, "New"),
+            ExecutionReportStatus::PartiallyFill => write!(f, "Partially filled"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostOrderRequest {
+    pub instrument_id: String,
+    /// Number of lots
+    pub quantity: String,
+    pub direction: OrderDirection,
+    pub account_id: String,
+    pub order_type: OrderType,
+    /// Idempotency key, repeated requests with the same key don't create new orders
+    pub order_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostOrderResponse {
+    pub order_id: String,
+    pub execution_report_status: ExecutionReportStatus,
+    pub lots_requested: String,
+    pub lots_executed: String,
+    pub initial_order_price: Option<MoneyValue>,
+    pub executed_order_price: Option<MoneyValue>,
+    pub total_order_amount: Option<MoneyValue>,
+    pub executed_commission: Option<MoneyValue>,
+    pub direction: OrderDirection,
+    pub instrument_uid: Option<String>,
+    pub message: Option<String>,
+}
+
+impl PostOrderRequest {
+    pub fn new_market(
+        instrument_id: &str,
+        lots: i64,
+        direction: OrderDirection,
+        account_id: &str,
+        order_id: &str,
+    ) -> Self {
+        Self {
+            instrument_id: instrument_id.to_string(),
+            quantity: lots.to_string(),
+            direction,
+            account_id: account_id.to_string(),
+            order_type: OrderType::Market,
+            order_id: order_id.to_string(),
+        }
+    }
+}
+
+impl PostOrderResponse {
+    /// Places an order, to the sandbox account if `sandbox` is set
+    pub async fn post_order(
+        client: &reqwest::Client,
+        token: &str,
+        request: PostOrderRequest,
+        sandbox: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let url = if sandbox {
+            "https://sandbox-invest-public-api.tinkoff.ru/rest/tinkoff.public.invest.api.contract.v1.SandboxService/PostSandboxOrder"
+        } else {
+            "https://invest-public-api.tinkoff.ru/rest/tinkoff.public.invest.api.contract.v1.OrdersService/PostOrder"
+        };
+
+        info!("Posting order: {:?}", request);
+
+        let response = client
+            .post(url)
+            .bearer_auth(token)
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Server returned error: {}", error_text);
+            return Err(Box::new(std::io::Error::other(
+                format!("Server error: {}", error_text)
+            )));
+        }
+
+        let response: PostOrderResponse = response.json().await?;
+        info!(
+            "Order {} {}: {}/{} lots executed",
+            response.order_id, response.execution_report_status, response.lots_executed, response.lots_requested
+        );
+
+        Ok(response)
+    }
+}